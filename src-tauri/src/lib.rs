@@ -1,797 +1,7175 @@
 use std::path::PathBuf;
-use ffmpeg_sidecar::command::FfmpegCommand;
-use ffmpeg_sidecar::download::auto_download;
-use ffmpeg_sidecar::event::FfmpegEvent;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use ffmpeg_sidecar::child::FfmpegChild;
+use ffmpeg_sidecar::command::{ffmpeg_is_installed, FfmpegCommand};
+use ffmpeg_sidecar::download::{auto_download, ffmpeg_download_url, unpack_ffmpeg};
+use ffmpeg_sidecar::event::{FfmpegEvent, FfmpegProgress, LogLevel};
+use ffmpeg_sidecar::ffprobe::ffprobe_path;
+use ffmpeg_sidecar::paths::{ffmpeg_path, sidecar_dir, sidecar_path};
 use serde::{Deserialize, Serialize};
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
 use tauri::menu::{MenuBuilder, SubmenuBuilder, MenuItemBuilder};
 use tauri_plugin_dialog::DialogExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
-#[derive(Serialize, Deserialize)]
-struct VimeoUploadResponse {
-    link: String,
+// Error returned by every command. It reaches the frontend as an object with a `kind`
+// discriminant, a human readable `message` and the variant's fields, e.g.
+// {"kind": "file_not_found", "message": "File not found: /a.mp3", "path": "/a.mp3"}
+#[derive(Debug, Clone)]
+enum WavecastError {
+    FileNotFound { path: String },
+    FfmpegFailed { exit_code: Option<i32>, stderr_tail: String },
+    VimeoApi { status: u16, body: String },
+    InvalidInput { field: String, reason: String },
+    // The user stopped a running export
+    Cancelled,
+    // The user closed a save or open dialog without picking a file
+    DialogCancelled,
+    Other { message: String },
 }
 
-#[derive(Clone, Serialize)]
-struct ExportProgress {
-    frame: u32,
-    fps: f32,
-    time: String,
-    progress: f64,
-}
+impl WavecastError {
+    fn kind(&self) -> &'static str {
+        match self {
+            WavecastError::FileNotFound { .. } => "file_not_found",
+            WavecastError::FfmpegFailed { .. } => "ffmpeg_failed",
+            WavecastError::VimeoApi { .. } => "vimeo_api",
+            WavecastError::InvalidInput { .. } => "invalid_input",
+            WavecastError::Cancelled => "cancelled",
+            WavecastError::DialogCancelled => "dialog_cancelled",
+            WavecastError::Other { .. } => "other",
+        }
+    }
 
-// Timeline-based structures
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct TimelineClip {
-    source_file: String,
-    start_time: f64,
-    duration: f64,
-    trim_start: f64,
-    trim_end: f64,
+    fn invalid(field: &str, reason: impl Into<String>) -> Self {
+        WavecastError::InvalidInput { field: field.to_string(), reason: reason.into() }
+    }
+
+    fn ffmpeg_failed(exit_code: Option<i32>, stderr_tail: impl Into<String>) -> Self {
+        let err = WavecastError::FfmpegFailed { exit_code, stderr_tail: stderr_tail.into() };
+        eprintln!("ERROR: {}", err);
+        err
+    }
+
+    // Read the status and body of a failed Vimeo API response
+    async fn vimeo(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        let err = WavecastError::VimeoApi { status, body };
+        eprintln!("ERROR: {}", err);
+        err
+    }
 }
 
-// Internal structure with track volume
-#[derive(Debug, Clone)]
-struct ClipWithVolume {
-    clip: TimelineClip,
-    track_volume: f64,
+impl std::fmt::Display for WavecastError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WavecastError::FileNotFound { path } => write!(f, "File not found: {}", path),
+            WavecastError::FfmpegFailed { exit_code, stderr_tail } => {
+                match exit_code {
+                    Some(code) => write!(f, "FFmpeg failed (exit code {})", code)?,
+                    None => write!(f, "FFmpeg failed")?,
+                }
+                match stderr_tail.lines().rev().find(|line| !line.trim().is_empty()) {
+                    Some(last_line) => write!(f, ": {}", last_line.trim()),
+                    None => Ok(()),
+                }
+            }
+            WavecastError::VimeoApi { status: 401, .. } => write!(
+                f,
+                "Vimeo rejected the access token: it is invalid, expired or was revoked. Please set a new token in Settings."
+            ),
+            WavecastError::VimeoApi { status: 403, .. } => write!(
+                f,
+                "The Vimeo access token is missing a required scope. Generate a token with the \"upload\" and \"edit\" scopes and set it in Settings."
+            ),
+            WavecastError::VimeoApi { body, .. } => write!(f, "Vimeo API error: {}", body),
+            WavecastError::InvalidInput { reason, .. } => write!(f, "{}", reason),
+            WavecastError::Cancelled => write!(f, "Export cancelled"),
+            WavecastError::DialogCancelled => write!(f, "Cancelled"),
+            WavecastError::Other { message } => write!(f, "{}", message),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct TimelineTrack {
-    clips: Vec<TimelineClip>,
-    volume: f64,
+impl Serialize for WavecastError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            WavecastError::FileNotFound { path } => map.serialize_entry("path", path)?,
+            WavecastError::FfmpegFailed { exit_code, stderr_tail } => {
+                map.serialize_entry("exit_code", exit_code)?;
+                map.serialize_entry("stderr_tail", stderr_tail)?;
+            }
+            WavecastError::VimeoApi { status, body } => {
+                map.serialize_entry("status", status)?;
+                map.serialize_entry("body", body)?;
+            }
+            WavecastError::InvalidInput { field, reason } => {
+                map.serialize_entry("field", field)?;
+                map.serialize_entry("reason", reason)?;
+            }
+            WavecastError::Cancelled | WavecastError::DialogCancelled | WavecastError::Other { .. } => {}
+        }
+        map.end()
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct TimelineData {
-    tracks: Vec<TimelineTrack>,
+// Helpers keep returning plain String errors; they surface as `other`
+impl From<String> for WavecastError {
+    fn from(message: String) -> Self {
+        WavecastError::Other { message }
+    }
 }
 
-// Project data structure for export/import
-#[derive(Serialize, Deserialize, Debug)]
-struct ProjectClip {
-    id: String,
-    source_file: String,
-    source_name: String,
-    track_id: String,
-    start_time: f64,
-    duration: f64,
-    trim_start: f64,
-    trim_end: f64,
-    source_duration: f64,
+impl From<&str> for WavecastError {
+    fn from(message: &str) -> Self {
+        WavecastError::Other { message: message.to_string() }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct ProjectTrack {
-    id: String,
-    track_type: String,
-    name: String,
-    clips: Vec<ProjectClip>,
-    volume: f64,
-    muted: bool,
+impl From<WavecastError> for String {
+    fn from(err: WavecastError) -> Self {
+        err.to_string()
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct ProjectData {
-    version: String,
-    background_image: Option<String>,
-    background_color: Option<String>,
-    background_type: String, // "image" or "color"
-    background_style: String,
-    tracks: Vec<ProjectTrack>,
-    video_title: String,
-    video_description: String,
+// FileNotFound unless `path` is an existing file
+fn require_file(path: &str) -> Result<(), WavecastError> {
+    if std::path::Path::new(path).is_file() {
+        Ok(())
+    } else {
+        eprintln!("ERROR: File not found: {}", path);
+        Err(WavecastError::FileNotFound { path: path.to_string() })
+    }
 }
 
-fn parse_time_to_seconds(time_str: &str) -> f64 {
-    // Parse FFmpeg time format (HH:MM:SS.ms or just seconds)
-    let parts: Vec<&str> = time_str.split(':').collect();
+// What the frontend gets back once a video is on Vimeo
+#[derive(Clone, Serialize, Deserialize)]
+struct VimeoUploadResult {
+    link: String,
+    uri: String,
+    manage_url: String,
+    embed_html: String,
+}
 
-    match parts.len() {
-        1 => {
-            // Just seconds (e.g., "123.45")
-            time_str.parse::<f64>().unwrap_or(0.0)
-        }
-        3 => {
-            // HH:MM:SS.ms format
-            let hours: f64 = parts[0].parse().unwrap_or(0.0);
-            let minutes: f64 = parts[1].parse().unwrap_or(0.0);
-            let seconds: f64 = parts[2].parse().unwrap_or(0.0);
-            hours * 3600.0 + minutes * 60.0 + seconds
-        }
-        _ => 0.0
-    }
+// Shaped like ExportProgress so the frontend can drive a progress bar the same way
+#[derive(Clone, Serialize)]
+struct UploadProgress {
+    bytes_sent: u64,
+    total_bytes: u64,
+    progress: f64,
 }
 
-fn generate_filter_complex(clips: &[ClipWithVolume], unique_sources: &[String], main_volume: f64, has_bg_music: bool) -> String {
-    if clips.is_empty() {
-        return String::new();
-    }
+// Payload of the upload-retrying event, sent before waiting out a transient failure
+#[derive(Clone, Serialize)]
+struct UploadRetry {
+    reason: String,
+    attempt: u32,
+    max_attempts: u32,
+    delay_seconds: u64,
+}
 
-    let mut filter_parts = Vec::new();
+// Longest Retry-After we are willing to sleep through
+const MAX_RETRY_AFTER_SECS: u64 = 300;
 
-    for (i, clip_with_vol) in clips.iter().enumerate() {
-        let clip = &clip_with_vol.clip;
-        let track_vol = clip_with_vol.track_volume;
+// Seconds from a Retry-After header (the delta-seconds form; dates are ignored)
+fn retry_after(response: &reqwest::Response) -> Option<std::time::Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|secs| std::time::Duration::from_secs(secs.min(MAX_RETRY_AFTER_SECS)))
+}
 
-        // Find the input index for this clip's source file
-        // Offset by 1 for the image input (always at index 0)
-        // If background music exists, offset by an additional 1 (bg music at index 1)
-        let base_offset = if has_bg_music { 2 } else { 1 };
-        let input_idx = unique_sources.iter().position(|s| s == &clip.source_file).unwrap() + base_offset;
+fn emit_upload_retrying(app: &tauri::AppHandle, reason: &str, attempt: u32, max_attempts: u32, delay: std::time::Duration) {
+    let _ = app.emit("upload-retrying", UploadRetry {
+        reason: reason.to_string(),
+        attempt,
+        max_attempts,
+        delay_seconds: delay.as_secs(),
+    });
+}
 
-        eprintln!("  Clip {}: source '{}' -> FFmpeg input index {}, track volume: {}", i, clip.source_file, input_idx, track_vol);
+// Default size of each TUS PATCH request, and how often progress is reported within one
+const DEFAULT_UPLOAD_CHUNK_MB: u64 = 50;
+const UPLOAD_PROGRESS_STEP: u64 = 256 * 1024;
+const DEFAULT_UPLOAD_RETRIES: u32 = 5;
 
-        // Create filter for each clip: trim, adjust timing, delay to position, apply track volume
-        let trim_end = clip.duration + clip.trim_start;
-        let delay_ms = (clip.start_time * 1000.0) as i64;
+// How an upload is split up and how hard it tries when the connection drops
+#[derive(Debug, Clone, Copy)]
+struct TusSettings {
+    chunk_size: u64,
+    max_retries: u32,
+}
 
-        // Apply track volume to each clip individually
-        filter_parts.push(format!(
-            "[{}:a]atrim=start={}:end={},asetpts=PTS-STARTPTS,volume={},adelay={}|{}[a{}]",
-            input_idx, clip.trim_start, trim_end, track_vol, delay_ms, delay_ms, i
-        ));
+impl TusSettings {
+    fn new(chunk_size_mb: Option<u64>, max_retries: Option<u32>) -> Result<Self, WavecastError> {
+        let chunk_size_mb = chunk_size_mb.unwrap_or(DEFAULT_UPLOAD_CHUNK_MB);
+        if !(1..=1024).contains(&chunk_size_mb) {
+            return Err(WavecastError::invalid(
+                "chunk_size_mb",
+                format!("Invalid upload chunk size {}MB: must be between 1 and 1024", chunk_size_mb),
+            ));
+        }
+        let max_retries = max_retries.unwrap_or(DEFAULT_UPLOAD_RETRIES);
+        if max_retries > 20 {
+            return Err(WavecastError::invalid(
+                "max_retries",
+                format!("Invalid upload retry count {}: must be 20 or fewer", max_retries),
+            ));
+        }
+        Ok(TusSettings {
+            chunk_size: chunk_size_mb * 1024 * 1024,
+            max_retries,
+        })
     }
+}
 
-    // Mix all audio streams
-    let stream_labels: Vec<String> = (0..clips.len()).map(|i| format!("[a{}]", i)).collect();
-    filter_parts.push(format!(
-        "{}amix=inputs={}:duration=longest,volume={}[aout]",
-        stream_labels.join(""),
-        clips.len(),
-        main_volume
-    ));
-
-    filter_parts.join(";")
+// Optional metadata sent along when a video is created on Vimeo
+struct VimeoVideoDetails {
+    description: Option<String>,
+    privacy: Option<String>,
+    password: Option<String>,
+    tags: Vec<String>,
+    // Who can comment: anybody, contacts or nobody
+    comments: Option<String>,
+    // Where the video can be embedded: public (anywhere) or private (nowhere)
+    embed: Option<String>,
+    // Image set as the video's thumbnail once the upload finishes
+    thumbnail_path: Option<String>,
+    // Folder ("/users/1/projects/2" or just "2") and showcase to file the video in
+    folder_uri: Option<String>,
+    showcase_id: Option<String>,
 }
 
-#[tauri::command]
-fn create_solid_color_image(color: String, width: u32, height: u32) -> Result<String, String> {
-    // Parse hex color
-    let color_str = color.trim_start_matches('#');
-    let r = u8::from_str_radix(&color_str[0..2], 16).map_err(|e| format!("Invalid color: {}", e))?;
-    let g = u8::from_str_radix(&color_str[2..4], 16).map_err(|e| format!("Invalid color: {}", e))?;
-    let b = u8::from_str_radix(&color_str[4..6], 16).map_err(|e| format!("Invalid color: {}", e))?;
+impl VimeoVideoDetails {
+    fn validate(&self) -> Result<(), WavecastError> {
+        if let Some(ref folder) = self.folder_uri {
+            vimeo_resource_id(folder, "folder").map_err(|reason| WavecastError::invalid("folder_uri", reason))?;
+        }
+        if let Some(ref showcase) = self.showcase_id {
+            vimeo_resource_id(showcase, "showcase").map_err(|reason| WavecastError::invalid("showcase_id", reason))?;
+        }
+        if let Some(ref thumbnail) = self.thumbnail_path {
+            thumbnail_content_type(thumbnail).map_err(|reason| WavecastError::invalid("thumbnail_path", reason))?;
+            require_file(thumbnail)?;
+        }
+        if let Some(ref comments) = self.comments {
+            if !["anybody", "contacts", "nobody"].contains(&comments.as_str()) {
+                return Err(WavecastError::invalid("comments", format!(
+                    "Unknown comments setting '{}' (expected anybody, contacts or nobody)",
+                    comments
+                )));
+            }
+        }
+        if let Some(ref embed) = self.embed {
+            if !["public", "private"].contains(&embed.as_str()) {
+                return Err(WavecastError::invalid(
+                    "embed",
+                    format!("Unknown embed setting '{}' (expected public or private)", embed),
+                ));
+            }
+        }
 
-    // Create a simple PNG using raw RGBA data
-    let temp_dir = std::env::temp_dir();
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let temp_path = temp_dir.join(format!("solid_color_{}.png", timestamp));
+        match self.privacy.as_deref() {
+            None | Some("anybody") | Some("unlisted") | Some("nobody") => Ok(()),
+            Some("password") => match self.password.as_deref() {
+                Some(password) if !password.is_empty() => Ok(()),
+                _ => Err(WavecastError::invalid("password", "A password is required when privacy is set to \"password\"")),
+            },
+            Some(other) => Err(WavecastError::invalid("privacy", format!(
+                "Unknown privacy setting '{}' (expected anybody, unlisted, password or nobody)",
+                other
+            ))),
+        }
+    }
+
+    // JSON body for POST /me/videos
+    fn create_body(&self, title: &str, video_size: u64) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "upload": {
+                "approach": "tus",
+                "size": video_size.to_string()
+            },
+            "name": title
+        });
 
-    // Create image buffer
-    let mut imgbuf = image::ImageBuffer::new(width, height);
+        if let Some(ref description) = self.description {
+            body["description"] = serde_json::json!(description);
+        }
+        let mut privacy = serde_json::Map::new();
+        if let Some(ref view) = self.privacy {
+            privacy.insert("view".to_string(), serde_json::json!(view));
+            if view == "password" {
+                body["password"] = serde_json::json!(self.password);
+            }
+        }
+        if let Some(ref comments) = self.comments {
+            privacy.insert("comments".to_string(), serde_json::json!(comments));
+        }
+        if let Some(ref embed) = self.embed {
+            privacy.insert("embed".to_string(), serde_json::json!(embed));
+        }
+        if !privacy.is_empty() {
+            body["privacy"] = serde_json::Value::Object(privacy);
+        }
 
-    // Fill with solid color
-    for pixel in imgbuf.pixels_mut() {
-        *pixel = image::Rgb([r, g, b]);
+        body
     }
+}
 
-    // Save the image
-    imgbuf.save(&temp_path)
-        .map_err(|e| format!("Failed to save image: {}", e))?;
+// An in-flight TUS upload, saved next to the video so it can be resumed
+#[derive(Serialize, Deserialize)]
+struct UploadSession {
+    upload_link: String,
+    video_uri: String,
+    size: u64,
+}
 
-    Ok(temp_path.to_str().unwrap().to_string())
+#[derive(Clone, Serialize)]
+struct ExportProgress {
+    job_id: u64,
+    frame: u32,
+    fps: f32,
+    time: String,
+    progress: f64,
+    // Encode speed as a multiple of realtime; the rest of these are None until FFmpeg reports them
+    speed: Option<f32>,
+    bitrate_kbps: Option<f32>,
+    out_size_bytes: Option<u64>,
+    eta_seconds: Option<f64>,
 }
 
-#[tauri::command]
-fn convert_timeline_to_video(
-    app: tauri::AppHandle,
-    image_path: String,
-    timeline: TimelineData,
-    background_style: String,
-    bg_music_path: Option<String>,
-    bg_music_volume: i32,
-    main_audio_volume: i32,
-    output_filename: Option<String>,
-) -> Result<String, String> {
-    eprintln!("=== Starting timeline-based video conversion ===");
-    eprintln!("Image path: {}", image_path);
-    eprintln!("Timeline tracks: {}", timeline.tracks.len());
-    eprintln!("Background style: {}", background_style);
-    eprintln!("Main audio volume: {}", main_audio_volume);
-    eprintln!("BG music path: {:?}", bg_music_path);
-    eprintln!("BG music volume: {}", bg_music_volume);
+// Progress of a preparation step that runs before the main encode
+#[derive(Clone, Serialize)]
+struct StageProgress {
+    job_id: u64,
+    phase: String,
+    time: String,
+    progress: Option<f64>,
+}
 
-    // Download FFmpeg if not present
-    eprintln!("Checking for FFmpeg...");
-    auto_download().map_err(|e| {
-        let err_msg = format!("Failed to download FFmpeg: {}", e);
-        eprintln!("ERROR: {}", err_msg);
-        err_msg
-    })?;
-    eprintln!("FFmpeg ready");
+// Output resolution preset chosen in the export dialog
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+enum VideoPreset {
+    #[serde(alias = "720p")]
+    HD720,
+    #[default]
+    #[serde(alias = "1080p")]
+    FHD1080,
+    #[serde(alias = "4k", alias = "2160p")]
+    UHD4K,
+    // 9:16 for Reels and Shorts
+    #[serde(alias = "portrait", alias = "9:16", alias = "1080x1920")]
+    Portrait1080,
+    #[serde(alias = "square", alias = "1:1", alias = "1080x1080")]
+    Square1080,
+    Custom { w: u32, h: u32 },
+}
 
-    // Get all clips from all audio tracks with their track volumes
-    let mut all_clips: Vec<ClipWithVolume> = Vec::new();
-    for (i, track) in timeline.tracks.iter().enumerate() {
-        eprintln!("Track {}: {} clips, volume: {}", i, track.clips.len(), track.volume);
-        for clip in &track.clips {
-            all_clips.push(ClipWithVolume {
-                clip: clip.clone(),
-                track_volume: track.volume,
-            });
+impl VideoPreset {
+    fn dimensions(&self) -> Result<(u32, u32), String> {
+        match *self {
+            VideoPreset::HD720 => Ok((1280, 720)),
+            VideoPreset::FHD1080 => Ok((1920, 1080)),
+            VideoPreset::UHD4K => Ok((3840, 2160)),
+            VideoPreset::Portrait1080 => Ok((1080, 1920)),
+            VideoPreset::Square1080 => Ok((1080, 1080)),
+            VideoPreset::Custom { w, h } => {
+                // yuv420p needs even dimensions
+                if !(2..=7680).contains(&w) || !(2..=7680).contains(&h) {
+                    return Err(format!("Invalid custom resolution {}x{}: each side must be between 2 and 7680", w, h));
+                }
+                if w % 2 != 0 || h % 2 != 0 {
+                    return Err(format!("Invalid custom resolution {}x{}: width and height must be even", w, h));
+                }
+                Ok((w, h))
+            }
         }
     }
 
-    if all_clips.is_empty() {
-        let err_msg = "No audio clips in timeline".to_string();
-        eprintln!("ERROR: {}", err_msg);
-        return Err(err_msg);
+    // x264 CRF for this resolution
+    fn default_crf(&self) -> u8 {
+        match *self {
+            VideoPreset::HD720 => 23,
+            VideoPreset::FHD1080 => 21,
+            VideoPreset::UHD4K => 20,
+            VideoPreset::Portrait1080 | VideoPreset::Square1080 => 21,
+            VideoPreset::Custom { .. } => 21,
+        }
     }
-    eprintln!("Total clips to process: {}", all_clips.len());
-
-    // Create output path
-    let first_clip_with_vol = &all_clips[0];
-    eprintln!("First clip source: {}", first_clip_with_vol.clip.source_file);
-    let audio_dir = PathBuf::from(&first_clip_with_vol.clip.source_file)
-        .parent()
-        .ok_or_else(|| {
-            eprintln!("ERROR: Could not determine audio directory");
-            "Could not determine audio directory".to_string()
-        })?
-        .to_path_buf();
-    eprintln!("Output directory: {}", audio_dir.display());
 
-    // Use provided filename or default to "output.mp4"
-    let output_name = output_filename
-        .map(|name| {
-            // Sanitize filename: remove invalid characters and ensure .mp4 extension
-            let sanitized = name
-                .replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
-                .trim()
-                .to_string();
-            if sanitized.to_lowercase().ends_with(".mp4") {
-                sanitized
-            } else {
-                format!("{}.mp4", sanitized)
+    // Bitrate cap for this resolution
+    fn max_bitrate_kbps(&self) -> Result<u32, String> {
+        Ok(match *self {
+            VideoPreset::HD720 => 2500,
+            VideoPreset::FHD1080 => 5000,
+            VideoPreset::UHD4K => 16000,
+            VideoPreset::Portrait1080 => 5000,
+            VideoPreset::Square1080 => 3000,
+            VideoPreset::Custom { .. } => {
+                // Scale the 1080p cap by pixel count
+                let (w, h) = self.dimensions()?;
+                let pixels = (w as u64) * (h as u64);
+                ((5000 * pixels) / (1920 * 1080)).max(500) as u32
             }
         })
-        .unwrap_or_else(|| "output.mp4".to_string());
+    }
 
-    let output_path = audio_dir.join(&output_name);
-    eprintln!("Output path: {}", output_path.display());
+    // x264 rate control for this resolution: a CRF plus a VBV cap, either of which
+    // can be overridden
+    fn x264_args(&self, crf_override: Option<u8>, maxrate_override: Option<u32>) -> Result<Vec<String>, String> {
+        let maxrate_kbps = match maxrate_override {
+            Some(maxrate_kbps) => maxrate_kbps,
+            None => self.max_bitrate_kbps()?,
+        };
+        let crf = crf_override.unwrap_or_else(|| self.default_crf());
 
-    // Determine filter based on background style
-    let video_filter = match background_style.as_str() {
-        "cover" => "scale=1280:720:force_original_aspect_ratio=increase,crop=1280:720",
-        "contain" => "scale=1280:720:force_original_aspect_ratio=decrease,pad=1280:720:(ow-iw)/2:(oh-ih)/2",
-        "repeat" => "tile=2x2",
-        "center" => "scale=1280:720:force_original_aspect_ratio=decrease,pad=1280:720:(ow-iw)/2:(oh-ih)/2",
-        _ => "scale=1280:720:force_original_aspect_ratio=increase,crop=1280:720",
-    };
+        Ok(vec![
+            "-crf".to_string(), crf.to_string(),
+            "-maxrate".to_string(), format!("{}k", maxrate_kbps),
+            "-bufsize".to_string(), format!("{}k", maxrate_kbps * 2),
+        ])
+    }
+}
 
-    let main_volume = main_audio_volume as f64 / 100.0;
+// Named quality levels for exports that don't want to pick a CRF
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+enum VideoQuality {
+    #[serde(alias = "small")]
+    Small,
+    #[serde(alias = "balanced")]
+    Balanced,
+    #[serde(alias = "high")]
+    High,
+}
 
-    // Build FFmpeg command with all input files
-    let mut cmd = FfmpegCommand::new();
+impl VideoQuality {
+    // x264 CRF for this quality level
+    fn crf(&self) -> u8 {
+        match *self {
+            VideoQuality::Small => 28,
+            VideoQuality::Balanced => 23,
+            VideoQuality::High => 18,
+        }
+    }
+}
 
-    // IMPORTANT: -loop 1 must come BEFORE the image input
-    cmd.args(&["-loop", "1"]);
-    cmd.input(&image_path);
+const X264_PRESETS: [&str; 9] = [
+    "ultrafast", "superfast", "veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow",
+];
 
-    // Add background music as input if provided
-    let has_bg_music = bg_music_path.is_some();
-    if let Some(ref music_path) = bg_music_path {
-        eprintln!("Adding background music input: {}", music_path);
-        cmd.input(music_path);
-    }
+// Hardware encoders that can be asked for by name
+const HARDWARE_ENCODERS: [&str; 8] = [
+    "h264_videotoolbox", "h264_nvenc", "h264_qsv", "h264_amf",
+    "hevc_videotoolbox", "hevc_nvenc", "hevc_qsv", "hevc_amf",
+];
 
-    // Add each unique source file as input
-    let mut unique_sources: Vec<String> = Vec::new();
-    for clip_with_vol in &all_clips {
-        if !unique_sources.contains(&clip_with_vol.clip.source_file) {
-            unique_sources.push(clip_with_vol.clip.source_file.clone());
+// Encoder overrides for mp4 exports; anything left out keeps the current defaults
+// (libx264, the resolution preset's CRF, x264's default preset and 192k AAC)
+#[derive(Deserialize, Debug, Clone, Default)]
+struct EncodingOptions {
+    codec: Option<String>,
+    // FFmpeg encoder to use, e.g. "h264_nvenc". Falls back to software with a
+    // warning when it isn't known or doesn't work on this machine
+    encoder: Option<String>,
+    crf: Option<u8>,
+    // Used for the CRF when crf isn't given
+    quality: Option<VideoQuality>,
+    preset: Option<String>,
+    // Video bitrate cap in kbps, instead of the resolution's. A still image
+    // looks fine at a fraction of the default
+    max_bitrate_kbps: Option<u32>,
+    audio_bitrate: Option<String>,
+    // Set from encoder or use_hardware_encoder once a working hardware encoder has been found
+    #[serde(skip)]
+    hardware_encoder: Option<&'static str>,
+    // Set when the picture moves (a background video), which rules out x264's stillimage tune
+    #[serde(skip)]
+    moving_picture: bool,
+}
+
+impl EncodingOptions {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(ref codec) = self.codec {
+            if codec != "h264" && codec != "h265" {
+                return Err(format!("Unsupported video codec '{}' (expected h264 or h265)", codec));
+            }
+        }
+        if let Some(crf) = self.crf {
+            if crf > 51 {
+                return Err(format!("Invalid CRF {}: must be between 0 and 51", crf));
+            }
+        }
+        if let Some(ref preset) = self.preset {
+            if !X264_PRESETS.contains(&preset.as_str()) {
+                return Err(format!("Unknown encoder preset '{}' (expected one of {})", preset, X264_PRESETS.join(", ")));
+            }
+        }
+        if let Some(maxrate_kbps) = self.max_bitrate_kbps {
+            if !(100..=100_000).contains(&maxrate_kbps) {
+                return Err(format!("Invalid max bitrate {}k: must be between 100k and 100000k", maxrate_kbps));
+            }
+        }
+        if let Some(ref bitrate) = self.audio_bitrate {
+            validate_audio_bitrate(bitrate)?;
         }
+        Ok(())
     }
 
-    for source in &unique_sources {
-        cmd.input(source);
+    fn is_h265(&self) -> bool {
+        self.codec.as_deref() == Some("h265")
     }
 
-    // Generate audio filter complex
-    eprintln!("Generating audio filter complex...");
-    let mut audio_filter = generate_filter_complex(&all_clips, &unique_sources, main_volume, has_bg_music);
-
-    // If background music is provided, mix it with the main audio
-    if has_bg_music {
-        let bg_volume = bg_music_volume as f64 / 100.0;
-        eprintln!("Adding background music mixing (volume: {})", bg_volume);
-
-        // The filter complex from generate_filter_complex outputs to [aout]
-        // We need to mix it with the background music (input 1)
-        // Input 0: image
-        // Input 1: background music (if provided)
-        // Input 2+: audio clips
+    // CRF from crf or quality, with `offset` added to the quality's x264 CRF
+    fn crf_override(&self, offset: u8) -> Option<u8> {
+        self.crf.or_else(|| self.quality.map(|quality| quality.crf() + offset))
+    }
 
-        audio_filter = format!(
-            "{};[1:a]aloop=loop=-1:size=2e+09,volume={}[bgmusic];[aout][bgmusic]amix=inputs=2:duration=first:dropout_transition=2[final]",
-            audio_filter, bg_volume
-        );
+    fn max_bitrate_kbps(&self, preset: &VideoPreset) -> Result<u32, String> {
+        match self.max_bitrate_kbps {
+            Some(maxrate_kbps) => Ok(maxrate_kbps),
+            None => preset.max_bitrate_kbps(),
+        }
     }
 
-    eprintln!("Final audio filter complex: {}", audio_filter);
+    // Settle on the video encoder: the requested one if it works here, otherwise the
+    // first working hardware encoder when `auto_hardware` is set, otherwise software.
+    // Returns a warning for the user when the requested encoder can't be used
+    fn choose_encoder(&mut self, auto_hardware: bool) -> Option<String> {
+        let software = if self.is_h265() { "libx265" } else { "libx264" };
+        let requested = match self.encoder.as_deref() {
+            Some(requested) => requested,
+            None => {
+                if auto_hardware {
+                    self.hardware_encoder = find_hardware_encoder(self.is_h265());
+                }
+                return None;
+            }
+        };
+        if requested == software {
+            return None;
+        }
 
-    let audio_output_label = if has_bg_music { "[final]" } else { "[aout]" };
+        let codec = if self.is_h265() { "h265" } else { "h264" };
+        let warning = match HARDWARE_ENCODERS.iter().find(|encoder| **encoder == requested) {
+            Some(encoder) if encoder.starts_with("hevc_") != self.is_h265() => {
+                format!("The {} encoder can't produce {} video, using {} instead", requested, codec, software)
+            }
+            Some(encoder) => {
+                eprintln!("Probing requested encoder {}...", encoder);
+                if hardware_encoder_works(encoder) {
+                    self.hardware_encoder = Some(*encoder);
+                    return None;
+                }
+                format!("The {} encoder isn't available on this computer, using {} instead", requested, software)
+            }
+            None if requested == "libx264" || requested == "libx265" => {
+                format!("The {} encoder can't produce {} video, using {} instead", requested, codec, software)
+            }
+            None => format!("Unknown video encoder '{}', using {} instead", requested, software),
+        };
+        eprintln!("WARNING: {}", warning);
+        Some(warning)
+    }
 
-    cmd.args(&[
-        "-vf", video_filter,
-        "-filter_complex", &audio_filter,
-        "-map", "0:v",
-        "-map", audio_output_label,
-        "-c:v", "libx264",
-        "-tune", "stillimage",
-        "-c:a", "aac",
-        "-b:a", "192k",
-        "-pix_fmt", "yuv420p",
-        "-shortest",
-        "-progress", "pipe:1"
-    ])
-    .overwrite()
-    .output(output_path.to_str().unwrap());
+    // The encoder choose_encoder would try, without the test encode that checks it works
+    // here: the requested hardware encoder if it can produce this codec, otherwise the
+    // first hardware candidate when `auto_hardware` is set, otherwise software
+    fn assume_encoder(&mut self, auto_hardware: bool) {
+        let h265 = self.is_h265();
+        self.hardware_encoder = match self.encoder.as_deref() {
+            Some(requested) => HARDWARE_ENCODERS.iter()
+                .find(|encoder| **encoder == requested && encoder.starts_with("hevc_") == h265)
+                .copied(),
+            None if auto_hardware => hardware_encoder_candidates(h265).first().copied(),
+            None => None,
+        };
+    }
 
-    // Log the complete FFmpeg command for debugging
-    eprintln!("=== FFmpeg Command Debug ===");
-    eprintln!("Image path: {}", image_path);
-    if has_bg_music {
-        if let Some(ref music_path) = bg_music_path {
-            eprintln!("Music path: {}", music_path);
+    // Name of the FFmpeg video encoder these options end up using
+    fn encoder_name(&self) -> &'static str {
+        match self.hardware_encoder {
+            Some(encoder) => encoder,
+            None if self.is_h265() => "libx265",
+            None => "libx264",
         }
     }
-    eprintln!("Unique audio sources: {:?}", unique_sources);
-    eprintln!("Video filter: {}", video_filter);
-    eprintln!("Audio filter: {}", audio_filter);
-    eprintln!("Output path: {}", output_path.display());
-    eprintln!("===========================");
 
-    // Spawn process and capture events
-    eprintln!("Spawning FFmpeg process...");
-    let mut child = cmd.spawn()
-        .map_err(|e| {
-            let err_msg = format!("Failed to spawn FFmpeg: {}", e);
-            eprintln!("ERROR: {}", err_msg);
-            err_msg
-        })?;
-    eprintln!("FFmpeg process started");
+    // Video codec and rate control flags for an mp4 at the given resolution
+    fn video_args(&self, preset: &VideoPreset) -> Result<Vec<String>, String> {
+        let mut args: Vec<String> = Vec::new();
+        if let Some(encoder) = self.hardware_encoder {
+            let maxrate_kbps = self.max_bitrate_kbps(preset)?;
+            args.extend(["-c:v".to_string(), encoder.to_string()]);
+            if encoder.ends_with("_nvenc") {
+                // NVENC has a constant quality mode close enough to x264's CRF scale,
+                // capped at the resolution's bitrate
+                if self.preset.is_some() {
+                    eprintln!("WARNING: preset overrides don't apply to {}, ignoring it", encoder);
+                }
+                let cq = self.crf_override(0).unwrap_or(preset.default_crf());
+                args.extend([
+                    "-preset".to_string(), "p5".to_string(),
+                    "-rc".to_string(), "vbr".to_string(),
+                    "-cq".to_string(), cq.to_string(),
+                    "-b:v".to_string(), "0".to_string(),
+                ]);
+            } else {
+                // The others don't share x264's CRF scale, so target half the
+                // resolution's cap and let it peak up to the cap
+                if self.crf.is_some() || self.quality.is_some() || self.preset.is_some() {
+                    eprintln!("WARNING: CRF, quality and preset overrides don't apply to {}, ignoring them", encoder);
+                }
+                args.extend(["-b:v".to_string(), format!("{}k", maxrate_kbps / 2)]);
+            }
+            args.extend([
+                "-maxrate".to_string(), format!("{}k", maxrate_kbps),
+                "-bufsize".to_string(), format!("{}k", maxrate_kbps * 2),
+                "-pix_fmt".to_string(), hardware_pix_fmt(encoder).to_string(),
+            ]);
+            if self.is_h265() {
+                args.extend(["-tag:v", "hvc1"].map(String::from));
+            }
+            return Ok(args);
+        }
 
-    // Calculate total duration for progress percentage
-    let total_duration: f64 = all_clips.iter()
-        .map(|clip_with_vol| clip_with_vol.clip.start_time + clip_with_vol.clip.duration)
-        .fold(0.0, f64::max);
-    eprintln!("Total duration: {:.2}s", total_duration);
+        if self.is_h265() {
+            // x265 has no stillimage tune, and its CRF scale sits about 5 above x264's
+            // for the same quality. hvc1 tagging lets Apple players open the file.
+            let crf = self.crf_override(5).unwrap_or(preset.default_crf() + 5);
+            args.extend(["-c:v", "libx265"].map(String::from));
+            args.extend(preset.x264_args(Some(crf), self.max_bitrate_kbps)?);
+            args.extend(["-tag:v", "hvc1"].map(String::from));
+        } else {
+            args.extend(["-c:v", "libx264"].map(String::from));
+            if !self.moving_picture {
+                args.extend(["-tune", "stillimage"].map(String::from));
+            }
+            args.extend(preset.x264_args(self.crf_override(0), self.max_bitrate_kbps)?);
+        }
+        if let Some(ref encoder_preset) = self.preset {
+            args.extend(["-preset".to_string(), encoder_preset.clone()]);
+        }
+        args.extend(["-pix_fmt", "yuv420p"].map(String::from));
+        Ok(args)
+    }
 
-    // Iterate over FFmpeg events
-    eprintln!("Processing FFmpeg output...");
-    let iter = child.iter()
-        .map_err(|e| {
-            let err_msg = format!("Failed to get FFmpeg iterator: {}", e);
-            eprintln!("ERROR: {}", err_msg);
-            err_msg
-        })?;
+    fn audio_bitrate(&self) -> &str {
+        self.audio_bitrate.as_deref().unwrap_or("192k")
+    }
+}
 
-    for event in iter {
-        match event {
-            FfmpegEvent::Progress(progress) => {
-                // Parse time string (format: "HH:MM:SS.ms" or similar)
-                let current_time = parse_time_to_seconds(&progress.time);
-                let progress_pct = if total_duration > 0.0 {
-                    (current_time / total_duration * 100.0).min(100.0)
-                } else {
-                    0.0
-                };
+fn validate_audio_bitrate(bitrate: &str) -> Result<(), String> {
+    let kbps = bitrate.strip_suffix('k').and_then(|n| n.parse::<u32>().ok());
+    if !matches!(kbps, Some(32..=512)) {
+        return Err(format!("Invalid audio bitrate '{}': expected a value like \"192k\" between 32k and 512k", bitrate));
+    }
+    Ok(())
+}
 
-                let progress_data = ExportProgress {
-                    frame: progress.frame,
-                    fps: progress.fps,
-                    time: progress.time.clone(),
-                    progress: progress_pct,
-                };
+// Quick Sync only takes NV12 input; the others accept yuv420p like x264
+fn hardware_pix_fmt(encoder: &str) -> &'static str {
+    if encoder.ends_with("_qsv") {
+        "nv12"
+    } else {
+        "yuv420p"
+    }
+}
 
-                // Emit progress event
-                let _ = app.emit("export-progress", progress_data);
+// Hardware encoders worth trying on this platform, in order of preference
+fn hardware_encoder_candidates(h265: bool) -> &'static [&'static str] {
+    if cfg!(target_os = "macos") {
+        if h265 { &["hevc_videotoolbox"] } else { &["h264_videotoolbox"] }
+    } else if cfg!(target_os = "windows") {
+        if h265 { &["hevc_nvenc", "hevc_qsv", "hevc_amf"] } else { &["h264_nvenc", "h264_qsv", "h264_amf"] }
+    } else if h265 {
+        &["hevc_nvenc", "hevc_qsv"]
+    } else {
+        &["h264_nvenc", "h264_qsv"]
+    }
+}
+
+// Whether `encoder` actually works here: FFmpeg builds list encoders whether or not
+// the GPU and drivers are present, so do a one-second test encode to a temp file
+fn hardware_encoder_works(encoder: &str) -> bool {
+    let test_output = std::env::temp_dir().join(format!("wavecast-encoder-test-{}-{}.mp4", encoder, std::process::id()));
+
+    let mut cmd = ffmpeg_command();
+    cmd.format("lavfi")
+        .input("color=c=black:s=640x360:r=30:d=1")
+        .args(&["-c:v", encoder, "-pix_fmt", hardware_pix_fmt(encoder)])
+        .overwrite()
+        .output(test_output.to_str().unwrap());
+
+    let works = match cmd.spawn() {
+        Ok(mut child) => child.wait().map(|status| status.success()).unwrap_or(false),
+        Err(_) => false,
+    };
+    let _ = std::fs::remove_file(&test_output);
+    works
+}
+
+// First hardware encoder that passes a test encode, if any
+fn find_hardware_encoder(h265: bool) -> Option<&'static str> {
+    for encoder in hardware_encoder_candidates(h265) {
+        eprintln!("Probing hardware encoder {}...", encoder);
+        if hardware_encoder_works(encoder) {
+            eprintln!("Using hardware encoder {}", encoder);
+            return Some(*encoder);
+        }
+        eprintln!("Hardware encoder {} is not available", encoder);
+    }
+    eprintln!("No hardware encoder available, falling back to software encoding");
+    None
+}
+
+// Output container, which also decides the video/audio codecs
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Mp4,
+    Webm,
+    Mov,
+}
+
+impl OutputFormat {
+    fn parse(format: &str) -> Result<Self, String> {
+        match format.trim().to_lowercase().as_str() {
+            "mp4" => Ok(OutputFormat::Mp4),
+            "webm" => Ok(OutputFormat::Webm),
+            "mov" => Ok(OutputFormat::Mov),
+            other => Err(format!("Unsupported output format '{}' (expected mp4, webm or mov)", other)),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Mp4 => "mp4",
+            OutputFormat::Webm => "webm",
+            OutputFormat::Mov => "mov",
+        }
+    }
+
+    // Codec flags for this container
+    fn codec_args(&self, preset: &VideoPreset, encoding: &EncodingOptions) -> Result<Vec<String>, String> {
+        let mut args: Vec<String> = Vec::new();
+        let video_overrides = encoding.codec.is_some() || encoding.encoder.is_some() || encoding.crf.is_some()
+            || encoding.quality.is_some() || encoding.preset.is_some() || encoding.max_bitrate_kbps.is_some();
+        if *self != OutputFormat::Mp4 && video_overrides {
+            eprintln!("WARNING: codec, encoder, quality, preset and bitrate overrides only apply to mp4 output, ignoring them");
+        }
+        match self {
+            OutputFormat::Mp4 => {
+                args.extend(encoding.video_args(preset)?);
+                args.extend(["-c:a".to_string(), "aac".to_string(), "-b:a".to_string(), encoding.audio_bitrate().to_string()]);
             }
-            FfmpegEvent::Log(_level, msg) => {
-                // Optionally log messages
-                eprintln!("FFmpeg: {}", msg);
+            OutputFormat::Webm => {
+                // libvpx-vp9 is very slow at its default settings; "good" with cpu-used 4
+                // and row multithreading keeps a still-image encode close to x264 speed
+                args.extend([
+                    "-c:v", "libvpx-vp9",
+                    "-crf", "32",
+                    "-b:v", "0",
+                    "-deadline", "good",
+                    "-cpu-used", "4",
+                    "-row-mt", "1",
+                    "-pix_fmt", "yuv420p",
+                    "-c:a", "libopus",
+                ].map(String::from));
+                let audio_bitrate = encoding.audio_bitrate.as_deref().unwrap_or("160k");
+                args.extend(["-b:a".to_string(), audio_bitrate.to_string()]);
+            }
+            OutputFormat::Mov => {
+                // ProRes 422 with uncompressed PCM audio, intended for further editing
+                args.extend([
+                    "-c:v", "prores_ks",
+                    "-profile:v", "2",
+                    "-pix_fmt", "yuv422p10le",
+                    "-c:a", "pcm_s16le",
+                ].map(String::from));
             }
-            _ => {}
         }
+        Ok(args)
     }
+}
 
-    // Wait for completion
-    eprintln!("Waiting for FFmpeg to complete...");
-    let result = child.wait()
-        .map_err(|e| {
-            let err_msg = format!("Failed to execute FFmpeg: {}", e);
-            eprintln!("ERROR: {}", err_msg);
-            err_msg
-        })?;
+// Format of an audio-only export
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AudioFormat {
+    Mp3,
+    M4a,
+    Flac,
+}
 
-    if !result.success() {
-        let err_msg = "FFmpeg encoding failed".to_string();
-        eprintln!("ERROR: {}", err_msg);
-        eprintln!("ERROR CONTEXT:");
-        eprintln!("  - Image: {}", image_path);
-        eprintln!("  - Audio sources: {:?}", unique_sources);
-        eprintln!("  - Video filter: {}", video_filter);
-        eprintln!("  - Audio filter: {}", audio_filter);
-        eprintln!("  - Has BG music: {}", has_bg_music);
-        eprintln!("  - Exit code: {:?}", result.code());
-        return Err(err_msg);
+impl AudioFormat {
+    fn parse(format: &str) -> Result<Self, String> {
+        match format.trim().to_lowercase().as_str() {
+            "mp3" => Ok(AudioFormat::Mp3),
+            "m4a" => Ok(AudioFormat::M4a),
+            "flac" => Ok(AudioFormat::Flac),
+            other => Err(format!("Unsupported audio format '{}' (expected mp3, m4a or flac)", other)),
+        }
     }
 
-    eprintln!("=== Timeline video conversion completed successfully ===");
-    eprintln!("Output file: {}", output_path.display());
-    Ok(output_path.to_str().unwrap().to_string())
+    fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Flac => "flac",
+        }
+    }
+
+    // Whether the codec takes a bitrate; FLAC is lossless
+    fn has_bitrate(&self) -> bool {
+        *self != AudioFormat::Flac
+    }
+
+    fn codec_args(&self, bitrate: &str) -> Vec<String> {
+        let codec = match self {
+            AudioFormat::Mp3 => "libmp3lame",
+            AudioFormat::M4a => "aac",
+            AudioFormat::Flac => return vec!["-c:a".to_string(), "flac".to_string()],
+        };
+        vec!["-c:a".to_string(), codec.to_string(), "-b:a".to_string(), bitrate.to_string()]
+    }
 }
 
-#[tauri::command]
-fn convert_to_video(
-    image_path: String,
-    audio_paths: Vec<String>,
-    background_style: String,
-    bg_music_path: Option<String>,
-    bg_music_volume: i32,
-    main_audio_volume: i32,
-) -> Result<String, String> {
-    eprintln!("=== Starting video conversion ===");
-    eprintln!("Image path: {}", image_path);
-    eprintln!("Audio paths: {:?}", audio_paths);
-    eprintln!("Background style: {}", background_style);
-    eprintln!("BG music path: {:?}", bg_music_path);
-    eprintln!("BG music volume: {}", bg_music_volume);
-    eprintln!("Main audio volume: {}", main_audio_volume);
+// Extensions of everything we export, so a name typed for one format can be switched to another
+const OUTPUT_EXTENSIONS: [&str; 6] = ["mp4", "webm", "mov", "mp3", "m4a", "flac"];
 
-    // Download FFmpeg if not present (will use cached version if available)
-    eprintln!("Checking for FFmpeg...");
-    auto_download().map_err(|e| {
-        let err_msg = format!("Failed to download FFmpeg: {}", e);
-        eprintln!("ERROR: {}", err_msg);
-        err_msg
-    })?;
-    eprintln!("FFmpeg ready");
+// Sanitize a user-supplied output filename and give it the output's extension
+fn sanitize_output_filename(name: &str, extension: &str) -> String {
+    // Remove invalid characters
+    let mut sanitized = name
+        .replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+        .trim()
+        .to_string();
 
-    // Create output path in the same directory as the first audio file
-    let first_audio = audio_paths.first()
-        .ok_or_else(|| {
-            eprintln!("ERROR: No audio files provided");
-            "No audio files provided".to_string()
-        })?;
-    eprintln!("First audio file: {}", first_audio);
+    // Drop an extension for a different format so "episode.mp4" exported as webm becomes "episode.webm"
+    let lower = sanitized.to_lowercase();
+    for other in OUTPUT_EXTENSIONS {
+        let suffix = format!(".{}", other);
+        if other != extension && lower.ends_with(&suffix) {
+            sanitized.truncate(sanitized.len() - suffix.len());
+            break;
+        }
+    }
 
-    let audio_dir = PathBuf::from(first_audio)
-        .parent()
-        .ok_or_else(|| {
-            eprintln!("ERROR: Could not determine audio directory from path: {}", first_audio);
-            "Could not determine audio directory".to_string()
-        })?
-        .to_path_buf();
-    eprintln!("Output directory: {}", audio_dir.display());
+    if sanitized.to_lowercase().ends_with(&format!(".{}", extension)) {
+        sanitized
+    } else {
+        format!("{}.{}", sanitized, extension)
+    }
+}
 
-    let output_path = audio_dir.join("output.mp4");
-    eprintln!("Output path: {}", output_path.display());
+// Device names Windows won't create files as, whatever the extension
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
 
-    // If multiple audio files, concatenate them first
-    let final_audio_path = if audio_paths.len() > 1 {
-        eprintln!("Multiple audio files detected, concatenating {} files...", audio_paths.len());
-        let concat_list_path = audio_dir.join("concat_list.txt");
+// Values the placeholders of an output filename template are filled from
+#[derive(Deserialize, Debug, Default)]
+struct FilenameContext {
+    video_title: Option<String>,
+    episode: Option<u32>,
+    // Already formatted the way it should appear, e.g. 2024-05-01
+    date: Option<String>,
+}
 
-        // Create concat file
-        // Convert backslashes to forward slashes for FFmpeg compatibility on Windows
-        let concat_content = audio_paths
-            .iter()
-            .map(|p| {
-                let normalized_path = p.replace('\\', "/");
-                format!("file '{}'", normalized_path)
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+// Fill in a template like "{title} - EP{episode} - {date}" and sanitize the result into
+// a file name with `extension`. Unknown placeholders, placeholders without a value and
+// names Windows can't create are errors
+fn apply_filename_template(template: &str, context: &FilenameContext, extension: &str) -> Result<String, String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        rendered.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let close = after.find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in \"{}\"", template))?;
+        let name = after[..close].trim();
+        let value = match name {
+            "title" => context.video_title.clone(),
+            "episode" => context.episode.map(|episode| episode.to_string()),
+            "date" => context.date.clone(),
+            _ => return Err(format!("Unknown placeholder {{{}}}", name)),
+        };
+        let value = value
+            .filter(|value| !value.trim().is_empty())
+            .ok_or_else(|| format!("No value for placeholder {{{}}}", name))?;
+        rendered.push_str(value.trim());
+        rest = &after[close + 1..];
+    }
+    rendered.push_str(rest);
 
-        std::fs::write(&concat_list_path, &concat_content)
-            .map_err(|e| {
-                let err_msg = format!("Failed to create concat list: {}", e);
-                eprintln!("ERROR: {}", err_msg);
-                err_msg
-            })?;
-        eprintln!("Created concat list at: {}", concat_list_path.display());
+    let file_name = sanitize_output_filename(&rendered, extension);
+    let stem = &file_name[..file_name.len() - extension.len() - 1];
+    if stem.trim().is_empty() {
+        return Err(format!("\"{}\" renders to an empty file name", template));
+    }
+    if stem.ends_with(['.', ' ']) {
+        return Err(format!("File name \"{}\" can't end with a dot or a space", stem));
+    }
+    // Windows also reserves these with any extension, e.g. "nul.tar.mp4"
+    let device = stem.split('.').next().unwrap_or(stem).trim_end().to_uppercase();
+    if WINDOWS_RESERVED_NAMES.contains(&device.as_str()) {
+        return Err(format!("\"{}\" is a reserved file name on Windows", stem));
+    }
 
-        let temp_audio = audio_dir.join("temp_combined.mp3");
-        eprintln!("Concatenating to: {}", temp_audio.display());
+    Ok(file_name)
+}
 
-        // Concatenate audio files
-        let mut concat_cmd = FfmpegCommand::new();
-        concat_cmd
-            .format("concat")
-            .input(concat_list_path.to_str().unwrap())
-            .args(&["-safe", "0", "-c", "copy"])
-            .overwrite()
-            .output(temp_audio.to_str().unwrap());
+#[tauri::command]
+fn render_output_filename(
+    template: String,
+    context: FilenameContext,
+    format: Option<String>,
+) -> Result<String, WavecastError> {
+    let extension = format.unwrap_or_else(|| "mp4".to_string()).to_lowercase();
+    apply_filename_template(&template, &context, &extension)
+        .map_err(|reason| WavecastError::invalid("template", reason))
+}
 
-        eprintln!("Running FFmpeg concat command...");
-        let concat_result = concat_cmd.spawn()
-            .map_err(|e| {
-                let err_msg = format!("Failed to spawn FFmpeg concat: {}", e);
-                eprintln!("ERROR: {}", err_msg);
-                err_msg
-            })?
-            .wait()
-            .map_err(|e| {
-                let err_msg = format!("Failed to concatenate audio: {}", e);
-                eprintln!("ERROR: {}", err_msg);
-                err_msg
-            })?;
+// Where an export is written. Unless `replace` is set an existing file there must not
+// be overwritten, and the export moves on to the next free "name (n)" instead
+struct OutputTarget {
+    path: PathBuf,
+    // The file name the numbering starts from
+    name: String,
+    replace: bool,
+}
 
-        if !concat_result.success() {
-            let err_msg = "FFmpeg concatenation failed".to_string();
-            eprintln!("ERROR: {}", err_msg);
-            return Err(err_msg);
-        }
-        eprintln!("Audio concatenation successful");
+impl OutputTarget {
+    // -y or -n, then the output file, to go at the end of the FFmpeg command
+    fn args(&self) -> Vec<String> {
+        vec![
+            if self.replace { "-y" } else { "-n" }.to_string(),
+            self.path.to_string_lossy().to_string(),
+        ]
+    }
 
-        // Clean up concat list
-        let _ = std::fs::remove_file(concat_list_path);
+    fn renumber(&mut self) {
+        if let Some(dir) = self.path.parent() {
+            self.path = next_available_path(dir, &self.name);
+        }
+    }
+}
 
-        temp_audio.to_str().unwrap().to_string()
-    } else {
-        eprintln!("Single audio file, no concatenation needed");
-        audio_paths[0].clone()
-    };
-    eprintln!("Final audio path: {}", final_audio_path);
+// Resolve where an export is written: the caller's path, or a save dialog
+// opened in `default_dir` and pre-filled with `default_name`
+fn resolve_output_path(
+    app: &tauri::AppHandle,
+    output_path: Option<String>,
+    default_dir: &std::path::Path,
+    default_name: &str,
+    extension: &str,
+    overwrite: bool,
+) -> Result<OutputTarget, WavecastError> {
+    let (path, name, replace) = match output_path {
+        Some(path) => {
+            let mut path = PathBuf::from(path);
+            if path.extension().is_none() {
+                path.set_extension(extension);
+            }
+            let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            // Don't clobber an earlier export unless asked to; the save dialog
+            // already confirms overwrites itself
+            if !overwrite && path.exists() {
+                if let Some(dir) = path.parent() {
+                    path = next_available_path(dir, &name);
+                }
+            }
+            (path, name, overwrite)
+        }
+        None => {
+            let file_path = app.dialog()
+                .file()
+                .set_title("Save Video")
+                .add_filter(&extension.to_uppercase(), &[extension])
+                .set_directory(default_dir)
+                .set_file_name(default_name)
+                .blocking_save_file();
 
-    // Determine filter based on background style
-    let video_filter = match background_style.as_str() {
-        "cover" => "scale=1280:720:force_original_aspect_ratio=increase,crop=1280:720",
-        "contain" => "scale=1280:720:force_original_aspect_ratio=decrease,pad=1280:720:(ow-iw)/2:(oh-ih)/2",
-        "repeat" => "tile=2x2",
-        "center" => "scale=1280:720:force_original_aspect_ratio=decrease,pad=1280:720:(ow-iw)/2:(oh-ih)/2",
-        _ => "scale=1280:720:force_original_aspect_ratio=increase,crop=1280:720",
+            let file_path = file_path.ok_or(WavecastError::DialogCancelled)?;
+            let mut path = file_path.as_path()
+                .ok_or("Failed to get path")?
+                .to_path_buf();
+            if path.extension().is_none() {
+                path.set_extension(extension);
+            }
+            let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            (path, name, true)
+        }
     };
-    eprintln!("Video filter: {}", video_filter);
 
-    // Calculate volumes as decimals (0-100 -> 0.0-1.0)
-    let main_volume = main_audio_volume as f32 / 100.0;
-    eprintln!("Main volume: {}", main_volume);
+    let dir = path.parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .ok_or_else(|| {
+            let err_msg = format!("Could not determine output directory for {}", path.display());
+            eprintln!("ERROR: {}", err_msg);
+            err_msg
+        })?;
+    check_dir_writable(dir)?;
 
-    // If background music is provided, we need to mix the audio
-    let _output = if let Some(bg_music) = bg_music_path {
-        eprintln!("Background music detected: {}", bg_music);
-        let bg_volume = bg_music_volume as f32 / 100.0;
-        eprintln!("Background music volume: {}", bg_volume);
+    Ok(OutputTarget { path, name, replace })
+}
 
-        // Create audio filter for mixing: loop bg music, adjust volumes, and mix
-        let audio_filter = format!(
-            "[1:a]aloop=loop=-1:size=2e+09[bg];[bg]volume={}[bg_vol];[0:a]volume={}[main];[bg_vol][main]amix=inputs=2:duration=first:dropout_transition=2",
-            bg_volume, main_volume
-        );
-        eprintln!("Audio filter: {}", audio_filter);
+// First of "name", "name (1)", "name (2)", ... that doesn't exist yet in `dir`
+fn next_available_path(dir: &std::path::Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
 
-        let mut cmd = FfmpegCommand::new();
-        cmd
-            .args(&["-loop", "1"])
-            .input(&image_path)
-            .input(&bg_music)
-            .input(&final_audio_path)
-            .args(&[
-                "-vf", video_filter,
-                "-filter_complex", &audio_filter,
-                "-c:v", "libx264",
-                "-tune", "stillimage",
-                "-c:a", "aac",
-                "-b:a", "192k",
-                "-pix_fmt", "yuv420p",
-                "-shortest"
-            ])
-            .overwrite()
-            .output(output_path.to_str().unwrap());
+    let name_path = std::path::Path::new(name);
+    let stem = name_path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = name_path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
 
-        eprintln!("Running FFmpeg with background music...");
-        let result = cmd.spawn()
-            .map_err(|e| {
-                let err_msg = format!("Failed to spawn FFmpeg: {}", e);
-                eprintln!("ERROR: {}", err_msg);
-                err_msg
-            })?
-            .wait()
-            .map_err(|e| {
-                let err_msg = format!("Failed to execute FFmpeg: {}", e);
-                eprintln!("ERROR: {}", err_msg);
-                err_msg
-            })?;
+    let mut n = 1;
+    loop {
+        let candidate = dir.join(format!("{} ({}){}", stem, n, extension));
+        if !candidate.exists() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
 
-        if !result.success() {
-            let err_msg = "FFmpeg encoding failed (with background music)".to_string();
+// Fail early if FFmpeg won't be able to write into `dir`
+fn check_dir_writable(dir: &std::path::Path) -> Result<(), String> {
+    let probe = dir.join(format!(".wavecast-write-test-{}", std::process::id()));
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|e| {
+            let err_msg = format!("Output directory not writable: {} ({})", dir.display(), e);
             eprintln!("ERROR: {}", err_msg);
-            return Err(err_msg);
-        }
-        eprintln!("FFmpeg encoding successful (with background music)");
-        result
-    } else {
-        // No background music, but still apply main audio volume
-        eprintln!("No background music, encoding with main audio only");
-        let audio_filter = format!("volume={}", main_volume);
-        eprintln!("Audio filter: {}", audio_filter);
+            err_msg
+        })?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
 
-        let mut cmd = FfmpegCommand::new();
-        cmd
-            .args(&["-loop", "1"])
-            .input(&image_path)
-            .input(&final_audio_path)
-            .args(&[
-                "-vf", video_filter,
-                "-af", &audio_filter,
-                "-c:v", "libx264",
-                "-tune", "stillimage",
-                "-c:a", "aac",
-                "-b:a", "192k",
-                "-pix_fmt", "yuv420p",
-                "-shortest"
-            ])
-            .overwrite()
-            .output(output_path.to_str().unwrap());
+// Lowest frame rate allowed once anything in the picture moves
+const MIN_ANIMATED_FPS: u32 = 24;
 
-        eprintln!("Running FFmpeg without background music...");
-        let result = cmd.spawn()
-            .map_err(|e| {
-                let err_msg = format!("Failed to spawn FFmpeg: {}", e);
-                eprintln!("ERROR: {}", err_msg);
-                err_msg
-            })?
-            .wait()
-            .map_err(|e| {
-                let err_msg = format!("Failed to execute FFmpeg: {}", e);
-                eprintln!("ERROR: {}", err_msg);
-                err_msg
-            })?;
+// Frame rate of the generated video; the still image is fed in at the same rate
+// so the output is constant frame rate. A pure still image encodes much faster at
+// 1-2 fps, but `animated` video chains need enough frames for the motion.
+fn output_frame_rate(fps: Option<u32>, animated: bool) -> Result<u32, String> {
+    let fps = fps.unwrap_or(30);
+    if fps == 0 || fps > 120 {
+        return Err(format!("Invalid frame rate {}: must be between 1 and 120", fps));
+    }
+    if animated && fps < MIN_ANIMATED_FPS {
+        return Err(format!(
+            "Frame rate {} is too low for an animated background: must be at least {}",
+            fps, MIN_ANIMATED_FPS
+        ));
+    }
+    Ok(fps)
+}
 
-        if !result.success() {
-            let err_msg = "FFmpeg encoding failed (without background music)".to_string();
-            eprintln!("ERROR: {}", err_msg);
-            return Err(err_msg);
-        }
-        eprintln!("FFmpeg encoding successful (without background music)");
-        result
-    };
+// -loop 1 and -framerate go BEFORE the image input so the still is read at `fps`;
+// -r goes after the inputs so the encoded output is constant frame rate
+fn frame_rate_args(fps: &str) -> ([&str; 4], [&str; 2]) {
+    (["-loop", "1", "-framerate", fps], ["-r", fps])
+}
 
-    // Clean up temporary combined audio if it exists
-    if audio_paths.len() > 1 {
-        eprintln!("Cleaning up temporary concatenated audio file...");
-        let temp_audio = audio_dir.join("temp_combined.mp3");
-        let _ = std::fs::remove_file(temp_audio);
+// Build the -vf scale filter for a background style at the target resolution. Nothing
+// here assumes 16:9: "cover" fills width x height and crops the overflow from the
+// middle, "contain" and "center" fit the image inside and pad it evenly on both sides,
+// whatever the image and target aspect ratios. The fitted size is kept even so the
+// padding splits cleanly for yuv420p.
+fn build_video_filter(background_style: &str, width: u32, height: u32) -> String {
+    let cover = format!("scale={w}:{h}:force_original_aspect_ratio=increase,crop={w}:{h}", w = width, h = height);
+    let contain = format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease:force_divisible_by=2,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2",
+        w = width,
+        h = height
+    );
+    match background_style {
+        "cover" => cover,
+        "contain" | "center" => contain,
+        "repeat" => format!("tile=2x2,{}", cover),
+        _ => cover,
     }
+}
 
-    eprintln!("=== Video conversion completed successfully ===");
-    eprintln!("Output file: {}", output_path.display());
-    Ok(output_path.to_str().unwrap().to_string())
+// Slow Ken Burns style movement over a still background, spread across the whole video
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BackgroundMotion {
+    ZoomIn,
+    ZoomOut,
+    PanLeftRight,
+    PanRightLeft,
 }
 
-#[tauri::command]
-async fn upload_to_vimeo(
-    video_path: String,
-    access_token: String,
-    title: String,
-) -> Result<String, String> {
-    // Read the video file
-    let video_data = std::fs::read(&video_path)
-        .map_err(|e| format!("Failed to read video file: {}", e))?;
+// How far the motion zooms in; pans run at this zoom so there is room to move
+const MOTION_ZOOM: f64 = 0.2;
 
-    // Create HTTP client
+impl BackgroundMotion {
+    fn parse(motion: &str) -> Result<Option<Self>, String> {
+        match motion.trim().to_lowercase().as_str() {
+            "" | "none" => Ok(None),
+            "zoom_in" => Ok(Some(BackgroundMotion::ZoomIn)),
+            "zoom_out" => Ok(Some(BackgroundMotion::ZoomOut)),
+            "pan_lr" => Ok(Some(BackgroundMotion::PanLeftRight)),
+            "pan_rl" => Ok(Some(BackgroundMotion::PanRightLeft)),
+            other => Err(format!("Unknown motion '{}' (expected zoom_in, zoom_out, pan_lr or pan_rl)", other)),
+        }
+    }
+
+    // zoompan chain appended to the background style's filter. That filter already
+    // produces exactly width x height, and zoompan crops a window of the same aspect
+    // ratio back to width x height, so nothing is stretched. Upscaling first gives
+    // zoompan sub-pixel room and avoids the jitter of whole-pixel steps.
+    fn filter(&self, width: u32, height: u32, fps: u32, duration: f64) -> String {
+        let frames = ((duration * fps as f64).round() as u64).max(1);
+        // Progress through the video from 0 to 1
+        let t = format!("min(on/{},1)", frames);
+        let center_x = "iw/2-(iw/zoom/2)";
+        let center_y = "ih/2-(ih/zoom/2)";
+        let (z, x, y) = match self {
+            BackgroundMotion::ZoomIn => (format!("1+{}*{}", MOTION_ZOOM, t), center_x.to_string(), center_y),
+            BackgroundMotion::ZoomOut => (format!("{}-{}*{}", 1.0 + MOTION_ZOOM, MOTION_ZOOM, t), center_x.to_string(), center_y),
+            BackgroundMotion::PanLeftRight => ((1.0 + MOTION_ZOOM).to_string(), format!("(iw-iw/zoom)*{}", t), center_y),
+            BackgroundMotion::PanRightLeft => ((1.0 + MOTION_ZOOM).to_string(), format!("(iw-iw/zoom)*(1-{})", t), center_y),
+        };
+        format!(
+            "scale={sw}:{sh},zoompan=z='{z}':x='{x}':y='{y}':d=1:s={w}x{h}:fps={fps}",
+            sw = width * 2,
+            sh = height * 2,
+            z = z,
+            x = x,
+            y = y,
+            w = width,
+            h = height,
+            fps = fps
+        )
+    }
+}
+
+// Animated waveform of the final mix drawn over the background
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct WaveformStyle {
+    // "#rrggbb", white by default
+    color: Option<String>,
+    // top, center or bottom (the default)
+    position: Option<String>,
+    // Height of the waveform band as a percentage of the video height, 25 by default
+    height_percent: Option<u32>,
+    // showwaves drawing mode: line, cline (the default), p2p or point
+    mode: Option<String>,
+}
+
+impl WaveformStyle {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(ref color) = self.color {
+            let hex = color.trim_start_matches('#');
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("Invalid waveform color '{}' (expected #rrggbb)", color));
+            }
+        }
+        if let Some(ref position) = self.position {
+            if !["top", "center", "bottom"].contains(&position.as_str()) {
+                return Err(format!("Unknown waveform position '{}' (expected top, center or bottom)", position));
+            }
+        }
+        if let Some(height_percent) = self.height_percent {
+            if !(5..=100).contains(&height_percent) {
+                return Err(format!("Invalid waveform height {}%: must be between 5 and 100", height_percent));
+            }
+        }
+        if let Some(ref mode) = self.mode {
+            if !["line", "cline", "p2p", "point"].contains(&mode.as_str()) {
+                return Err(format!("Unknown waveform mode '{}' (expected line, cline, p2p or point)", mode));
+            }
+        }
+        Ok(())
+    }
+
+    // Filter graph chains that turn `audio_label` into a waveform and lay it over the
+    // `background_label` video. Produces [vout] for the video and [amain] for the sound.
+    fn filter(&self, audio_label: &str, background_label: &str, width: u32, height: u32, fps: u32) -> String {
+        let wave_height = (height * self.height_percent.unwrap_or(25) / 100).max(2) / 2 * 2;
+        let color = format!("0x{}", self.color.as_deref().unwrap_or("#ffffff").trim_start_matches('#'));
+        let y = match self.position.as_deref() {
+            Some("top") => "0",
+            Some("center") => "(H-h)/2",
+            _ => "H-h",
+        };
+        format!(
+            "{audio}asplit=2[amain][awave];[awave]showwaves=s={w}x{wh}:mode={mode}:rate={fps}:colors={color},format=rgba[waves];{bg}[waves]overlay=x=0:y={y}:format=auto[vout]",
+            audio = audio_label,
+            w = width,
+            wh = wave_height,
+            mode = self.mode.as_deref().unwrap_or("cline"),
+            fps = fps,
+            color = color,
+            bg = background_label,
+            y = y
+        )
+    }
+}
+
+// Logo image laid over a corner of the video
+#[derive(Deserialize, Debug, Clone)]
+struct WatermarkSpec {
+    path: String,
+    // top_left, top_right, bottom_left or bottom_right (the default)
+    position: Option<String>,
+    // 0.0 (invisible) to 1.0 (opaque), 0.8 by default
+    opacity: Option<f64>,
+    // Logo width as a fraction of the video width, 0.15 by default
+    scale: Option<f64>,
+}
+
+impl WatermarkSpec {
+    fn validate(&self) -> Result<(), String> {
+        if !std::path::Path::new(&self.path).is_file() {
+            return Err(format!("Watermark image not found: {}", self.path));
+        }
+        if let Some(ref position) = self.position {
+            if !["top_left", "top_right", "bottom_left", "bottom_right"].contains(&position.as_str()) {
+                return Err(format!(
+                    "Unknown watermark position '{}' (expected top_left, top_right, bottom_left or bottom_right)",
+                    position
+                ));
+            }
+        }
+        if let Some(opacity) = self.opacity {
+            if !(0.0..=1.0).contains(&opacity) {
+                return Err(format!("Invalid watermark opacity {}: must be between 0 and 1", opacity));
+            }
+        }
+        if let Some(scale) = self.scale {
+            if !(0.01..=1.0).contains(&scale) {
+                return Err(format!("Invalid watermark scale {}: must be between 0.01 and 1", scale));
+            }
+        }
+        Ok(())
+    }
+
+    // Filter graph chain that scales the logo from `input`, fades it to the opacity and
+    // overlays it on `video_label` with a margin from the edges. Produces [vmark].
+    fn filter(&self, input: usize, video_label: &str, width: u32) -> String {
+        let logo_width = ((width as f64 * self.scale.unwrap_or(0.15)).round() as u32).max(2) / 2 * 2;
+        let margin = (width / 50).max(8);
+        let (x, y) = match self.position.as_deref() {
+            Some("top_left") => (format!("{}", margin), format!("{}", margin)),
+            Some("top_right") => (format!("W-w-{}", margin), format!("{}", margin)),
+            Some("bottom_left") => (format!("{}", margin), format!("H-h-{}", margin)),
+            _ => (format!("W-w-{}", margin), format!("H-h-{}", margin)),
+        };
+        format!(
+            "[{input}:v]scale={lw}:-1,format=rgba,colorchannelmixer=aa={opacity}[logo];{video}[logo]overlay=x={x}:y={y}:format=auto[vmark]",
+            input = input,
+            lw = logo_width,
+            opacity = self.opacity.unwrap_or(0.8),
+            video = video_label,
+            x = x,
+            y = y
+        )
+    }
+}
+
+// Stream details of an audio file as reported by ffprobe
+#[derive(Serialize, Debug, Clone)]
+struct AudioProbe {
+    path: String,
+    duration: f64,
+    codec: String,
+    sample_rate: u32,
+    channels: u32,
+    bit_rate: Option<u64>,
+    has_audio: bool,
+}
+
+// Timeline-based structures
+//
+// Trim semantics: `trim_start` and `trim_end` are the seconds cut off the head and
+// the tail of the source file, and `duration` is the clip's length on the timeline.
+// The clip plays the source from `trim_start` for `duration` seconds, but never past
+// `source_duration - trim_end`; whatever of `duration` is left after that is silent.
+// Exports probe the source length when the frontend didn't send one, so trim_end
+// always applies.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TimelineClip {
+    source_file: String,
+    start_time: f64,
+    duration: f64,
+    trim_start: f64,
+    trim_end: f64,
+    #[serde(default)]
+    source_duration: Option<f64>,
+    // Gain for this clip on top of its track's volume (1.0 when omitted)
+    #[serde(default)]
+    volume: Option<f64>,
+    // Fade lengths in seconds, relative to the trimmed clip (0 = no fade)
+    #[serde(default)]
+    fade_in: f64,
+    #[serde(default)]
+    fade_out: f64,
+    // Crossfade from the previous touching clip on the track into this one, in
+    // seconds; overrides the export-wide crossfade_duration
+    #[serde(default)]
+    crossfade: Option<f64>,
+    // Playback rate without a pitch change (1.0 when omitted). `duration` stays in source
+    // seconds, so the clip takes up duration / speed on the timeline
+    #[serde(default)]
+    speed: Option<f64>,
+    // Voice clean-up: `voice_enhance` turns on the default chain and `enhance` tunes
+    // it (setting it is enough on its own). Takes the place of the track's setting
+    #[serde(default)]
+    voice_enhance: bool,
+    #[serde(default)]
+    enhance: Option<AudioEnhanceOptions>,
+    // Play the trimmed section backwards
+    #[serde(default)]
+    reverse: bool,
+}
+
+impl TimelineClip {
+    fn speed(&self) -> f64 {
+        self.speed.unwrap_or(1.0)
+    }
+
+    fn enhance_options(&self) -> Option<AudioEnhanceOptions> {
+        enhance_setting(self.voice_enhance, self.enhance)
+    }
+
+    // Length of the clip on the timeline, after the speed change
+    fn timeline_length(&self) -> f64 {
+        self.duration / self.speed()
+    }
+
+    fn timeline_end(&self) -> f64 {
+        self.start_time + self.timeline_length()
+    }
+
+    // Section of the source file this clip plays, as (start, end) in seconds
+    fn source_range(&self) -> Result<(f64, f64), String> {
+        if self.trim_start < 0.0 || self.trim_end < 0.0 {
+            return Err(format!("Clip '{}' has a negative trim value", self.source_file));
+        }
+
+        let mut end = self.trim_start + self.duration;
+        if let Some(source_duration) = self.source_duration.filter(|d| *d > 0.0) {
+            end = end.min(source_duration - self.trim_end);
+        }
+
+        if end <= self.trim_start {
+            return Err(format!(
+                "Clip '{}' has no audio left after trimming (start {:.3}s, end {:.3}s)",
+                self.source_file, self.trim_start, end
+            ));
+        }
+
+        Ok((self.trim_start, end))
+    }
+}
+
+// Internal structure with track volume
+#[derive(Debug, Clone)]
+struct ClipWithVolume {
+    clip: TimelineClip,
+    track_volume: f64,
+    track_pan: f64,
+    track_enhance: Option<AudioEnhanceOptions>,
+    track_denoise: Option<f64>,
+    track_index: usize,
+}
+
+// Voice clean-up run on a clip before its fades and volume: a high-pass for rumble,
+// click removal for plosives and mouth noise, a de-esser and gentle compression
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+struct AudioEnhanceOptions {
+    // High-pass cutoff in Hz (0 = off)
+    highpass_hz: f64,
+    declick: bool,
+    deesser: bool,
+    compressor: bool,
+    compressor_threshold_db: f64,
+    compressor_ratio: f64,
+}
+
+impl Default for AudioEnhanceOptions {
+    fn default() -> Self {
+        AudioEnhanceOptions {
+            highpass_hz: 80.0,
+            declick: true,
+            deesser: true,
+            compressor: true,
+            compressor_threshold_db: -18.0,
+            compressor_ratio: 3.0,
+        }
+    }
+}
+
+impl AudioEnhanceOptions {
+    fn filters(&self) -> Result<Vec<String>, String> {
+        let mut filters = Vec::new();
+        if self.highpass_hz != 0.0 {
+            if !(20.0..=500.0).contains(&self.highpass_hz) {
+                return Err(format!("Invalid high-pass cutoff {} Hz: must be between 20 and 500, or 0 for none", self.highpass_hz));
+            }
+            filters.push(format!("highpass=f={}", self.highpass_hz));
+        }
+        if self.declick {
+            filters.push("adeclick".to_string());
+        }
+        if self.deesser {
+            filters.push("deesser".to_string());
+        }
+        if self.compressor {
+            if !(-60.0..=0.0).contains(&self.compressor_threshold_db) {
+                return Err(format!("Invalid compressor threshold {} dB: must be between -60 and 0", self.compressor_threshold_db));
+            }
+            if !(1.0..=20.0).contains(&self.compressor_ratio) {
+                return Err(format!("Invalid compressor ratio {}: must be between 1 and 20", self.compressor_ratio));
+            }
+            filters.push(format!(
+                "acompressor=threshold={:.6}:ratio={}",
+                10f64.powf(self.compressor_threshold_db / 20.0),
+                self.compressor_ratio
+            ));
+        }
+        Ok(filters)
+    }
+}
+
+// `enhance` when given, otherwise the default chain if `enabled`
+fn enhance_setting(enabled: bool, enhance: Option<AudioEnhanceOptions>) -> Option<AudioEnhanceOptions> {
+    enhance.or(if enabled { Some(AudioEnhanceOptions::default()) } else { None })
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct TimelineTrack {
+    clips: Vec<TimelineClip>,
+    volume: f64,
+    // Stereo position from -1.0 (left) through 0.0 (center) to 1.0 (right)
+    #[serde(default)]
+    pan: f64,
+    #[serde(default)]
+    muted: bool,
+    // When any track is soloed, only soloed tracks are exported
+    #[serde(default)]
+    solo: bool,
+    // Voice clean-up for every clip on the track that doesn't set its own
+    #[serde(default)]
+    voice_enhance: bool,
+    #[serde(default)]
+    enhance: Option<AudioEnhanceOptions>,
+    // Inline noise reduction in dB for constant background noise (none when unset)
+    #[serde(default)]
+    denoise_strength: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct TimelineData {
+    tracks: Vec<TimelineTrack>,
+}
+
+// A named point in the timeline shown in the player's chapter list
+#[derive(Deserialize, Debug, Clone)]
+struct ChapterMarker {
+    title: String,
+    #[serde(alias = "start")]
+    start_time: f64,
+}
+
+// Chapters given with an export must be in order, with room for each one before
+// the next starts, and start before `total_duration` (all in timeline seconds)
+fn validate_chapters(chapters: &[ChapterMarker], total_duration: f64) -> Result<(), String> {
+    let mut previous: Option<&ChapterMarker> = None;
+    for chapter in chapters {
+        if !chapter.start_time.is_finite() || chapter.start_time < 0.0 {
+            return Err(format!("Chapter '{}' has an invalid start time {}", chapter.title, chapter.start_time));
+        }
+        if chapter.start_time >= total_duration {
+            return Err(format!(
+                "Chapter '{}' starts at {:.2}s, after the end of the export ({:.2}s)",
+                chapter.title, chapter.start_time, total_duration
+            ));
+        }
+        if let Some(previous) = previous {
+            if chapter.start_time <= previous.start_time {
+                return Err(format!(
+                    "Chapter '{}' at {:.2}s must start after '{}' at {:.2}s",
+                    chapter.title, chapter.start_time, previous.title, previous.start_time
+                ));
+            }
+        }
+        previous = Some(chapter);
+    }
+    Ok(())
+}
+
+// Tags written into the exported file. Empty fields are left out.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+struct VideoMetadata {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    #[serde(alias = "year")]
+    date: Option<String>,
+    comment: Option<String>,
+    // Dedicated cover image; falls back to the background image when `embed_cover` is set
+    cover_path: Option<String>,
+    embed_cover: bool,
+}
+
+impl VideoMetadata {
+    // One `-metadata key=value` pair per field. Each value is its own argument, so
+    // quotes and non-ASCII text reach ffmpeg untouched.
+    fn args(&self) -> Vec<String> {
+        let fields = [
+            ("title", &self.title),
+            ("artist", &self.artist),
+            ("album", &self.album),
+            ("date", &self.date),
+            ("comment", &self.comment),
+        ];
+        let mut args = Vec::new();
+        for (key, value) in fields {
+            if let Some(value) = value.as_deref().map(str::trim).filter(|value| !value.is_empty()) {
+                args.push("-metadata".to_string());
+                args.push(format!("{}={}", key, value));
+            }
+        }
+        args
+    }
+
+    fn cover_image<'a>(&'a self, background_image: &'a str) -> Option<&'a str> {
+        match self.cover_path.as_deref().filter(|path| !path.trim().is_empty()) {
+            Some(path) => Some(path),
+            None if self.embed_cover => Some(background_image),
+            None => None,
+        }
+    }
+}
+
+// Remux `output_path` with `cover_path` attached as its cover art. The cover is added in
+// a separate pass so the scaling filters and frame rate of the main encode don't touch it.
+fn embed_cover_art(job: &ExportJob, output_path: &PathBuf, cover_path: &str) -> Result<(), String> {
+    if !std::path::Path::new(cover_path).is_file() {
+        let err_msg = format!("Cover image not found: {}", cover_path);
+        eprintln!("ERROR: {}", err_msg);
+        return Err(err_msg);
+    }
+
+    let extension = output_path.extension().and_then(|ext| ext.to_str()).unwrap_or("mp4");
+    let temp_path = output_path.with_extension(format!("cover.{}", extension));
+    eprintln!("Embedding cover art {} into {}", cover_path, output_path.display());
+
+    let mut cmd = ffmpeg_command();
+    cmd.input(output_path.to_str().unwrap())
+        .input(cover_path)
+        .args(&[
+            "-map", "0",
+            "-map", "1:v:0",
+            "-c", "copy",
+            "-c:v:1", "mjpeg",
+            "-disposition:v:1", "attached_pic",
+            "-map_metadata", "0",
+            "-map_chapters", "0",
+        ])
+        .overwrite()
+        .output(temp_path.to_str().unwrap());
+
+    let result = run_ffmpeg(job, &mut cmd, |event| {
+        if let FfmpegEvent::Log(_level, msg) = event {
+            eprintln!("FFmpeg: {}", msg);
+        }
+    });
+    match result {
+        Ok(run) if run.success() => {}
+        Ok(run) => {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(format!("Failed to embed cover art: {}", run.error()));
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e);
+        }
+    }
+
+    std::fs::rename(&temp_path, output_path).map_err(|e| {
+        let _ = std::fs::remove_file(&temp_path);
+        let err_msg = format!("Failed to replace output with cover art version: {}", e);
+        eprintln!("ERROR: {}", err_msg);
+        err_msg
+    })
+}
+
+// Chapters named after the source files of the first track's clips
+fn chapters_from_clips(clips: &[ClipWithVolume]) -> Vec<ChapterMarker> {
+    let main_track = match clips.first() {
+        Some(first) => first.track_index,
+        None => return Vec::new(),
+    };
+    clips.iter()
+        .filter(|clip_with_vol| clip_with_vol.track_index == main_track)
+        .map(|clip_with_vol| ChapterMarker {
+            title: std::path::Path::new(&clip_with_vol.clip.source_file)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            start_time: clip_with_vol.clip.start_time,
+        })
+        .collect()
+}
+
+// Escape the characters FFMETADATA treats specially
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// FFMETADATA document with one [CHAPTER] per marker. Markers are sorted, shifted by
+// `offset`, clamped to `total_duration` and each runs until the next one starts.
+fn build_chapters_metadata(chapters: &[ChapterMarker], offset: f64, total_duration: f64) -> String {
+    let mut starts: Vec<(f64, &str)> = chapters.iter()
+        .filter(|chapter| chapter.start_time.is_finite())
+        .map(|chapter| ((chapter.start_time + offset).clamp(0.0, total_duration), chapter.title.as_str()))
+        .filter(|(start, _)| *start < total_duration)
+        .collect();
+    starts.sort_by(|a, b| a.0.total_cmp(&b.0));
+    // Two chapters can't start at the same moment; keep the first one
+    starts.dedup_by(|b, a| (b.0 * 1000.0).round() == (a.0 * 1000.0).round());
+
+    let mut metadata = String::from(";FFMETADATA1\n");
+    for (i, (start, title)) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map(|next| next.0).unwrap_or(total_duration);
+        metadata.push_str(&format!(
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            (start * 1000.0).round() as u64,
+            (end * 1000.0).round() as u64,
+            escape_ffmetadata(title)
+        ));
+    }
+    metadata
+}
+
+// Project data structure for export/import
+#[derive(Serialize, Deserialize, Debug)]
+struct ProjectClip {
+    id: String,
+    source_file: String,
+    source_name: String,
+    track_id: String,
+    start_time: f64,
+    duration: f64,
+    trim_start: f64,
+    trim_end: f64,
+    source_duration: f64,
+    #[serde(default = "default_clip_volume")]
+    volume: f64,
+    #[serde(default = "default_clip_speed")]
+    speed: f64,
+}
+
+fn default_clip_volume() -> f64 {
+    1.0
+}
+
+fn default_clip_speed() -> f64 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ProjectTrack {
+    id: String,
+    track_type: String,
+    name: String,
+    clips: Vec<ProjectClip>,
+    volume: f64,
+    #[serde(default)]
+    pan: f64,
+    muted: bool,
+    #[serde(default)]
+    voice_enhance: bool,
+    #[serde(default)]
+    denoise_strength: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ProjectData {
+    version: String,
+    background_image: Option<String>,
+    background_color: Option<String>,
+    background_type: String, // "image" or "color"
+    background_style: String,
+    tracks: Vec<ProjectTrack>,
+    video_title: String,
+    video_description: String,
+    #[serde(default)]
+    background_music: Option<BackgroundMusic>,
+}
+
+// Project file version written by the current frontend
+const PROJECT_VERSION: (u32, u32, u32) = (1, 0, 0);
+
+// "1.0.0" / "0.9" style version; files from before versioning count as 0.9
+fn parse_project_version(json: &serde_json::Value) -> Result<(u32, u32, u32), String> {
+    let version = match json.get("version") {
+        None | Some(serde_json::Value::Null) => return Ok((0, 9, 0)),
+        Some(version) => version.as_str().ok_or("Project file has an invalid version field")?,
+    };
+    let mut parts = version.trim().split('.').map(|part| part.parse::<u32>());
+    let mut next = || parts.next().unwrap_or(Ok(0)).map_err(|_| format!("Unrecognized project version '{}'", version));
+    Ok((next()?, next()?, next()?))
+}
+
+// Bring a project file from any supported version up to the current format
+fn migrate_project(mut json: serde_json::Value) -> Result<ProjectData, String> {
+    if !json.is_object() {
+        return Err("Not a project file: expected a JSON object".to_string());
+    }
+
+    let version = parse_project_version(&json)?;
+    if version.0 > PROJECT_VERSION.0 {
+        return Err(format!(
+            "This project was saved by a newer version of the app (format {}.{}.{}); please update to open it",
+            version.0, version.1, version.2
+        ));
+    }
+
+    if version < (1, 0, 0) {
+        eprintln!("Migrating project from version {}.{}.{}", version.0, version.1, version.2);
+        migrate_project_from_0_9(&mut json);
+    }
+    json["version"] = serde_json::json!(format!("{}.{}.{}", PROJECT_VERSION.0, PROJECT_VERSION.1, PROJECT_VERSION.2));
+
+    serde_json::from_value(json).map_err(|e| format!("Project file is missing or has an invalid field: {}", e))
+}
+
+// 0.9 projects called the track kind "type", had no per-clip volume or source length,
+// and could leave out the background and description settings
+fn migrate_project_from_0_9(json: &mut serde_json::Value) {
+    fn set_default(object: &mut serde_json::Value, key: &str, value: serde_json::Value) {
+        if let Some(map) = object.as_object_mut() {
+            map.entry(key).or_insert(value);
+        }
+    }
+
+    let has_image = json.get("background_image").map(|v| !v.is_null()).unwrap_or(false);
+    set_default(json, "background_image", serde_json::Value::Null);
+    set_default(json, "background_color", serde_json::Value::Null);
+    set_default(json, "background_type", serde_json::json!(if has_image { "image" } else { "color" }));
+    set_default(json, "background_style", serde_json::json!("cover"));
+    set_default(json, "video_title", serde_json::json!(""));
+    set_default(json, "video_description", serde_json::json!(""));
+    set_default(json, "tracks", serde_json::json!([]));
+
+    if let Some(tracks) = json["tracks"].as_array_mut() {
+        for track in tracks {
+            if let Some(map) = track.as_object_mut() {
+                if !map.contains_key("track_type") {
+                    let track_type = map.remove("type").unwrap_or_else(|| serde_json::json!("audio"));
+                    map.insert("track_type".to_string(), track_type);
+                }
+            }
+            set_default(track, "volume", serde_json::json!(1.0));
+            set_default(track, "muted", serde_json::json!(false));
+            set_default(track, "clips", serde_json::json!([]));
+
+            if let Some(clips) = track["clips"].as_array_mut() {
+                for clip in clips {
+                    let number = |key: &str| clip.get(key).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                    // The source ran from before trim_start to after trim_end
+                    let source_duration = number("trim_start") + number("duration") + number("trim_end");
+                    let source_name = clip.get("source_file")
+                        .and_then(|v| v.as_str())
+                        .and_then(|path| std::path::Path::new(path).file_name())
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    set_default(clip, "source_duration", serde_json::json!(source_duration));
+                    set_default(clip, "source_name", serde_json::json!(source_name));
+                    set_default(clip, "trim_start", serde_json::json!(0.0));
+                    set_default(clip, "trim_end", serde_json::json!(0.0));
+                    set_default(clip, "volume", serde_json::json!(1.0));
+                }
+            }
+        }
+    }
+}
+
+// Where an export job is up to, as reported by get_export_status and export-finished
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum ExportJobStatus {
+    Running { stage: Option<ExportStage>, progress: Option<f64> },
+    Done { output_path: String },
+    Failed { error: WavecastError },
+    Cancelled,
+}
+
+// Sent once per job when it stops running, whatever the outcome
+#[derive(Clone, Serialize)]
+struct ExportFinished {
+    job_id: u64,
+    status: ExportJobStatus,
+}
+
+// One export started by a convert command, with its FFmpeg process so it can be cancelled
+struct ExportJob {
+    id: u64,
+    cancelled: AtomicBool,
+    child: Mutex<Option<FfmpegChild>>,
+    // Claimed once the output location is known, so another job can't write to it too
+    output_path: Mutex<Option<PathBuf>>,
+    status: Mutex<ExportJobStatus>,
+}
+
+impl ExportJob {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn is_running(&self) -> bool {
+        matches!(*self.status.lock().unwrap(), ExportJobStatus::Running { .. })
+    }
+
+    fn set_stage(&self, new_stage: &ExportStage) {
+        if let ExportJobStatus::Running { stage, .. } = &mut *self.status.lock().unwrap() {
+            *stage = Some(new_stage.clone());
+        }
+    }
+
+    fn set_progress(&self, percent: f64) {
+        if let ExportJobStatus::Running { progress, .. } = &mut *self.status.lock().unwrap() {
+            *progress = Some(percent);
+        }
+    }
+
+    fn cancel(&self) -> Result<(), String> {
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(child) = self.child.lock().unwrap().as_mut() {
+            child.kill()
+                .map_err(|e| format!("Failed to stop FFmpeg: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+// Registry of export jobs. Finished jobs stay here until the frontend acknowledges them
+#[derive(Default)]
+struct ExportJobs {
+    next_id: std::sync::atomic::AtomicU64,
+    jobs: Mutex<std::collections::HashMap<u64, std::sync::Arc<ExportJob>>>,
+}
+
+impl ExportJobs {
+    fn start(&self) -> std::sync::Arc<ExportJob> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let job = std::sync::Arc::new(ExportJob {
+            id,
+            cancelled: AtomicBool::new(false),
+            child: Mutex::new(None),
+            output_path: Mutex::new(None),
+            status: Mutex::new(ExportJobStatus::Running { stage: None, progress: None }),
+        });
+        self.jobs.lock().unwrap().insert(id, job.clone());
+        job
+    }
+
+    fn get(&self, id: u64) -> Result<std::sync::Arc<ExportJob>, WavecastError> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+            .ok_or_else(|| WavecastError::invalid("id", format!("No export job with id {}", id)))
+    }
+
+    // Reserve `path` for `job`, failing if another running job is already writing there
+    fn claim_output(&self, job: &ExportJob, path: &PathBuf) -> Result<(), WavecastError> {
+        let jobs = self.jobs.lock().unwrap();
+        let taken = jobs.values().any(|other| {
+            other.id != job.id
+                && other.is_running()
+                && other.output_path.lock().unwrap().as_ref() == Some(path)
+        });
+        if taken {
+            return Err(WavecastError::invalid(
+                "output_path",
+                format!("Another export is already writing to {}", path.display()),
+            ));
+        }
+        *job.output_path.lock().unwrap() = Some(path.clone());
+        Ok(())
+    }
+}
+
+// How many of FFmpeg's last log lines are kept for error reports
+const STDERR_TAIL_LINES: usize = 50;
+
+// Ring buffer of the most recent lines FFmpeg logged
+#[derive(Default)]
+struct StderrTail {
+    lines: std::collections::VecDeque<String>,
+}
+
+impl StderrTail {
+    fn record(&mut self, event: &FfmpegEvent) {
+        let line = match event {
+            FfmpegEvent::Log(_level, msg) => msg,
+            FfmpegEvent::Error(msg) => msg,
+            _ => return,
+        };
+        if self.lines.len() == STDERR_TAIL_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.clone());
+    }
+
+    fn text(&self) -> String {
+        self.lines.iter().map(String::as_str).collect::<Vec<_>>().join("\n")
+    }
+}
+
+// How an FFmpeg run ended, with the tail of its log for when it failed
+struct FfmpegRun {
+    status: std::process::ExitStatus,
+    stderr_tail: StderrTail,
+}
+
+impl FfmpegRun {
+    fn success(&self) -> bool {
+        self.status.success()
+    }
+
+    fn code(&self) -> Option<i32> {
+        self.status.code()
+    }
+
+    fn error(&self) -> WavecastError {
+        WavecastError::ffmpeg_failed(self.code(), self.stderr_tail.text())
+    }
+
+    // FFmpeg was run with -n and the output file already existed
+    fn output_exists(&self) -> bool {
+        !self.success() && self.stderr_tail.text().contains("already exists")
+    }
+}
+
+// Spawn FFmpeg, hand every event to `on_event`, then wait for it to exit.
+// The child is parked in the export job while running so cancel_export can kill it.
+fn run_ffmpeg<F: FnMut(FfmpegEvent)>(
+    job: &ExportJob,
+    cmd: &mut FfmpegCommand,
+    mut on_event: F,
+) -> Result<FfmpegRun, String> {
+    if job.is_cancelled() {
+        return Err("cancelled".to_string());
+    }
+
+    let mut child = cmd.spawn()
+        .map_err(|e| {
+            let err_msg = format!("Failed to spawn FFmpeg: {}", e);
+            eprintln!("ERROR: {}", err_msg);
+            err_msg
+        })?;
+
+    let iter = child.iter()
+        .map_err(|e| {
+            let err_msg = format!("Failed to get FFmpeg iterator: {}", e);
+            eprintln!("ERROR: {}", err_msg);
+            err_msg
+        })?;
+
+    {
+        let mut slot = job.child.lock().unwrap();
+        *slot = Some(child);
+        // cancel_export may have fired between the spawn and now
+        if job.is_cancelled() {
+            if let Some(child) = slot.as_mut() {
+                let _ = child.kill();
+            }
+        }
+    }
+
+    let mut stderr_tail = StderrTail::default();
+    for event in iter {
+        // Stop consuming output as soon as a cancel comes in; the kill closes the pipes anyway
+        if job.is_cancelled() {
+            break;
+        }
+        stderr_tail.record(&event);
+        on_event(event);
+    }
+
+    let mut child = job.child.lock().unwrap().take()
+        .ok_or_else(|| "FFmpeg process handle was lost".to_string())?;
+    if job.is_cancelled() {
+        // cancel_export may have set the flag without reaching the child in time
+        let _ = child.kill();
+    }
+    let result = child.wait()
+        .map_err(|e| {
+            let err_msg = format!("Failed to execute FFmpeg: {}", e);
+            eprintln!("ERROR: {}", err_msg);
+            err_msg
+        })?;
+
+    if job.is_cancelled() {
+        eprintln!("FFmpeg process was cancelled");
+        return Err("cancelled".to_string());
+    }
+
+    Ok(FfmpegRun { status: result, stderr_tail })
+}
+
+// Run the encode `build` sets up, writing to `output`. FFmpeg won't overwrite unless the
+// target allows it, so a file that appeared there after the name was picked makes it
+// fail; the next free number is then claimed and the encode runs once more
+fn run_encode<B, F>(
+    app: &tauri::AppHandle,
+    job: &ExportJob,
+    output: &mut OutputTarget,
+    build: B,
+    mut on_event: F,
+) -> Result<FfmpegRun, WavecastError>
+where
+    B: Fn() -> FfmpegCommand,
+    F: FnMut(FfmpegEvent),
+{
+    let mut cmd = build();
+    cmd.args(output.args());
+    let run = run_ffmpeg(job, &mut cmd, &mut on_event)?;
+    if output.replace || !run.output_exists() {
+        return Ok(run);
+    }
+
+    let taken = output.path.clone();
+    output.renumber();
+    eprintln!("WARNING: {} appeared during the export, retrying as {}", taken.display(), output.path.display());
+    app.state::<ExportJobs>().claim_output(job, &output.path)?;
+    let mut cmd = build();
+    cmd.args(output.args());
+    Ok(run_ffmpeg(job, &mut cmd, &mut on_event)?)
+}
+
+// How many recent estimates the ETA is averaged over so it doesn't jump around
+const ETA_SMOOTHING_SAMPLES: usize = 5;
+
+// Moving average of the remaining encode time
+#[derive(Default)]
+struct EtaEstimate {
+    recent: std::collections::VecDeque<f64>,
+}
+
+impl EtaEstimate {
+    // None until there is a length to count down from and FFmpeg reports a speed
+    fn update(&mut self, remaining_seconds: f64, speed: f32) -> Option<f64> {
+        if remaining_seconds <= 0.0 || !speed.is_finite() || speed <= 0.0 {
+            return None;
+        }
+        if self.recent.len() == ETA_SMOOTHING_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(remaining_seconds / speed as f64);
+        Some(self.recent.iter().sum::<f64>() / self.recent.len() as f64)
+    }
+}
+
+fn export_progress(job_id: u64, progress: &FfmpegProgress, total_duration: f64, eta: &mut EtaEstimate) -> ExportProgress {
+    // Parse time string (format: "HH:MM:SS.ms" or similar)
+    let current_time = parse_time_to_seconds(&progress.time);
+    let progress_pct = if total_duration > 0.0 {
+        (current_time / total_duration * 100.0).min(100.0)
+    } else {
+        0.0
+    };
+    let remaining_seconds = if total_duration > 0.0 { (total_duration - current_time).max(0.0) } else { 0.0 };
+
+    ExportProgress {
+        job_id,
+        frame: progress.frame,
+        fps: progress.fps,
+        time: progress.time.clone(),
+        progress: progress_pct,
+        speed: Some(progress.speed).filter(|speed| speed.is_finite() && *speed > 0.0),
+        bitrate_kbps: Some(progress.bitrate_kbps).filter(|bitrate| bitrate.is_finite() && *bitrate > 0.0),
+        out_size_bytes: Some(progress.size_kb as u64 * 1024).filter(|size| *size > 0),
+        eta_seconds: eta.update(remaining_seconds, progress.speed),
+    }
+}
+
+// Send export-progress and remember the percentage for get_export_status
+fn emit_export_progress(app: &tauri::AppHandle, job: &ExportJob, progress: ExportProgress) {
+    job.set_progress(progress.progress);
+    let _ = app.emit("export-progress", progress);
+}
+
+// Progress callback for encodes of `known_duration` seconds. When that is unknown (0)
+// the length is taken from FFmpeg's report of the main audio input's duration
+fn encode_progress_handler<'a>(
+    app: &'a tauri::AppHandle,
+    job: &'a ExportJob,
+    audio_input: u32,
+    known_duration: f64,
+) -> impl FnMut(FfmpegEvent) + 'a {
+    let mut total_duration = known_duration;
+    let mut eta = EtaEstimate::default();
+    move |event| match event {
+        FfmpegEvent::ParsedDuration(duration) if duration.input_index == audio_input && known_duration <= 0.0 => {
+            total_duration = duration.duration;
+        }
+        FfmpegEvent::Progress(progress) => {
+            emit_export_progress(app, job, export_progress(job.id, &progress, total_duration, &mut eta));
+        }
+        _ => {}
+    }
+}
+
+// One `file '...'` line of a concat demuxer list. Backslashes become forward slashes
+// for Windows, and a quote closes the string, is escaped, then reopens it: ' -> '\''
+fn concat_list_entry(path: &str) -> String {
+    format!("file '{}'", path.replace('\\', "/").replace('\'', "'\\''"))
+}
+
+// Whether the inputs can be joined with the concat demuxer and `-c copy`. That needs
+// the same codec, sample rate and channel count throughout; anything that can't be
+// probed counts as different
+fn concat_can_copy(paths: &[String]) -> bool {
+    let probes = match paths.iter().map(|path| probe_audio(path)).collect::<Result<Vec<_>, String>>() {
+        Ok(probes) => probes,
+        Err(e) => {
+            eprintln!("Warning: could not probe the audio inputs, re-encoding them: {}", e);
+            return false;
+        }
+    };
+    let first = &probes[0];
+    probes.iter().all(|probe| {
+        probe.codec == first.codec && probe.sample_rate == first.sample_rate && probe.channels == first.channels
+    })
+}
+
+// concat filter joining `count` audio inputs after converting each to 48 kHz stereo s16,
+// so inputs of any format line up. The result is labelled [joined]
+fn resampled_concat_filter(count: usize) -> String {
+    let mut filter = String::new();
+    for i in 0..count {
+        filter.push_str(&format!("[{}:a:0]aresample=48000,aformat=sample_fmts=s16:channel_layouts=stereo[c{}];", i, i));
+    }
+    for i in 0..count {
+        filter.push_str(&format!("[c{}]", i));
+    }
+    filter.push_str(&format!("concat=n={}:v=0:a=1[joined]", count));
+    filter
+}
+
+// An intermediate file that is deleted when the guard goes out of scope, so it
+// doesn't outlive the export whether that succeeds, fails or is cancelled
+struct TempFile {
+    path: PathBuf,
+}
+
+// Numbers the temp files of this process so concurrent exports don't collide
+static TEMP_FILE_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+impl TempFile {
+    fn new(path: PathBuf) -> Self {
+        TempFile { path }
+    }
+
+    // A fresh name in the OS temp dir, e.g. wavecast-concat-1234-0.txt
+    fn unique(name: &str, extension: &str) -> Self {
+        let n = TEMP_FILE_COUNTER.fetch_add(1, Ordering::SeqCst);
+        TempFile::new(std::env::temp_dir().join(format!(
+            "wavecast-{}-{}-{}.{}",
+            name,
+            std::process::id(),
+            n,
+            extension
+        )))
+    }
+
+    fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    fn to_str(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+
+    // Hand the file over to the caller instead of deleting it
+    fn keep(mut self) -> PathBuf {
+        std::mem::take(&mut self.path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if std::fs::remove_file(&self.path).is_ok() {
+            eprintln!("Removed temporary file {}", self.path.display());
+        }
+    }
+}
+
+// Where an export is up to, sent as export-stage so the UI can say more than 0%
+#[derive(Clone, Serialize, Debug)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+enum ExportStage {
+    DownloadingFfmpeg,
+    PreparingInputs,
+    // Files joined so far, counted from the concat pass's progress
+    ConcatenatingAudio { current: usize, total: usize },
+    Encoding,
+    Finalizing,
+    Done,
+}
+
+// export-stage payload: the stage's fields plus the job they belong to
+#[derive(Clone, Serialize)]
+struct ExportStageEvent {
+    job_id: u64,
+    #[serde(flatten)]
+    stage: ExportStage,
+}
+
+fn emit_export_stage(app: &tauri::AppHandle, job: &ExportJob, stage: ExportStage) {
+    eprintln!("Export job {} stage: {:?}", job.id, stage);
+    job.set_stage(&stage);
+    let _ = app.emit("export-stage", ExportStageEvent { job_id: job.id, stage });
+}
+
+// Something the user should know about that didn't stop the export,
+// like a requested encoder being replaced
+#[derive(Clone, Serialize)]
+struct ExportWarning {
+    job_id: u64,
+    message: String,
+}
+
+fn emit_export_warning(app: &tauri::AppHandle, job: &ExportJob, message: String) {
+    let _ = app.emit("export-warning", ExportWarning { job_id: job.id, message });
+}
+
+// ffmpeg_available for exports. A first-run download goes through download_ffmpeg
+// so the frontend gets progress instead of a frozen-looking export
+fn ensure_ffmpeg(app: &tauri::AppHandle, job: &ExportJob) -> Result<(), String> {
+    if ffmpeg_override().is_none() && !ffmpeg_is_installed() && ffmpeg_download_allowed() {
+        emit_export_stage(app, job, ExportStage::DownloadingFfmpeg);
+        tauri::async_runtime::block_on(download_ffmpeg(app))?;
+    }
+    ffmpeg_available()
+}
+
+// FFmpeg chosen in settings, used instead of the bundled download when set
+static FFMPEG_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+fn ffmpeg_override() -> Option<PathBuf> {
+    FFMPEG_OVERRIDE.lock().unwrap().clone()
+}
+
+// Turned off in settings on machines that must not fetch binaries from the internet
+static FFMPEG_DOWNLOAD_ALLOWED: AtomicBool = AtomicBool::new(true);
+
+fn ffmpeg_download_allowed() -> bool {
+    FFMPEG_DOWNLOAD_ALLOWED.load(Ordering::SeqCst)
+}
+
+// FfmpegCommand running whichever FFmpeg is configured
+fn ffmpeg_command() -> FfmpegCommand {
+    match ffmpeg_override() {
+        Some(path) => FfmpegCommand::new_with_path(path),
+        None => FfmpegCommand::new(),
+    }
+}
+
+// The ffprobe next to a configured FFmpeg, or the bundled one
+fn ffprobe_command() -> std::process::Command {
+    let sibling = ffmpeg_override()
+        .and_then(|ffmpeg| ffmpeg.parent().map(|dir| dir.join(format!("ffprobe{}", std::env::consts::EXE_SUFFIX))))
+        .filter(|path| path.is_file());
+    std::process::Command::new(sibling.unwrap_or_else(ffprobe_path))
+}
+
+// Make sure FFmpeg can run, downloading the bundled build unless one is configured
+fn ffmpeg_available() -> Result<(), String> {
+    if ffmpeg_override().is_some() || ffmpeg_is_installed() {
+        return Ok(());
+    }
+    if !ffmpeg_download_allowed() {
+        let err_msg = "FFmpeg was not found. Install FFmpeg and set its path in Settings, or allow Wavecast to download it".to_string();
+        eprintln!("ERROR: {}", err_msg);
+        return Err(err_msg);
+    }
+    auto_download().map_err(|e| {
+        let err_msg = format!("Failed to download FFmpeg: {}", e);
+        eprintln!("ERROR: {}", err_msg);
+        err_msg
+    })
+}
+
+// "ffmpeg version 7.0.1-static https://..." -> "7.0.1-static"
+fn ffmpeg_version_of(path: &std::path::Path) -> Result<String, String> {
+    let output = std::process::Command::new(path)
+        .arg("-version")
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", path.display(), e))?;
+    if !output.status.success() {
+        return Err(format!("{} -version failed", path.display()));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("ffmpeg version "))
+        .and_then(|rest| rest.split_whitespace().next())
+        .map(str::to_string)
+        .ok_or_else(|| format!("{} is not FFmpeg", path.display()))
+}
+
+// What the settings screen shows about FFmpeg. `source` is "bundled" for
+// ffmpeg-sidecar's download and "system" for a configured or PATH install.
+#[derive(Clone, Serialize, Debug)]
+struct FfmpegStatus {
+    available: bool,
+    version: Option<String>,
+    source: String,
+    download_allowed: bool,
+}
+
+fn ffmpeg_status() -> FfmpegStatus {
+    let (path, source) = match ffmpeg_override() {
+        Some(path) => (path, "system"),
+        None => match sidecar_path() {
+            Ok(path) if path.exists() => (path, "bundled"),
+            _ => (ffmpeg_path(), "system"),
+        },
+    };
+    let version = ffmpeg_version_of(&path).ok();
+    FfmpegStatus {
+        available: version.is_some(),
+        version,
+        source: source.to_string(),
+        download_allowed: ffmpeg_download_allowed(),
+    }
+}
+
+// Payload of ffmpeg-download-progress
+#[derive(Clone, Serialize)]
+struct FfmpegDownloadProgress {
+    downloaded_bytes: u64,
+    total_bytes: Option<u64>,
+    percent: Option<f64>,
+}
+
+// How often download progress is reported
+const FFMPEG_DOWNLOAD_PROGRESS_STEP: u64 = 1024 * 1024;
+
+// ffmpeg-sidecar's auto_download, but streamed so progress can be reported
+async fn download_ffmpeg(app: &tauri::AppHandle) -> Result<(), String> {
+    use futures_util::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    let url = ffmpeg_download_url().map_err(|e| format!("No FFmpeg download for this platform: {}", e))?;
+    let dir = sidecar_dir().map_err(|e| format!("Failed to find the FFmpeg directory: {}", e))?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let archive = dir.join(url.rsplit('/').next().unwrap_or("ffmpeg-download"));
+    eprintln!("Downloading FFmpeg from {} to {}", url, archive.display());
+
+    let response = reqwest::get(url)
+        .await
+        .map_err(|e| format!("Failed to download FFmpeg: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to download FFmpeg: HTTP {}", response.status()));
+    }
+    let total_bytes = response.content_length();
+    let mut file = tokio::fs::File::create(&archive)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", archive.display(), e))?;
+
+    let mut downloaded_bytes = 0;
+    let mut reported_bytes = 0;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Failed to download FFmpeg: {}", e))?;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", archive.display(), e))?;
+        downloaded_bytes += chunk.len() as u64;
+        if downloaded_bytes - reported_bytes >= FFMPEG_DOWNLOAD_PROGRESS_STEP || Some(downloaded_bytes) == total_bytes {
+            reported_bytes = downloaded_bytes;
+            let _ = app.emit("ffmpeg-download-progress", FfmpegDownloadProgress {
+                downloaded_bytes,
+                total_bytes,
+                percent: total_bytes
+                    .filter(|total| *total > 0)
+                    .map(|total| (downloaded_bytes as f64 / total as f64 * 100.0).min(100.0)),
+            });
+        }
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", archive.display(), e))?;
+    drop(file);
+
+    eprintln!("Unpacking FFmpeg...");
+    let unpack_archive = archive.clone();
+    tokio::task::spawn_blocking(move || unpack_ffmpeg(&unpack_archive, &dir))
+        .await
+        .map_err(|e| format!("Failed to unpack FFmpeg: {}", e))?
+        .map_err(|e| format!("Failed to unpack FFmpeg: {}", e))?;
+    let _ = std::fs::remove_file(&archive);
+
+    let status = ffmpeg_status();
+    eprintln!("FFmpeg ready: {:?}", status);
+    let _ = app.emit("ffmpeg-ready", status);
+    Ok(())
+}
+
+// Point the app at an installed FFmpeg (checked with -version), or with no path go
+// back to the bundled build, downloading it with progress events if needed and allowed
+#[tauri::command]
+async fn configure_ffmpeg(
+    app: tauri::AppHandle,
+    path: Option<String>,
+    allow_download: Option<bool>,
+) -> Result<FfmpegStatus, WavecastError> {
+    if let Some(allow_download) = allow_download {
+        eprintln!("FFmpeg download allowed: {}", allow_download);
+        FFMPEG_DOWNLOAD_ALLOWED.store(allow_download, Ordering::SeqCst);
+    }
+    match path.filter(|path| !path.trim().is_empty()) {
+        Some(path) => {
+            require_file(&path)?;
+            let path = PathBuf::from(path);
+            let version = ffmpeg_version_of(&path).map_err(|reason| WavecastError::invalid("path", reason))?;
+            eprintln!("Using FFmpeg {} at {}", version, path.display());
+            *FFMPEG_OVERRIDE.lock().unwrap() = Some(path);
+        }
+        None => {
+            *FFMPEG_OVERRIDE.lock().unwrap() = None;
+            if !ffmpeg_is_installed() && ffmpeg_download_allowed() {
+                download_ffmpeg(&app).await?;
+            }
+        }
+    }
+    Ok(ffmpeg_status())
+}
+
+#[tauri::command(async)]
+fn get_ffmpeg_status() -> FfmpegStatus {
+    ffmpeg_status()
+}
+
+// Remove whatever a cancelled export left behind and tell the frontend to reset
+fn finish_cancelled_export(app: &tauri::AppHandle, job: &ExportJob, leftovers: &[&PathBuf]) {
+    for path in leftovers {
+        let _ = std::fs::remove_file(path);
+    }
+    let _ = app.emit("export-cancelled", job.id);
+}
+
+// EBU R128 true peak and loudness range used alongside the integrated target
+const LOUDNORM_TRUE_PEAK: f64 = -1.5;
+const LOUDNORM_LRA: f64 = 11.0;
+
+// Values printed by loudnorm's analysis pass (print_format=json)
+#[derive(Debug, Clone, Deserialize)]
+struct LoudnessMeasurement {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    target_offset: String,
+}
+
+// Sent when the loudness analysis finds the mix peaking above 0 dBFS
+#[derive(Clone, Serialize)]
+struct MixClipping {
+    job_id: u64,
+    true_peak: f64,
+}
+
+impl LoudnessMeasurement {
+    // Silent input measures as -inf, which loudnorm won't accept back
+    fn is_usable(&self) -> bool {
+        [&self.input_i, &self.input_tp, &self.input_lra, &self.input_thresh, &self.target_offset]
+            .iter()
+            .all(|value| value.parse::<f64>().map(f64::is_finite).unwrap_or(false))
+    }
+}
+
+// Sample rate every clip is brought to before mixing, so sources recorded at
+// different rates mix without drift
+const MIX_SAMPLE_RATE: u32 = 48000;
+
+// -ar/-ac flags for the requested output sample rate and channel count
+fn output_audio_args(sample_rate: Option<u32>, channels: Option<u32>) -> Result<Vec<String>, WavecastError> {
+    let mut args: Vec<String> = Vec::new();
+    if let Some(sample_rate) = sample_rate {
+        if sample_rate != 44100 && sample_rate != 48000 {
+            return Err(WavecastError::invalid("sample_rate", format!(
+                "Unsupported sample rate {} Hz (expected 44100 or 48000)",
+                sample_rate
+            )));
+        }
+        args.extend(["-ar".to_string(), sample_rate.to_string()]);
+    }
+    if let Some(channels) = channels {
+        if channels != 1 && channels != 2 {
+            return Err(WavecastError::invalid("channels", format!(
+                "Unsupported channel count {} (expected 1 or 2)",
+                channels
+            )));
+        }
+        args.extend(["-ac".to_string(), channels.to_string()]);
+    }
+    Ok(args)
+}
+
+fn validate_loudness_target(target: Option<f64>) -> Result<Option<f64>, String> {
+    match target {
+        Some(lufs) if !(-70.0..=-5.0).contains(&lufs) => {
+            Err(format!("Invalid loudness target {} LUFS: must be between -70 and -5", lufs))
+        }
+        _ => Ok(target),
+    }
+}
+
+// loudnorm stage for `target` LUFS, using the first pass's values when there are any.
+// loudnorm works at 192 kHz internally, so resample back down afterwards
+fn loudnorm_filter(target: f64, measured: Option<&LoudnessMeasurement>) -> String {
+    let mut filter = format!("loudnorm=I={}:TP={}:LRA={}", target, LOUDNORM_TRUE_PEAK, LOUDNORM_LRA);
+    if let Some(m) = measured {
+        filter.push_str(&format!(
+            ":measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+            m.input_i, m.input_tp, m.input_lra, m.input_thresh, m.target_offset
+        ));
+    }
+    filter.push_str(",aresample=48000");
+    filter
+}
+
+// First loudnorm pass: run `audio_filter` over `inputs` without encoding anything
+// and return what loudnorm measured at `label`. None means the mix can't be
+// measured (e.g. it's silent) and a single-pass loudnorm should be used instead
+fn measure_loudness(
+    app: &tauri::AppHandle,
+    job: &ExportJob,
+    inputs: &[&str],
+    audio_filter: &str,
+    label: &str,
+    target: f64,
+) -> Result<Option<LoudnessMeasurement>, String> {
+    let analysis_filter = format!(
+        "{};{}loudnorm=I={}:TP={}:LRA={}:print_format=json[measured]",
+        audio_filter, label, target, LOUDNORM_TRUE_PEAK, LOUDNORM_LRA
+    );
+    eprintln!("Loudness analysis filter: {}", analysis_filter);
+
+    let mut cmd = ffmpeg_command();
+    for input in inputs {
+        cmd.input(*input);
+    }
+    cmd.args(&["-filter_complex", &analysis_filter, "-map", "[measured]"])
+        .format("null")
+        .output("-");
+
+    let _ = app.emit("loudness-progress", StageProgress {
+        job_id: job.id,
+        phase: "measuring loudness".to_string(),
+        time: "00:00:00.00".to_string(),
+        progress: None,
+    });
+
+    // loudnorm logs its JSON report on stderr after a "[Parsed_loudnorm_N @ ...]" line
+    let mut in_report = false;
+    let mut report = String::new();
+    let result = run_ffmpeg(job, &mut cmd, |event| match event {
+        FfmpegEvent::Log(_level, msg) => {
+            if msg.contains("Parsed_loudnorm") {
+                in_report = true;
+                report.clear();
+            } else if in_report {
+                report.push_str(&msg);
+                report.push('\n');
+                if msg.trim() == "}" {
+                    in_report = false;
+                }
+            }
+        }
+        FfmpegEvent::Progress(progress) => {
+            let _ = app.emit("loudness-progress", StageProgress {
+                job_id: job.id,
+                phase: "measuring loudness".to_string(),
+                time: progress.time.clone(),
+                progress: None,
+            });
+        }
+        _ => {}
+    })?;
+
+    if !result.success() {
+        return Err(format!("Loudness analysis failed: {}", result.error()));
+    }
+
+    let start = report.find('{');
+    let end = report.rfind('}');
+    let measurement: LoudnessMeasurement = match (start, end) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&report[start..=end])
+            .map_err(|e| {
+                let err_msg = format!("Failed to parse loudness analysis: {}", e);
+                eprintln!("ERROR: {}", err_msg);
+                err_msg
+            })?,
+        _ => {
+            let err_msg = "FFmpeg loudness analysis produced no report".to_string();
+            eprintln!("ERROR: {}", err_msg);
+            return Err(err_msg);
+        }
+    };
+    eprintln!("Measured loudness: {:?}", measurement);
+
+    if let Some(true_peak) = measurement.input_tp.parse::<f64>().ok().filter(|tp| tp.is_finite() && *tp > 0.0) {
+        eprintln!("WARNING: Mix clips (true peak {:+.2} dBFS); the master limiter would prevent this", true_peak);
+        let _ = app.emit("mix-clipping-detected", MixClipping { job_id: job.id, true_peak });
+    }
+
+    if !measurement.is_usable() {
+        eprintln!("WARNING: Loudness could not be measured, falling back to single-pass loudnorm");
+        return Ok(None);
+    }
+    Ok(Some(measurement))
+}
+
+fn parse_time_to_seconds(time_str: &str) -> f64 {
+    // Parse FFmpeg time format (HH:MM:SS.ms, MM:SS.ms or just seconds)
+    let time_str = time_str.trim();
+
+    // FFmpeg reports "N/A" before the first frame, and negative times while
+    // it is still buffering; neither means any progress has been made
+    if time_str.is_empty() || time_str.eq_ignore_ascii_case("N/A") || time_str.starts_with('-') {
+        return 0.0;
+    }
+
+    let parts: Vec<f64> = match time_str.split(':').map(|p| p.parse::<f64>()).collect() {
+        Ok(parts) => parts,
+        Err(_) => return 0.0,
+    };
+
+    match parts.as_slice() {
+        // Just seconds (e.g., "123.45")
+        [seconds] => *seconds,
+        // MM:SS.ms format
+        [minutes, seconds] => minutes * 60.0 + seconds,
+        // HH:MM:SS.ms format
+        [hours, minutes, seconds] => hours * 3600.0 + minutes * 60.0 + seconds,
+        _ => 0.0
+    }
+}
+
+// Highest per-clip gain accepted (about +12 dB)
+const MAX_CLIP_VOLUME: f64 = 4.0;
+
+// Gap (in seconds) below which two clips on the same track count as touching
+const CROSSFADE_GAP_THRESHOLD: f64 = 0.05;
+
+// How a clip is joined to its neighbours on the same track
+#[derive(Debug, Clone, Copy, Default)]
+struct CrossfadeLink {
+    // Crossfade length from the previous clip into this one (0 = none)
+    into: f64,
+    // Crossfade length from this clip into `next`
+    out: f64,
+    next: Option<usize>,
+}
+
+// Work out which touching clips on the same track get crossfaded. A clip's own
+// `crossfade` value wins over the export-wide default; clips with a real gap
+// between them are left alone.
+fn crossfade_plan(clips: &[ClipWithVolume], default_crossfade: f64) -> Vec<CrossfadeLink> {
+    let mut plan = vec![CrossfadeLink::default(); clips.len()];
+
+    let mut order: Vec<usize> = (0..clips.len()).collect();
+    order.sort_by(|&a, &b| {
+        clips[a].track_index.cmp(&clips[b].track_index)
+            .then(clips[a].clip.start_time.total_cmp(&clips[b].clip.start_time))
+    });
+
+    for pair in order.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if clips[a].track_index != clips[b].track_index {
+            continue;
+        }
+
+        let a_end = clips[a].clip.timeline_end();
+        if (clips[b].clip.start_time - a_end).abs() > CROSSFADE_GAP_THRESHOLD {
+            continue;
+        }
+
+        let requested = clips[b].clip.crossfade.unwrap_or(default_crossfade);
+        if requested <= 0.0 {
+            continue;
+        }
+
+        // Neither side of the crossfade may be longer than the clip it belongs to
+        let duration = requested
+            .min(clips[a].clip.timeline_length())
+            .min(clips[b].clip.timeline_length());
+        plan[a].out = duration;
+        plan[a].next = Some(b);
+        plan[b].into = duration;
+    }
+
+    plan
+}
+
+// Most streams mixed by a single amix; more than this are mixed in groups first
+const AMIX_CHUNK_SIZE: usize = 16;
+
+// Join clips that are back-to-back pieces of the same source on the same track
+// (what splitting a clip leaves behind) into one clip, so they are trimmed once
+// instead of being crossfaded into themselves
+fn merge_contiguous_clips(clips: &[ClipWithVolume]) -> Vec<ClipWithVolume> {
+    let mut order: Vec<&ClipWithVolume> = clips.iter().collect();
+    order.sort_by(|a, b| {
+        a.track_index.cmp(&b.track_index)
+            .then(a.clip.start_time.total_cmp(&b.clip.start_time))
+    });
+
+    let mut merged: Vec<ClipWithVolume> = Vec::new();
+    for next in order {
+        if let Some(last) = merged.last_mut() {
+            let (a, b) = (&last.clip, &next.clip);
+            let contiguous = last.track_index == next.track_index
+                && a.source_file == b.source_file
+                && a.volume == b.volume
+                && a.speed() == b.speed()
+                && a.enhance_options() == b.enhance_options()
+                && !a.reverse
+                && !b.reverse
+                && a.fade_out <= 0.0
+                && b.fade_in <= 0.0
+                // An explicit crossfade into `b` asks for the join to be heard
+                && b.crossfade.is_none()
+                && (b.start_time - a.timeline_end()).abs() < TIMELINE_TOLERANCE
+                && (b.trim_start - (a.trim_start + a.duration)).abs() < TIMELINE_TOLERANCE;
+            if contiguous {
+                last.clip.duration += next.clip.duration;
+                last.clip.trim_end = next.clip.trim_end;
+                last.clip.fade_out = next.clip.fade_out;
+                continue;
+            }
+        }
+        merged.push(next.clone());
+    }
+
+    if merged.len() < clips.len() {
+        eprintln!("Merged {} contiguous clips from the same sources", clips.len() - merged.len());
+    }
+    merged
+}
+
+// Longest clip that may be reversed, in source seconds. areverse holds the whole
+// trimmed section in memory as float samples (about 23 MB a minute at 48 kHz stereo)
+const MAX_REVERSE_SECONDS: f64 = 600.0;
+
+// atempo stages for `speed`. One atempo only goes from 0.5 to 2.0, so faster or slower
+// rates are made of several, e.g. 3.0 as 2.0 then 1.5
+fn atempo_chain(speed: f64) -> Vec<String> {
+    let mut stages = Vec::new();
+    let mut remaining = speed;
+    while remaining > 2.0 {
+        stages.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        stages.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    if (remaining - 1.0).abs() > 1e-9 {
+        stages.push(format!("atempo={}", remaining));
+    }
+    stages
+}
+
+// Clip sources are looked up in `inputs`, which holds the FFmpeg input index each
+// source was actually registered at
+fn generate_filter_complex(
+    clips: &[ClipWithVolume],
+    inputs: &InputRegistry,
+    main_volume: f64,
+    crossfade_duration: f64,
+    lead_in: f64,
+    tail: f64,
+    limiter: Option<&MasterLimiter>,
+) -> Result<String, String> {
+    if clips.is_empty() {
+        return Ok(String::new());
+    }
+
+    let clips = &merge_contiguous_clips(clips)[..];
+    let mut filter_parts = Vec::new();
+    let crossfades = crossfade_plan(clips, crossfade_duration);
+
+    for (i, clip_with_vol) in clips.iter().enumerate() {
+        let clip = &clip_with_vol.clip;
+        let track_vol = clip_with_vol.track_volume;
+        let mut clip_vol = clip.volume.unwrap_or(1.0);
+        if !(0.0..=MAX_CLIP_VOLUME).contains(&clip_vol) {
+            eprintln!("WARNING: clip {} volume {} is out of range, clamping to 0..{}", i, clip_vol, MAX_CLIP_VOLUME);
+            clip_vol = clip_vol.clamp(0.0, MAX_CLIP_VOLUME);
+        }
+        let link = crossfades[i];
+
+        // Find the input index for this clip's source file
+        let input_idx = inputs.source_index(&clip.source_file)?;
+
+        eprintln!("  Clip {}: source '{}' -> FFmpeg input index {}, track volume: {}, clip volume: {}", i, clip.source_file, input_idx, track_vol, clip_vol);
+
+        // Create filter for each clip: trim, adjust timing, fade, apply track and clip volume
+        let (trim_start, mut trim_end) = clip.source_range()?;
+
+        // Trims are in source seconds, everything after the atempo stages in timeline seconds
+        let speed = clip.speed();
+
+        // acrossfade overlaps the last `out` seconds of this clip with the start of the
+        // next one. Play on past the clip's end (using trimmed-off audio where the source
+        // has some, silence otherwise) so the next clip still starts at its own position.
+        // A reversed clip would play that extra audio first, so it only gets silence
+        let mut tail_padding = 0.0;
+        if link.out > 0.0 {
+            let available = if clip.reverse {
+                0.0
+            } else {
+                match clip.source_duration.filter(|d| *d > 0.0) {
+                    Some(source_duration) => source_duration - trim_end,
+                    None => clip.trim_end,
+                }
+            };
+            let extension = (link.out * speed).min(available.max(0.0));
+            trim_end += extension;
+            tail_padding = link.out - extension / speed;
+        }
+
+        let clip_len = (trim_end - trim_start) / speed;
+
+        let mut chain = vec![
+            format!("atrim=start={}:end={}", trim_start, trim_end),
+            "asetpts=PTS-STARTPTS".to_string(),
+        ];
+        if clip.reverse {
+            if trim_end - trim_start > MAX_REVERSE_SECONDS {
+                return Err(format!(
+                    "Clip '{}' is too long to reverse ({:.0}s, at most {:.0}s)",
+                    clip.source_file, trim_end - trim_start, MAX_REVERSE_SECONDS
+                ));
+            }
+            chain.push("areverse".to_string());
+        }
+        chain.push(format!("aresample={}:async=1:first_pts=0", MIX_SAMPLE_RATE));
+        // Clean up the voice before the speed change, so the filters see the recording
+        // as it is, and before the fades and volume so the compressor can't undo them
+        if let Some(strength) = clip_with_vol.track_denoise {
+            chain.push(format!("afftdn=nr={}:nf=-25", validate_denoise_strength(strength)?));
+        }
+        if let Some(enhance) = clip.enhance_options().or(clip_with_vol.track_enhance) {
+            chain.extend(enhance.filters()?);
+        }
+        chain.extend(atempo_chain(speed));
+        if tail_padding > 0.0 {
+            chain.push(format!("apad=pad_dur={}", tail_padding));
+        }
+
+        // Fades are relative to the trimmed clip and can't be longer than it.
+        // Crossfaded edges are shaped by acrossfade instead.
+        let mut fade_in = if link.into > 0.0 { 0.0 } else { clip.fade_in.clamp(0.0, clip_len) };
+        let mut fade_out = if link.out > 0.0 { 0.0 } else { clip.fade_out.clamp(0.0, clip_len) };
+
+        // When both fades together are longer than the clip, shrink them proportionally
+        // so the fade-out doesn't start before the fade-in has finished
+        if fade_in + fade_out > clip_len {
+            let scale = clip_len / (fade_in + fade_out);
+            fade_in *= scale;
+            fade_out *= scale;
+        }
+
+        if fade_in > 0.0 {
+            chain.push(format!("afade=t=in:st=0:d={}", fade_in));
+        }
+        if fade_out > 0.0 {
+            chain.push(format!("afade=t=out:st={}:d={}", clip_len - fade_out, fade_out));
+        }
+
+        // Apply track volume times clip volume to each clip individually;
+        // unity gain needs no node
+        let gain = track_vol * clip_vol;
+        if gain != 1.0 {
+            chain.push(format!("volume={}", gain));
+        }
+
+        // Pan by turning down the opposite side, after making mono sources stereo
+        let mut pan = clip_with_vol.track_pan;
+        if !(-1.0..=1.0).contains(&pan) {
+            eprintln!("WARNING: track {} pan {} is out of range, clamping to -1..1", clip_with_vol.track_index, pan);
+            pan = pan.clamp(-1.0, 1.0);
+        }
+        if pan != 0.0 {
+            chain.push(format!(
+                "aformat=channel_layouts=stereo,pan=stereo|c0={}*c0|c1={}*c1",
+                (1.0 - pan).min(1.0),
+                (1.0 + pan).min(1.0)
+            ));
+        }
+
+        filter_parts.push(format!("[{}:a]{}[c{}]", input_idx, chain.join(","), i));
+    }
+
+    // Join each run of crossfaded clips into one stream, then delay every stream
+    // to the timeline position of its first clip
+    let mut stream_labels: Vec<String> = Vec::new();
+    for (head, link) in crossfades.iter().enumerate() {
+        if link.into > 0.0 {
+            continue;
+        }
+
+        let mut current = format!("[c{}]", head);
+        let mut next = link.next;
+        while let Some(j) = next {
+            let joined = format!("[x{}]", j);
+            filter_parts.push(format!(
+                "{}[c{}]acrossfade=d={}:c1=qsin:c2=qsin{}",
+                current, j, crossfades[j].into, joined
+            ));
+            current = joined;
+            next = crossfades[j].next;
+        }
+
+        let delay_ms = ((clips[head].clip.start_time + lead_in) * 1000.0) as i64;
+        let label = format!("[a{}]", stream_labels.len());
+        filter_parts.push(format!("{}adelay={}|{}{}", current, delay_ms, delay_ms, label));
+        stream_labels.push(label);
+    }
+
+    // amix can't take an unlimited number of inputs, so mix large timelines in groups
+    // and then mix the groups. normalize=0 sums the streams instead of dividing by
+    // their count, so clips don't get quieter as the timeline grows
+    let mut level = 0;
+    while stream_labels.len() > AMIX_CHUNK_SIZE {
+        let mut group_labels: Vec<String> = Vec::new();
+        for chunk in stream_labels.chunks(AMIX_CHUNK_SIZE) {
+            let label = format!("[m{}_{}]", level, group_labels.len());
+            filter_parts.push(format!(
+                "{}amix=inputs={}:duration=longest:normalize=0{}",
+                chunk.join(""),
+                chunk.len(),
+                label
+            ));
+            group_labels.push(label);
+        }
+        stream_labels = group_labels;
+        level += 1;
+    }
+
+    // Mix all audio streams and pad with silence to the full timeline length, so a
+    // gap after the last sample (a clip longer than its source, or the tail) is kept
+    let whole_duration = lead_in + timeline_duration(clips) + tail;
+    let limiter = limiter.map(|limiter| format!(",{}", limiter.filter())).unwrap_or_default();
+    filter_parts.push(format!(
+        "{}amix=inputs={}:duration=longest:normalize=0,volume={}{},apad=whole_dur={:.3}[aout]",
+        stream_labels.join(""),
+        stream_labels.len(),
+        main_volume,
+        limiter,
+        whole_duration
+    ));
+
+    Ok(filter_parts.join(";"))
+}
+
+// Run ffprobe on a file and read the first audio stream
+fn probe_audio(path: &str) -> Result<AudioProbe, String> {
+    let mut cmd = ffprobe_command();
+    cmd.args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams", "-select_streams", "a:0"])
+        .arg(path);
+
+    // Don't flash a console window on Windows
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000);
+    }
+
+    let output = cmd.output()
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to probe {}: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse ffprobe output for {}: {}", path, e))?;
+
+    // ffprobe reports most numbers as strings
+    let number = |value: &serde_json::Value| -> Option<f64> {
+        value.as_str().and_then(|v| v.parse::<f64>().ok()).or_else(|| value.as_f64())
+    };
+
+    let stream = json["streams"].as_array().and_then(|streams| streams.first());
+    let format = &json["format"];
+
+    let duration = stream
+        .and_then(|s| number(&s["duration"]))
+        .or_else(|| number(&format["duration"]))
+        .unwrap_or(0.0);
+
+    Ok(AudioProbe {
+        path: path.to_string(),
+        duration,
+        codec: stream
+            .and_then(|s| s["codec_name"].as_str())
+            .unwrap_or_default()
+            .to_string(),
+        sample_rate: stream
+            .and_then(|s| number(&s["sample_rate"]))
+            .unwrap_or(0.0) as u32,
+        channels: stream
+            .and_then(|s| s["channels"].as_u64())
+            .unwrap_or(0) as u32,
+        bit_rate: stream
+            .and_then(|s| number(&s["bit_rate"]))
+            .or_else(|| number(&format["bit_rate"]))
+            .map(|b| b as u64),
+        has_audio: stream.is_some(),
+    })
+}
+
+#[tauri::command(async)]
+fn probe_audio_file(path: String) -> Result<AudioProbe, WavecastError> {
+    require_file(&path)?;
+    ffmpeg_available()?;
+
+    let probe = probe_audio(&path)?;
+    if !probe.has_audio {
+        return Err(WavecastError::invalid("path", format!("No audio stream found in {}", path)));
+    }
+
+    Ok(probe)
+}
+
+// Container duration of any media file, in seconds
+fn probe_duration(path: &str) -> Result<f64, String> {
+    let mut cmd = ffprobe_command();
+    cmd.args(["-v", "error", "-print_format", "json", "-show_format"])
+        .arg(path);
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000);
+    }
+
+    let output = cmd.output()
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to probe {}: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse ffprobe output for {}: {}", path, e))?;
+
+    json["format"]["duration"].as_str()
+        .and_then(|d| d.parse::<f64>().ok())
+        .filter(|d| d.is_finite() && *d > 0.0)
+        .ok_or_else(|| format!("Could not determine the duration of {}", path))
+}
+
+// Image format of a generated thumbnail
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ThumbnailFormat {
+    Jpeg,
+    Png,
+}
+
+impl ThumbnailFormat {
+    fn parse(format: &str) -> Result<Self, String> {
+        match format.trim().to_lowercase().as_str() {
+            "jpg" | "jpeg" => Ok(ThumbnailFormat::Jpeg),
+            "png" => Ok(ThumbnailFormat::Png),
+            other => Err(format!("Unsupported thumbnail format '{}' (expected jpg or png)", other)),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::Png => "png",
+        }
+    }
+}
+
+// Grab one frame of `video_path` at `timestamp` (the midpoint when None), scaled to `width`
+fn render_thumbnail(
+    video_path: &str,
+    timestamp: Option<f64>,
+    width: u32,
+    output_path: Option<String>,
+    format: Option<String>,
+) -> Result<String, String> {
+    if !(16..=7680).contains(&width) {
+        return Err(format!("Invalid thumbnail width {}: must be between 16 and 7680", width));
+    }
+    if let Some(timestamp) = timestamp {
+        if !timestamp.is_finite() {
+            return Err(format!("Invalid thumbnail timestamp {}", timestamp));
+        }
+    }
+
+    // An explicit format wins, then the extension of the output path, then JPEG
+    let format = match (format, &output_path) {
+        (Some(format), _) => ThumbnailFormat::parse(&format)?,
+        (None, Some(path)) => std::path::Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| ThumbnailFormat::parse(ext).ok())
+            .unwrap_or(ThumbnailFormat::Jpeg),
+        (None, None) => ThumbnailFormat::Jpeg,
+    };
+
+    ffmpeg_available()?;
+
+    // Seeking to (or past) the very end yields no frame, so stay a little before it
+    let duration = probe_duration(video_path)?;
+    let last_frame = (duration - 0.1).max(0.0);
+    let timestamp = timestamp.unwrap_or(duration / 2.0).clamp(0.0, last_frame);
+    eprintln!("Thumbnail of {} at {:.3}s (duration {:.3}s), width {}", video_path, timestamp, duration, width);
+
+    let output_path = match output_path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let video = std::path::Path::new(video_path);
+            let stem = video.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "video".to_string());
+            video.with_file_name(format!("{}-thumbnail.{}", stem, format.extension()))
+        }
+    };
+
+    let mut cmd = ffmpeg_command();
+    cmd.args(&["-ss", &format!("{:.3}", timestamp)])
+        .input(video_path)
+        .args(&[
+            "-frames:v", "1",
+            "-update", "1",
+            "-vf", &format!("scale={}:-2", width),
+        ]);
+    if format == ThumbnailFormat::Jpeg {
+        cmd.args(&["-q:v", "2"]);
+    }
+    cmd.overwrite().output(output_path.to_str().unwrap());
+
+    let mut child = cmd.spawn()
+        .map_err(|e| {
+            let err_msg = format!("Failed to spawn FFmpeg: {}", e);
+            eprintln!("ERROR: {}", err_msg);
+            err_msg
+        })?;
+    // Drain the log so FFmpeg never blocks on a full pipe
+    if let Ok(iter) = child.iter() {
+        for event in iter {
+            if let FfmpegEvent::Log(LogLevel::Error, msg) = event {
+                eprintln!("FFmpeg: {}", msg);
+            }
+        }
+    }
+    let result = child.wait()
+        .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+    if !result.success() || !output_path.exists() {
+        let err_msg = format!("Failed to generate thumbnail for {}", video_path);
+        eprintln!("ERROR: {}", err_msg);
+        return Err(err_msg);
+    }
+
+    eprintln!("Thumbnail written to {}", output_path.display());
+    Ok(output_path.to_str().unwrap().to_string())
+}
+
+// Write a poster frame next to a finished export and announce it with export-thumbnail.
+// The export itself succeeded, so a failure here is only logged
+fn export_poster_frame(app: &tauri::AppHandle, output_path: &std::path::Path, timestamp: Option<f64>, width: u32) {
+    match render_thumbnail(output_path.to_str().unwrap(), timestamp, width, None, None) {
+        Ok(thumbnail) => {
+            let _ = app.emit("export-thumbnail", thumbnail);
+        }
+        Err(e) => eprintln!("Warning: failed to generate a thumbnail for {}: {}", output_path.display(), e),
+    }
+}
+
+#[tauri::command(async)]
+fn generate_thumbnail(
+    video_path: String,
+    timestamp: f64,
+    width: u32,
+    output_path: Option<String>,
+    format: Option<String>,
+) -> Result<String, WavecastError> {
+    require_file(&video_path)?;
+    Ok(render_thumbnail(&video_path, Some(timestamp), width, output_path, format)?)
+}
+
+// Same as generate_thumbnail, taken from the middle of the video
+#[tauri::command(async)]
+fn generate_midpoint_thumbnail(
+    video_path: String,
+    width: u32,
+    output_path: Option<String>,
+    format: Option<String>,
+) -> Result<String, WavecastError> {
+    require_file(&video_path)?;
+    Ok(render_thumbnail(&video_path, None, width, output_path, format)?)
+}
+
+// Decode a file to mono float PCM and reduce it to interleaved (min, max) pairs,
+// `samples_per_second` pairs per second of audio
+fn extract_waveform_peaks(path: &str, samples_per_second: u32) -> Result<Vec<f32>, String> {
+    use std::io::Read;
+
+    // Decode at a whole multiple of the peak rate so every bucket has the same size
+    let bucket = 8000u32.div_ceil(samples_per_second).max(2);
+    let decode_rate = samples_per_second * bucket;
+
+    let mut child = ffmpeg_command()
+        .args(["-v", "error", "-nostats"])
+        .input(path)
+        .args(["-vn", "-ac", "1", "-ar", &decode_rate.to_string()])
+        .format("f32le")
+        .output("-")
+        .spawn()
+        .map_err(|e| format!("Failed to spawn FFmpeg: {}", e))?;
+
+    let mut stdout = child.take_stdout()
+        .ok_or_else(|| "Failed to read FFmpeg output".to_string())?;
+
+    let mut peaks = Vec::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut pending: Vec<u8> = Vec::new();
+    let (mut min, mut max, mut count) = (f32::MAX, f32::MIN, 0u32);
+
+    loop {
+        let n = stdout.read(&mut buf)
+            .map_err(|e| format!("Failed to read decoded audio: {}", e))?;
+        if n == 0 {
+            break;
+        }
+
+        // Samples can straddle reads, so keep any trailing partial sample for next time
+        pending.extend_from_slice(&buf[..n]);
+        let whole = pending.len() / 4 * 4;
+        for bytes in pending[..whole].chunks_exact(4) {
+            let sample = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            min = min.min(sample);
+            max = max.max(sample);
+            count += 1;
+            if count == bucket {
+                peaks.push(min);
+                peaks.push(max);
+                min = f32::MAX;
+                max = f32::MIN;
+                count = 0;
+            }
+        }
+        pending.drain(..whole);
+    }
+
+    if count > 0 {
+        peaks.push(min);
+        peaks.push(max);
+    }
+
+    let result = child.wait()
+        .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+    if !result.success() {
+        return Err(format!("Failed to decode audio from {}", path));
+    }
+
+    Ok(peaks)
+}
+
+// Modification time of `path`, so cache entries go stale when the file changes
+fn modified_nanos(path: &str) -> Result<u128, String> {
+    let modified = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    Ok(modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0))
+}
+
+// Waveform peaks are cached in the temp dir, keyed by path, modification time and resolution
+fn waveform_cache_path(path: &str, samples_per_second: u32) -> Result<PathBuf, String> {
+    use std::hash::{Hash, Hasher};
+
+    let mtime = modified_nanos(path)?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (path, mtime, samples_per_second).hash(&mut hasher);
+
+    Ok(std::env::temp_dir()
+        .join("wavecast-waveforms")
+        .join(format!("{:016x}.peaks", hasher.finish())))
+}
+
+#[tauri::command(async)]
+fn get_waveform_peaks(path: String, samples_per_second: u32) -> Result<Vec<f32>, WavecastError> {
+    if samples_per_second == 0 || samples_per_second > 1000 {
+        return Err(WavecastError::invalid("samples_per_second", format!(
+            "Invalid waveform resolution {}: must be between 1 and 1000 peaks per second",
+            samples_per_second
+        )));
+    }
+    require_file(&path)?;
+
+    let cache_path = waveform_cache_path(&path, samples_per_second)?;
+    if let Ok(bytes) = std::fs::read(&cache_path) {
+        return Ok(bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect());
+    }
+
+    ffmpeg_available()?;
+
+    let peaks = extract_waveform_peaks(&path, samples_per_second)?;
+
+    // A failed cache write only costs a re-decode next time
+    if let Some(cache_dir) = cache_path.parent() {
+        let _ = std::fs::create_dir_all(cache_dir);
+    }
+    let bytes: Vec<u8> = peaks.iter().flat_map(|p| p.to_le_bytes()).collect();
+    let _ = std::fs::write(&cache_path, bytes);
+
+    Ok(peaks)
+}
+
+// Noise reduction in dB (afftdn's nr) for denoise_clip when no strength is given
+const DEFAULT_DENOISE_STRENGTH: f64 = 12.0;
+
+fn validate_denoise_strength(strength: f64) -> Result<f64, String> {
+    if !(0.01..=97.0).contains(&strength) {
+        return Err(format!("Invalid noise reduction strength {} dB: must be between 0.01 and 97", strength));
+    }
+    Ok(strength)
+}
+
+// Cleaned copies are cached in the temp dir like waveform peaks, keyed by the source
+// and the noise sample and strength used
+fn denoise_cache_path(path: &str, noise_start: f64, noise_duration: f64, strength: f64) -> Result<PathBuf, String> {
+    use std::hash::{Hash, Hasher};
+
+    let mtime = modified_nanos(path)?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (path, mtime, noise_start.to_bits(), noise_duration.to_bits(), strength.to_bits()).hash(&mut hasher);
+
+    Ok(std::env::temp_dir()
+        .join("wavecast-denoised")
+        .join(format!("{:016x}.flac", hasher.finish())))
+}
+
+// afftdn can only learn a noise profile while audio is going through it, so input 0
+// (just the noise sample) is played first with sample_noise on, then input 1 (the
+// whole file) with the learned profile, and the sample is cut off the front again
+fn denoise_filter(noise_duration: f64, strength: f64) -> String {
+    format!(
+        "[0:a]asetpts=PTS-STARTPTS[sample];[1:a]asetpts=PTS-STARTPTS[full];\
+         [sample][full]concat=n=2:v=0:a=1,\
+         asendcmd=c='0 afftdn sn start',asendcmd=c='{d} afftdn sn stop',\
+         afftdn=nr={nr},atrim=start={d},asetpts=PTS-STARTPTS[clean]",
+        d = noise_duration,
+        nr = strength
+    )
+}
+
+// Write a noise-reduced copy of `path`, learning the noise from the noise-only section
+// starting at `noise_sample_start`, and return where it is. The original is left
+// alone so the timeline can swap the copy in (and back out)
+#[tauri::command(async)]
+fn denoise_clip(
+    path: String,
+    noise_sample_start: f64,
+    noise_sample_duration: f64,
+    strength: Option<f64>,
+) -> Result<String, WavecastError> {
+    require_file(&path)?;
+    if !noise_sample_start.is_finite() || noise_sample_start < 0.0 {
+        return Err(WavecastError::invalid("noise_sample_start", format!(
+            "Invalid noise sample start {}: must be 0 or later",
+            noise_sample_start
+        )));
+    }
+    if !(0.1..=60.0).contains(&noise_sample_duration) {
+        return Err(WavecastError::invalid("noise_sample_duration", format!(
+            "Invalid noise sample length {}s: must be between 0.1 and 60 seconds",
+            noise_sample_duration
+        )));
+    }
+    let strength = validate_denoise_strength(strength.unwrap_or(DEFAULT_DENOISE_STRENGTH))
+        .map_err(|reason| WavecastError::invalid("strength", reason))?;
+
+    ffmpeg_available()?;
+
+    let length = probe_duration(&path)?;
+    if noise_sample_start + noise_sample_duration > length + TIMELINE_TOLERANCE {
+        return Err(WavecastError::invalid("noise_sample_start", format!(
+            "The noise sample ({:.2}s to {:.2}s) runs past the end of {}, which is {:.2}s long",
+            noise_sample_start, noise_sample_start + noise_sample_duration, path, length
+        )));
+    }
+
+    let cache_path = denoise_cache_path(&path, noise_sample_start, noise_sample_duration, strength)?;
+    if cache_path.exists() {
+        eprintln!("Using cached noise-reduced copy of {}: {}", path, cache_path.display());
+        return Ok(cache_path.to_str().unwrap().to_string());
+    }
+    if let Some(cache_dir) = cache_path.parent() {
+        std::fs::create_dir_all(cache_dir)
+            .map_err(|e| format!("Failed to create {}: {}", cache_dir.display(), e))?;
+    }
+
+    // FFmpeg writes next to the final name and the file is moved into place once it
+    // succeeds, so a failed run never leaves something that looks cached
+    let partial = TempFile::new(cache_path.with_extension("partial.flac"));
+    let filter = denoise_filter(noise_sample_duration, strength);
+    eprintln!("Denoising {} (noise sample {:.2}s + {:.2}s, strength {} dB)", path, noise_sample_start, noise_sample_duration, strength);
+    eprintln!("Denoise filter: {}", filter);
+
+    let mut cmd = ffmpeg_command();
+    cmd.args(&[
+        "-ss", &format!("{:.3}", noise_sample_start),
+        "-t", &format!("{:.3}", noise_sample_duration),
+    ])
+        .input(&path)
+        .input(&path)
+        .args(&["-filter_complex", &filter, "-map", "[clean]", "-c:a", "flac"])
+        .overwrite()
+        .output(partial.to_str());
+
+    let mut child = cmd.spawn()
+        .map_err(|e| {
+            let err_msg = format!("Failed to spawn FFmpeg: {}", e);
+            eprintln!("ERROR: {}", err_msg);
+            err_msg
+        })?;
+    // Drain the log so FFmpeg never blocks on a full pipe
+    if let Ok(iter) = child.iter() {
+        for event in iter {
+            if let FfmpegEvent::Log(LogLevel::Error, msg) = event {
+                eprintln!("FFmpeg: {}", msg);
+            }
+        }
+    }
+    let result = child.wait()
+        .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+    if !result.success() || !partial.path().exists() {
+        let err_msg = format!("Failed to reduce noise in {}", path);
+        eprintln!("ERROR: {}", err_msg);
+        return Err(err_msg.into());
+    }
+
+    std::fs::rename(partial.path(), &cache_path)
+        .map_err(|e| format!("Failed to save the noise-reduced copy of {}: {}", path, e))?;
+    partial.keep();
+    eprintln!("Noise-reduced copy written to {}", cache_path.display());
+    Ok(cache_path.to_str().unwrap().to_string())
+}
+
+// "#rgb", "#rgba", "#rrggbb" or "#rrggbbaa" -> its RGB or RGBA channels
+fn parse_hex_color(color: &str) -> Result<Vec<u8>, String> {
+    let hex = color.trim().trim_start_matches('#');
+    let invalid = || format!("Invalid color '{}' (expected #rgb, #rgba, #rrggbb or #rrggbbaa)", color);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    match hex.len() {
+        // Shorthand: each digit is doubled, so "f" is "ff"
+        3 | 4 => Ok(hex.chars().map(|c| c.to_digit(16).unwrap() as u8 * 17).collect()),
+        6 | 8 => Ok((0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()),
+        _ => Err(invalid()),
+    }
+}
+
+#[tauri::command]
+fn create_solid_color_image(color: String, width: u32, height: u32) -> Result<String, WavecastError> {
+    let channels = parse_hex_color(&color).map_err(|reason| WavecastError::invalid("color", reason))?;
+
+    // Create a simple PNG using raw RGBA data
+    let temp_dir = std::env::temp_dir();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let temp_path = temp_dir.join(format!("solid_color_{}.png", timestamp));
+
+    // Fill an image buffer with the color, keeping the alpha channel when one was given
+    let saved = match channels[..] {
+        [r, g, b, a] => image::RgbaImage::from_pixel(width, height, image::Rgba([r, g, b, a])).save(&temp_path),
+        [r, g, b] => image::RgbImage::from_pixel(width, height, image::Rgb([r, g, b])).save(&temp_path),
+        _ => unreachable!("parse_hex_color returns 3 or 4 channels"),
+    };
+    saved.map_err(|e| format!("Failed to save image: {}", e))?;
+
+    Ok(temp_path.to_str().unwrap().to_string())
+}
+
+// Get all clips from all audio tracks with their track volumes,
+// leaving out muted tracks and, if anything is soloed, tracks that aren't
+fn audible_clips(timeline: &TimelineData) -> Result<Vec<ClipWithVolume>, String> {
+    let any_solo = timeline.tracks.iter().any(|track| track.solo);
+    let mut all_clips: Vec<ClipWithVolume> = Vec::new();
+    for (i, track) in timeline.tracks.iter().enumerate() {
+        eprintln!("Track {}: {} clips, volume: {}, muted: {}, solo: {}", i, track.clips.len(), track.volume, track.muted, track.solo);
+        if track.muted || (any_solo && !track.solo) {
+            eprintln!("Skipping track {}", i);
+            continue;
+        }
+        for (j, clip) in track.clips.iter().enumerate() {
+            let speed = clip.speed();
+            if !speed.is_finite() || speed <= 0.0 {
+                let err_msg = format!("Clip {} on track {} has speed {}: it must be greater than 0", j + 1, i + 1, speed);
+                eprintln!("ERROR: {}", err_msg);
+                return Err(err_msg);
+            }
+            all_clips.push(ClipWithVolume {
+                clip: clip.clone(),
+                track_volume: track.volume,
+                track_pan: track.pan,
+                track_enhance: enhance_setting(track.voice_enhance, track.enhance),
+                track_denoise: track.denoise_strength,
+                track_index: i,
+            });
+        }
+    }
+
+    if all_clips.is_empty() {
+        let err_msg = "No audio clips in timeline".to_string();
+        eprintln!("ERROR: {}", err_msg);
+        return Err(err_msg);
+    }
+    eprintln!("Total clips to process: {}", all_clips.len());
+    Ok(all_clips)
+}
+
+// Probe the source length of tail-trimmed clips that came without one, since
+// source_range can only apply trim_end when it knows where the source ends
+fn fill_source_durations(clips: &mut [ClipWithVolume]) {
+    let mut probed: std::collections::HashMap<String, Option<f64>> = std::collections::HashMap::new();
+    for entry in clips.iter_mut() {
+        let clip = &mut entry.clip;
+        if clip.trim_end <= 0.0 || clip.source_duration.filter(|d| *d > 0.0).is_some() {
+            continue;
+        }
+        let length = probed.entry(clip.source_file.clone()).or_insert_with(|| {
+            probe_duration(&clip.source_file)
+                .map_err(|e| eprintln!("Warning: could not probe {}, its trim_end will be ignored: {}", clip.source_file, e))
+                .ok()
+        });
+        clip.source_duration = *length;
+    }
+}
+
+// How far clip times may be off before validate_timeline reports them, in seconds
+const TIMELINE_TOLERANCE: f64 = 0.01;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum IssueSeverity {
+    // The export would fail or come out wrong, so it isn't started
+    Error,
+    Warning,
+}
+
+// A problem with one clip of a timeline, as reported by validate_timeline
+#[derive(Serialize, Debug, Clone)]
+struct TimelineIssue {
+    severity: IssueSeverity,
+    track_index: usize,
+    clip_index: usize,
+    message: String,
+}
+
+// Check the clips an export would use against their source files: that every file can
+// be opened, that trims and durations fit the probed length, that nothing starts
+// before 0 and which clips overlap on a track. Needs ffprobe to be available
+fn check_timeline(timeline: &TimelineData) -> Vec<TimelineIssue> {
+    let mut issues = Vec::new();
+    let mut durations: std::collections::HashMap<String, Result<f64, String>> = std::collections::HashMap::new();
+    let any_solo = timeline.tracks.iter().any(|track| track.solo);
+
+    for (track_index, track) in timeline.tracks.iter().enumerate() {
+        // Tracks left out of the export can't break it
+        if track.muted || (any_solo && !track.solo) {
+            continue;
+        }
+        let mut issue = |severity, clip_index, message: String| {
+            issues.push(TimelineIssue { severity, track_index, clip_index, message });
+        };
+
+        for (clip_index, clip) in track.clips.iter().enumerate() {
+            let source = &clip.source_file;
+            if let Err(e) = std::fs::File::open(source) {
+                let message = if e.kind() == std::io::ErrorKind::NotFound {
+                    format!("Source file not found: {}", source)
+                } else {
+                    format!("Source file can't be read: {} ({})", source, e)
+                };
+                issue(IssueSeverity::Error, clip_index, message);
+                continue;
+            }
+
+            if clip.start_time < 0.0 {
+                issue(IssueSeverity::Error, clip_index, format!("Clip starts before the timeline, at {:.2}s", clip.start_time));
+            }
+            if clip.trim_start < 0.0 || clip.trim_end < 0.0 {
+                issue(IssueSeverity::Error, clip_index, "Clip has a negative trim".to_string());
+                continue;
+            }
+            if clip.duration <= 0.0 {
+                issue(IssueSeverity::Error, clip_index, format!("Clip has no length ({:.2}s)", clip.duration));
+                continue;
+            }
+            if !clip.speed().is_finite() || clip.speed() <= 0.0 {
+                issue(IssueSeverity::Error, clip_index, format!("Clip has speed {}; it must be greater than 0", clip.speed()));
+                continue;
+            }
+
+            let length = match durations.entry(source.clone()).or_insert_with(|| probe_duration(source)) {
+                Ok(length) => *length,
+                Err(e) => {
+                    issue(IssueSeverity::Error, clip_index, e.clone());
+                    continue;
+                }
+            };
+            let end = length - clip.trim_end;
+            if clip.trim_start >= end {
+                issue(IssueSeverity::Error, clip_index, format!(
+                    "Trims of {:.2}s and {:.2}s leave nothing of {}, which is {:.2}s long",
+                    clip.trim_start, clip.trim_end, source, length
+                ));
+            } else if clip.trim_start + clip.duration > end + TIMELINE_TOLERANCE {
+                issue(IssueSeverity::Warning, clip_index, format!(
+                    "Clip is {:.2}s long but only {:.2}s of {} is left after trimming, so it will end early",
+                    clip.duration, end - clip.trim_start, source
+                ));
+            }
+        }
+
+        // Clips on one track are meant to follow each other, not play on top of each other
+        for (earlier, later) in find_overlaps(track) {
+            let (previous, next) = (&track.clips[earlier], &track.clips[later]);
+            let overlap = previous.timeline_end().min(next.timeline_end()) - next.start_time;
+            issue(IssueSeverity::Warning, later, format!(
+                "Clip overlaps clip {} on the same track by {:.2}s",
+                earlier + 1, overlap
+            ));
+        }
+    }
+
+    issues
+}
+
+// Pairs of clip indices on `track` whose time ranges overlap by more than
+// TIMELINE_TOLERANCE, the earlier-starting clip first. Each clip is checked against
+// the one that reaches furthest among those starting before it, so a long clip
+// covering several short ones is reported against each of them
+fn find_overlaps(track: &TimelineTrack) -> Vec<(usize, usize)> {
+    let clips = &track.clips;
+    let mut order: Vec<usize> = (0..clips.len()).collect();
+    order.sort_by(|a, b| clips[*a].start_time.total_cmp(&clips[*b].start_time));
+
+    let mut overlaps = Vec::new();
+    let mut furthest: Option<usize> = None;
+    for index in order {
+        let clip = &clips[index];
+        if let Some(previous) = furthest {
+            if clips[previous].timeline_end() - clip.start_time > TIMELINE_TOLERANCE {
+                overlaps.push((previous, index));
+            }
+        }
+        let reaches_further = furthest
+            .map(|previous| clip.timeline_end() > clips[previous].timeline_end())
+            .unwrap_or(true);
+        if reaches_further {
+            furthest = Some(index);
+        }
+    }
+    overlaps
+}
+
+// One line per overlapping pair on the tracks an export uses, e.g.
+// "Track 1: clips 2 and 3 overlap"
+fn describe_overlaps(timeline: &TimelineData) -> Vec<String> {
+    let any_solo = timeline.tracks.iter().any(|track| track.solo);
+    timeline.tracks.iter()
+        .enumerate()
+        .filter(|(_, track)| !track.muted && (!any_solo || track.solo))
+        .flat_map(|(track_index, track)| {
+            find_overlaps(track).into_iter().map(move |(earlier, later)| {
+                format!("Track {}: clips {} and {} overlap", track_index + 1, earlier + 1, later + 1)
+            })
+        })
+        .collect()
+}
+
+#[tauri::command(async)]
+fn validate_timeline(timeline: TimelineData) -> Result<Vec<TimelineIssue>, WavecastError> {
+    ffmpeg_available()?;
+    let issues = check_timeline(&timeline);
+    eprintln!("Timeline validation found {} issue(s)", issues.len());
+    Ok(issues)
+}
+
+// check_timeline before an export, refusing to start when any issue is an error
+fn require_valid_timeline(timeline: &TimelineData) -> Result<(), WavecastError> {
+    let errors: Vec<String> = check_timeline(timeline)
+        .into_iter()
+        .filter(|issue| issue.severity == IssueSeverity::Error)
+        .map(|issue| format!("Track {}, clip {}: {}", issue.track_index + 1, issue.clip_index + 1, issue.message))
+        .collect();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    for error in &errors {
+        eprintln!("ERROR: {}", error);
+    }
+    Err(WavecastError::invalid("timeline", errors.join("\n")))
+}
+
+// FFmpeg inputs in the order they are added to a command. Every input's index is
+// recorded when it is registered instead of being worked out from offsets later, so
+// adding inputs (watermarks, metadata files, ...) can't shift the audio streams.
+#[derive(Default, Debug)]
+struct InputRegistry {
+    paths: Vec<String>,
+    sources: std::collections::HashMap<String, usize>,
+}
+
+impl InputRegistry {
+    // Record the next input and return its index
+    fn add(&mut self, path: &str) -> usize {
+        self.paths.push(path.to_string());
+        self.paths.len() - 1
+    }
+
+    // Record a clip source; clips sharing a file share its input
+    fn add_source(&mut self, path: &str) -> usize {
+        if let Some(&index) = self.sources.get(path) {
+            return index;
+        }
+        let index = self.add(path);
+        self.sources.insert(path.to_string(), index);
+        index
+    }
+
+    fn source_index(&self, path: &str) -> Result<usize, String> {
+        self.sources.get(path).copied().ok_or_else(|| {
+            let err_msg = format!("Clip source was never added as an input: {}", path);
+            eprintln!("ERROR: {}", err_msg);
+            err_msg
+        })
+    }
+
+    fn paths(&self) -> Vec<&str> {
+        self.paths.iter().map(String::as_str).collect()
+    }
+}
+
+// Each source file once, in first-use order; this is the order they're added as inputs
+fn unique_clip_sources(clips: &[ClipWithVolume]) -> Vec<String> {
+    let mut unique_sources: Vec<String> = Vec::new();
+    for clip_with_vol in clips {
+        if !unique_sources.contains(&clip_with_vol.clip.source_file) {
+            unique_sources.push(clip_with_vol.clip.source_file.clone());
+        }
+    }
+    unique_sources
+}
+
+// Where the last clip ends on the timeline
+fn timeline_duration(clips: &[ClipWithVolume]) -> f64 {
+    clips.iter()
+        .map(|clip_with_vol| clip_with_vol.clip.timeline_end())
+        .fold(0.0, f64::max)
+}
+
+fn default_loop_enabled() -> bool {
+    true
+}
+
+// Background music under the whole export: `path` followed by the `playlist` tracks,
+// played back to back. `trim_start` and `trim_end` are the seconds cut off the head
+// and tail of that, like a clip's; `start_offset` is where on the output the music
+// comes in. All times are in seconds.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct BackgroundMusic {
+    path: String,
+    #[serde(default)]
+    playlist: Vec<String>,
+    // Percent, like the old bg_music_volume argument
+    volume: i32,
+    #[serde(default)]
+    start_offset: f64,
+    #[serde(default)]
+    trim_start: f64,
+    #[serde(default)]
+    trim_end: f64,
+    // Repeat the trimmed music until the export ends
+    #[serde(default = "default_loop_enabled")]
+    loop_enabled: bool,
+    #[serde(default)]
+    fade_in: f64,
+    #[serde(default)]
+    fade_out: f64,
+}
+
+impl BackgroundMusic {
+    // `music` if given, otherwise the music described by the older bg_music_path,
+    // bg_music_paths, bg_music_volume and bg_music_fade_out arguments
+    fn resolve(
+        music: Option<BackgroundMusic>,
+        path: Option<String>,
+        paths: Option<Vec<String>>,
+        volume: Option<i32>,
+        fade_out: Option<f64>,
+    ) -> Option<BackgroundMusic> {
+        if music.is_some() {
+            if path.is_some() || paths.is_some() {
+                eprintln!("WARNING: both bg_music and bg_music_path(s) were given, using bg_music");
+            }
+            return music;
+        }
+        let mut tracks = path.into_iter().chain(paths.unwrap_or_default());
+        let path = tracks.next()?;
+        Some(BackgroundMusic {
+            path,
+            playlist: tracks.collect(),
+            volume: volume.unwrap_or(100),
+            start_offset: 0.0,
+            trim_start: 0.0,
+            trim_end: 0.0,
+            loop_enabled: true,
+            fade_in: 0.0,
+            fade_out: fade_out.unwrap_or(0.0),
+        })
+    }
+
+    // Every music file in playing order
+    fn paths(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.path).chain(self.playlist.iter())
+    }
+
+    fn paths_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        std::iter::once(&mut self.path).chain(self.playlist.iter_mut())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let times = [
+            ("start offset", self.start_offset),
+            ("trim start", self.trim_start),
+            ("trim end", self.trim_end),
+            ("fade in", self.fade_in),
+            ("fade out", self.fade_out),
+        ];
+        for (name, value) in times {
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("Invalid background music {} {}: must be 0 or more seconds", name, value));
+            }
+        }
+        if self.volume < 0 {
+            return Err(format!("Invalid background music volume {}: must be 0 or more", self.volume));
+        }
+        Ok(())
+    }
+
+    // Length of all the music back to back, probed only when trim_end or a fade out at
+    // the end of unlooped music needs it
+    fn source_length(&self) -> Result<Option<f64>, String> {
+        if self.trim_end <= 0.0 && (self.loop_enabled || self.fade_out <= 0.0) {
+            return Ok(None);
+        }
+        let mut length = 0.0;
+        for path in self.paths() {
+            length += probe_duration(path)?;
+        }
+        if length - self.trim_end <= self.trim_start {
+            return Err(format!(
+                "Background music {} has no audio left after trimming ({:.2}s long, trimmed {:.2}s + {:.2}s)",
+                self.path, length, self.trim_start, self.trim_end
+            ));
+        }
+        Ok(Some(length))
+    }
+
+    // Filter chain from `inputs` (one per music file, in playing order) to [bgmusic],
+    // cut to `duration` so neither the music nor the speech decides how long the output
+    // is. `source_length` comes from source_length()
+    fn filter(&self, inputs: &[usize], duration: f64, source_length: Option<f64>) -> String {
+        // A playlist is joined with the concat filter, the files brought to one format
+        // first since they needn't share a sample rate or layout
+        let mut head = String::new();
+        if inputs.len() > 1 {
+            for (i, input) in inputs.iter().enumerate() {
+                head.push_str(&format!(
+                    "[{}:a:0]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo[track{}];",
+                    input, i
+                ));
+            }
+            for i in 0..inputs.len() {
+                head.push_str(&format!("[track{}]", i));
+            }
+            head.push_str(&format!("concat=n={}:v=0:a=1", inputs.len()));
+        } else {
+            head = format!("[{}:a]", inputs.first().copied().unwrap_or(0));
+        }
+
+        let mut chain: Vec<String> = Vec::new();
+        let end = source_length.map(|length| length - self.trim_end);
+        match end {
+            Some(end) => chain.push(format!("atrim=start={:.3}:end={:.3}", self.trim_start, end)),
+            None if self.trim_start > 0.0 => chain.push(format!("atrim=start={:.3}", self.trim_start)),
+            None => {}
+        }
+        if !chain.is_empty() {
+            chain.push("asetpts=PTS-STARTPTS".to_string());
+        }
+        if self.loop_enabled {
+            chain.push("aloop=loop=-1:size=2e+09".to_string());
+        }
+
+        // Time left for the music after it comes in, and how much of that it fills
+        let remaining = (duration - self.start_offset).max(0.0);
+        let audible = match end {
+            Some(end) if !self.loop_enabled => (end - self.trim_start).clamp(0.0, remaining),
+            _ => remaining,
+        };
+        chain.push(format!("atrim=end={:.3}", remaining));
+        chain.push("asetpts=PTS-STARTPTS".to_string());
+        chain.push(format!("volume={}", self.volume as f64 / 100.0));
+        if self.fade_in > 0.0 {
+            chain.push(format!("afade=t=in:st=0:d={:.3}", self.fade_in.min(audible)));
+        }
+        if self.fade_out > 0.0 {
+            let fade = self.fade_out.min(audible);
+            chain.push(format!("afade=t=out:st={:.3}:d={:.3}", audible - fade, fade));
+        }
+        if self.start_offset > 0.0 {
+            let delay_ms = (self.start_offset * 1000.0) as i64;
+            chain.push(format!("adelay={}|{}", delay_ms, delay_ms));
+        }
+        let separator = if inputs.len() > 1 { "," } else { "" };
+        format!("{}{}{}[bgmusic]", head, separator, chain.join(","))
+    }
+}
+
+// Sidechain compression settings for ducking background music under speech
+#[derive(Debug, Clone, Copy)]
+struct DuckingOptions {
+    threshold: f64,
+    ratio: f64,
+    attack_ms: f64,
+    release_ms: f64,
+}
+
+impl DuckingOptions {
+    fn new(threshold: Option<f64>, ratio: Option<f64>, attack_ms: Option<f64>, release_ms: Option<f64>) -> Result<Self, String> {
+        // Defaults: duck once speech rises above ~-24 dB, firm enough to pull the
+        // music down ~10 dB, quick to dip and slow enough to recover in short pauses
+        let threshold = threshold.unwrap_or(0.063);
+        let ratio = ratio.unwrap_or(8.0);
+        let attack_ms = attack_ms.unwrap_or(20.0);
+        let release_ms = release_ms.unwrap_or(250.0);
+        // Ranges accepted by FFmpeg's sidechaincompress
+        if !(0.000976563..=1.0).contains(&threshold) {
+            return Err(format!("Invalid duck threshold {}: must be between 0.000976563 and 1", threshold));
+        }
+        if !(1.0..=20.0).contains(&ratio) {
+            return Err(format!("Invalid duck ratio {}: must be between 1 and 20", ratio));
+        }
+        if !(0.01..=2000.0).contains(&attack_ms) {
+            return Err(format!("Invalid duck attack {}ms: must be between 0.01 and 2000", attack_ms));
+        }
+        if !(0.01..=9000.0).contains(&release_ms) {
+            return Err(format!("Invalid duck release {}ms: must be between 0.01 and 9000", release_ms));
+        }
+        Ok(DuckingOptions { threshold, ratio, attack_ms, release_ms })
+    }
+}
+
+// Mix [aout] with [bgmusic] into [final], optionally ducking the music under [aout].
+// Gain staging: clip and track volumes are applied per clip, the clips are summed
+// and scaled by the main volume into [aout], and the music has its own volume.
+// amix would otherwise divide both by two, so normalize=0 keeps the speech at the
+// same level whether or not there is music under it
+fn bg_music_mix_filter(ducking: Option<&DuckingOptions>) -> String {
+    match ducking {
+        Some(duck) => format!(
+            "[aout]asplit=2[voice][duckkey];[bgmusic][duckkey]sidechaincompress=threshold={}:ratio={}:attack={}:release={}[ducked];[voice][ducked]amix=inputs=2:duration=first:dropout_transition=2:normalize=0[final]",
+            duck.threshold, duck.ratio, duck.attack_ms, duck.release_ms
+        ),
+        None => "[aout][bgmusic]amix=inputs=2:duration=first:dropout_transition=2:normalize=0[final]".to_string(),
+    }
+}
+
+// Fold the mix ending at `label` down to mono in [mono]
+fn mono_downmix_filter(label: &str) -> String {
+    format!("{}aformat=channel_layouts=stereo,pan=mono|c0=0.5*c0+0.5*c1[mono]", label)
+}
+
+// Last stage before encoding: limit the finished audio ending at `label` (music,
+// downmix and loudness normalization included) to just under 0 dBFS, in [safe]
+fn clip_guard_filter(label: &str) -> String {
+    format!("{}alimiter=limit={}:level=0[safe]", label, MasterLimiter::DEFAULT_CEILING)
+}
+
+// Master bus stage for the summed mix: a gentle compressor above `threshold_db`, then a
+// brickwall limiter at `ceiling` (linear, 1.0 is 0 dBFS) so overlapping clips don't clip
+#[derive(Debug, Clone, Copy)]
+struct MasterLimiter {
+    threshold_db: f64,
+    ceiling: f64,
+}
+
+impl MasterLimiter {
+    const DEFAULT_THRESHOLD_DB: f64 = -6.0;
+    const DEFAULT_CEILING: f64 = 0.97;
+
+    // None unless `enabled` is set; off by default
+    fn from_options(enabled: Option<bool>, threshold_db: Option<f64>, ceiling: Option<f64>) -> Result<Option<Self>, WavecastError> {
+        if !enabled.unwrap_or(false) {
+            return Ok(None);
+        }
+        let threshold_db = threshold_db.unwrap_or(Self::DEFAULT_THRESHOLD_DB);
+        if !(-60.0..=0.0).contains(&threshold_db) {
+            return Err(WavecastError::invalid("limiter_threshold", format!(
+                "Invalid limiter threshold {} dB: must be between -60 and 0",
+                threshold_db
+            )));
+        }
+        let ceiling = ceiling.unwrap_or(Self::DEFAULT_CEILING);
+        if !(0.0625..=1.0).contains(&ceiling) {
+            return Err(WavecastError::invalid("limiter_ceiling", format!(
+                "Invalid limiter ceiling {}: must be between 0.0625 and 1",
+                ceiling
+            )));
+        }
+        Ok(Some(MasterLimiter { threshold_db, ceiling }))
+    }
+
+    // level=0 keeps alimiter from turning the limited signal back up to full scale
+    fn filter(&self) -> String {
+        format!(
+            "acompressor=threshold={:.6}:ratio=4:attack=5:release=100,alimiter=limit={}:level=0",
+            10f64.powf(self.threshold_db / 20.0),
+            self.ceiling
+        )
+    }
+}
+
+// Register an export job and run `export` for it on a blocking thread, since exports
+// spawn FFmpeg and wait on it for minutes at a time. Returns the job id straight away;
+// the outcome is recorded on the job and sent as export-finished
+fn start_export_job<F>(app: tauri::AppHandle, export: F) -> u64
+where
+    F: FnOnce(tauri::AppHandle, &ExportJob) -> Result<String, WavecastError> + Send + 'static,
+{
+    let job = app.state::<ExportJobs>().start();
+    let id = job.id;
+    eprintln!("Starting export job {}", id);
+
+    tauri::async_runtime::spawn(async move {
+        let export_job = job.clone();
+        let export_app = app.clone();
+        let result = tauri::async_runtime::spawn_blocking(move || export(export_app, &export_job))
+            .await
+            .map_err(|e| {
+                let err_msg = format!("Export task failed: {}", e);
+                eprintln!("ERROR: {}", err_msg);
+                WavecastError::from(err_msg)
+            })
+            .and_then(|result| result);
+
+        let status = match result {
+            Ok(output_path) => ExportJobStatus::Done { output_path },
+            Err(WavecastError::Cancelled) => ExportJobStatus::Cancelled,
+            Err(error) => ExportJobStatus::Failed { error },
+        };
+        eprintln!("Export job {} finished: {:?}", id, status);
+        *job.status.lock().unwrap() = status.clone();
+        let _ = app.emit("export-finished", ExportFinished { job_id: id, status });
+    });
+
+    id
+}
+
+// One image of a slideshow background and how many seconds it stays up
+#[derive(Deserialize, Debug, Clone)]
+struct SlideshowImage {
+    path: String,
+    duration: f64,
+}
+
+// A slideshow image as it ends up in the export: its FFmpeg input, how long it
+// actually shows and its scale/motion filter
+#[derive(Debug)]
+struct PlannedSlide {
+    input: usize,
+    duration: f64,
+    filter: String,
+}
+
+// Everything a timeline video export is configured with, passed by the frontend as one
+// object to both convert_timeline_to_video and preview_export_command
+#[derive(Deserialize)]
+struct TimelineVideoOptions {
+    image_path: String,
+    timeline: TimelineData,
+    background_style: String,
+    // bg_music replaces bg_music_path(s), bg_music_volume and bg_music_fade_out, which
+    // are still read when it's left out
+    bg_music: Option<BackgroundMusic>,
+    bg_music_path: Option<String>,
+    bg_music_paths: Option<Vec<String>>,
+    bg_music_volume: Option<i32>,
+    main_audio_volume: i32,
+    output_filename: Option<String>,
+    preset: Option<VideoPreset>,
+    output_format: Option<String>,
+    fps: Option<u32>,
+    crossfade_duration: Option<f64>,
+    duck_music: Option<bool>,
+    duck_threshold: Option<f64>,
+    duck_ratio: Option<f64>,
+    duck_attack: Option<f64>,
+    duck_release: Option<f64>,
+    output_path: Option<String>,
+    loudness_target: Option<f64>,
+    // Fold the finished mix down to one channel
+    mono: Option<bool>,
+    // Output sample rate (44100 or 48000) and channel count (1 or 2), FFmpeg's choice when unset
+    sample_rate: Option<u32>,
+    channels: Option<u32>,
+    // Compress and limit the summed mix
+    master_limiter: Option<bool>,
+    limiter_threshold: Option<f64>,
+    limiter_ceiling: Option<f64>,
+    // Limit the final output, after the music and loudness normalization
+    prevent_clipping: Option<bool>,
+    overwrite: Option<bool>,
+    bg_music_fade_out: Option<f64>,
+    lead_in_seconds: Option<f64>,
+    tail_seconds: Option<f64>,
+    encoding: Option<EncodingOptions>,
+    use_hardware_encoder: Option<bool>,
+    chapters: Option<Vec<ChapterMarker>>,
+    auto_chapters: Option<bool>,
+    metadata: Option<VideoMetadata>,
+    background_video: Option<String>,
+    // Images shown one after another instead of image_path. The last one stays up
+    // until the end, and images that would start after the end are left out
+    slideshow: Option<Vec<SlideshowImage>>,
+    motion: Option<String>,
+    waveform: Option<WaveformStyle>,
+    watermark: Option<WatermarkSpec>,
+    export_thumbnail: Option<bool>,
+    thumbnail_timestamp: Option<f64>,
+}
+
+// A timeline export worked out from its options: validated settings, inputs and filters.
+// The export and the command preview share it
+struct TimelineVideoPlan {
+    image_path: String,
+    output_format: OutputFormat,
+    // Whether input 0 is a background video rather than the still image
+    background_is_video: bool,
+    bg_music_path: Option<String>,
+    fps: u32,
+    encoding: EncodingOptions,
+    metadata: VideoMetadata,
+    cover_image: Option<String>,
+    ducking: Option<DuckingOptions>,
+    audio_dir: PathBuf,
+    output_name: String,
+    preset: VideoPreset,
+    width: u32,
+    height: u32,
+    inputs: InputRegistry,
+    unique_sources: Vec<String>,
+    watermark: Option<WatermarkSpec>,
+    watermark_input: Option<usize>,
+    waveform: Option<WaveformStyle>,
+    padded_duration: f64,
+    video_filter: String,
+    // Slideshow images in the order shown, the first one being input 0. Empty
+    // when input 0 is the whole background
+    slides: Vec<PlannedSlide>,
+    // The mixed audio before loudness normalization, ending at `audio_label`
+    audio_filter: String,
+    audio_label: &'static str,
+    // -ar/-ac for the output, empty to keep FFmpeg's choice
+    audio_format_args: Vec<String>,
+    loudness_target: Option<f64>,
+    // Brickwall-limit the finished audio right before it is encoded
+    prevent_clipping: bool,
+    // FFMETADATA text for the chapters, written to a temp file for the run
+    chapters_metadata: Option<String>,
+}
+
+// `probe_sources` lets the plan run ffprobe for the clip and music lengths trim_end
+// needs. Previews pass false so they run nothing, at the cost of trim_end only applying
+// where the frontend sent the length
+fn plan_timeline_video(options: &TimelineVideoOptions, probe_sources: bool) -> Result<TimelineVideoPlan, WavecastError> {
+    eprintln!("Image path: {}", options.image_path);
+    eprintln!("Background video: {:?}", options.background_video);
+    eprintln!("Slideshow: {:?}", options.slideshow);
+    eprintln!("Timeline tracks: {}", options.timeline.tracks.len());
+    eprintln!("Background style: {}", options.background_style);
+    eprintln!("Main audio volume: {}", options.main_audio_volume);
+    let bg_music = BackgroundMusic::resolve(
+        options.bg_music.clone(),
+        options.bg_music_path.clone(),
+        options.bg_music_paths.clone(),
+        options.bg_music_volume,
+        options.bg_music_fade_out,
+    );
+    eprintln!("BG music: {:?}", bg_music);
+
+    let output_format = OutputFormat::parse(options.output_format.as_deref().unwrap_or("mp4"))
+        .map_err(|reason| WavecastError::invalid("output_format", reason))?;
+    eprintln!("Output format: {:?}", output_format);
+    let slideshow = options.slideshow.as_deref().unwrap_or_default();
+    if !slideshow.is_empty() && options.background_video.is_some() {
+        return Err(WavecastError::invalid("slideshow", "A slideshow can't be combined with a background video"));
+    }
+    for image in slideshow {
+        require_file(&image.path)?;
+        if !image.duration.is_finite() || image.duration <= 0.0 {
+            return Err(WavecastError::invalid("slideshow", format!(
+                "Invalid duration {} for slideshow image {}: must be more than 0 seconds",
+                image.duration, image.path
+            )));
+        }
+    }
+    // A background video or the first slideshow image replaces the still image as input 0
+    let background = match slideshow.first() {
+        Some(image) => image.path.as_str(),
+        None => options.background_video.as_deref().unwrap_or(&options.image_path),
+    };
+    require_file(background)?;
+    let bg_music_length = match bg_music {
+        Some(ref music) => {
+            for path in music.paths() {
+                require_file(path)?;
+            }
+            music.validate().map_err(|reason| WavecastError::invalid("bg_music", reason))?;
+            if probe_sources { music.source_length()? } else { None }
+        }
+        None => None,
+    };
+    let motion = match options.motion {
+        Some(ref motion) => BackgroundMotion::parse(motion).map_err(|reason| WavecastError::invalid("motion", reason))?,
+        None => None,
+    };
+    if motion.is_some() && options.background_video.is_some() {
+        return Err(WavecastError::invalid("motion", "Background motion only applies to a still image, not a background video"));
+    }
+    eprintln!("Background motion: {:?}", motion);
+    if let Some(ref waveform) = options.waveform {
+        waveform.validate().map_err(|reason| WavecastError::invalid("waveform", reason))?;
+    }
+    eprintln!("Waveform: {:?}", options.waveform);
+    if let Some(ref watermark) = options.watermark {
+        watermark.validate().map_err(|reason| WavecastError::invalid("watermark", reason))?;
+    }
+    eprintln!("Watermark: {:?}", options.watermark);
+    // Without a background video, motion or waveform the video chain is only the scaled still image
+    // (or images, for a slideshow)
+    let animated = options.background_video.is_some() || motion.is_some() || options.waveform.is_some();
+    let fps = output_frame_rate(options.fps, animated).map_err(|reason| WavecastError::invalid("fps", reason))?;
+    let mut encoding = options.encoding.clone().unwrap_or_default();
+    encoding.validate().map_err(|reason| WavecastError::invalid("encoding", reason))?;
+    encoding.moving_picture = animated;
+    eprintln!("Encoding: {:?}", encoding);
+    let metadata = options.metadata.clone().unwrap_or_default();
+    eprintln!("Metadata: {:?}", metadata);
+    // WebM has no cover art stream, only mp4 and mov do
+    let cover_image = match metadata.cover_image(&options.image_path) {
+        Some(_) if output_format == OutputFormat::Webm => {
+            eprintln!("WARNING: cover art is not supported in webm output, skipping it");
+            None
+        }
+        cover => cover.map(str::to_string),
+    };
+
+    let crossfade_duration = options.crossfade_duration.unwrap_or(0.0);
+    if !(0.0..=10.0).contains(&crossfade_duration) {
+        return Err(WavecastError::invalid("crossfade_duration", format!(
+            "Invalid crossfade duration {}: must be between 0 and 10 seconds",
+            crossfade_duration
+        )));
+    }
+
+    let loudness_target = validate_loudness_target(options.loudness_target)
+        .map_err(|reason| WavecastError::invalid("loudness_target", reason))?;
+    eprintln!("Loudness target: {:?}", loudness_target);
+
+    // Opus only encodes at 48 kHz (among the rates offered)
+    if output_format == OutputFormat::Webm && options.sample_rate == Some(44100) {
+        return Err(WavecastError::invalid("sample_rate", "WebM audio is always 48000 Hz"));
+    }
+    let audio_format_args = output_audio_args(options.sample_rate, options.channels)?;
+
+    // Silence before the first clip and after the last one
+    let lead_in = options.lead_in_seconds.unwrap_or(0.0);
+    let tail = options.tail_seconds.unwrap_or(0.0);
+    for (field, name, value) in [("lead_in_seconds", "lead-in", lead_in), ("tail_seconds", "tail", tail)] {
+        if !(0.0..=60.0).contains(&value) {
+            return Err(WavecastError::invalid(field, format!("Invalid {} {}: must be between 0 and 60 seconds", name, value)));
+        }
+    }
+
+    // Ducking only applies when there is background music to duck
+    let ducking = if options.duck_music.unwrap_or(false) && bg_music.is_some() {
+        Some(
+            DuckingOptions::new(options.duck_threshold, options.duck_ratio, options.duck_attack, options.duck_release)
+                .map_err(|reason| WavecastError::invalid("ducking", reason))?,
+        )
+    } else {
+        None
+    };
+
+    let mut all_clips = audible_clips(&options.timeline)?;
+    for clip in &all_clips {
+        require_file(&clip.clip.source_file)?;
+    }
+    if probe_sources {
+        fill_source_durations(&mut all_clips);
+    }
+
+    // Default the save location to the first clip's directory
+    let first_clip_with_vol = &all_clips[0];
+    eprintln!("First clip source: {}", first_clip_with_vol.clip.source_file);
+    let audio_dir = PathBuf::from(&first_clip_with_vol.clip.source_file)
+        .parent()
+        .ok_or_else(|| {
+            eprintln!("ERROR: Could not determine audio directory");
+            "Could not determine audio directory".to_string()
+        })?
+        .to_path_buf();
+
+    // Use provided filename or default to "output.<ext>"
+    let output_name = options.output_filename.as_ref()
+        .map(|name| sanitize_output_filename(name, output_format.extension()))
+        .unwrap_or_else(|| format!("output.{}", output_format.extension()));
+
+    // Determine filter based on background style and resolution preset
+    let preset = options.preset.unwrap_or_default();
+    let (width, height) = preset.dimensions().map_err(|reason| WavecastError::invalid("preset", reason))?;
+    eprintln!("Resolution preset: {:?} ({}x{})", preset, width, height);
+    let scale_filter = build_video_filter(&options.background_style, width, height);
+    let mut video_filter = scale_filter.clone();
+
+    let main_volume = options.main_audio_volume as f64 / 100.0;
+
+    // Inputs in the order they are passed to FFmpeg, so the filters can refer to them by index
+    let mut inputs = InputRegistry::default();
+    inputs.add(background);
+
+    let bg_music_inputs = bg_music.as_ref().map(|music| {
+        music.paths()
+            .map(|path| {
+                eprintln!("Adding background music input: {}", path);
+                inputs.add(path)
+            })
+            .collect::<Vec<usize>>()
+    });
+
+    // Add each unique source file as input
+    let unique_sources = unique_clip_sources(&all_clips);
+    for source in &unique_sources {
+        inputs.add_source(source);
+    }
+
+    let watermark_input = options.watermark.as_ref().map(|watermark| {
+        eprintln!("Adding watermark input: {}", watermark.path);
+        inputs.add(&watermark.path)
+    });
+
+    // Calculate total duration for progress percentage and the music fade
+    let total_duration = timeline_duration(&all_clips);
+    eprintln!("Total duration: {:.2}s", total_duration);
+
+    // Length of the whole video including the silent lead-in and tail
+    let padded_duration = total_duration + lead_in + tail;
+    eprintln!("Padded duration: {:.2}s (lead-in {}s, tail {}s)", padded_duration, lead_in, tail);
+
+    // The motion is timed to span the whole padded video
+    if let Some(motion) = motion {
+        video_filter = format!("{},{}", video_filter, motion.filter(width, height, fps, padded_duration));
+    }
+
+    // Each slideshow image gets its own input and filter, with the motion spread over
+    // the time it is shown
+    let mut slides: Vec<PlannedSlide> = Vec::new();
+    let mut slide_start = 0.0;
+    for (i, image) in slideshow.iter().enumerate() {
+        let remaining = padded_duration - slide_start;
+        if remaining <= 0.0 {
+            eprintln!("Leaving out {} slideshow images that would start after the end", slideshow.len() - i);
+            break;
+        }
+        let duration = if i + 1 == slideshow.len() { remaining } else { image.duration.min(remaining) };
+        let mut filter = format!("{},setsar=1", scale_filter);
+        if let Some(motion) = motion {
+            filter = format!("{},{}", filter, motion.filter(width, height, fps, duration));
+        }
+        let input = if i == 0 { 0 } else { inputs.add(&image.path) };
+        eprintln!("  Slide {}: {} at {:.2}s for {:.2}s (input {})", i, image.path, slide_start, duration, input);
+        slides.push(PlannedSlide { input, duration, filter });
+        slide_start += duration;
+    }
+
+    // Generate audio filter complex
+    eprintln!("Generating audio filter complex...");
+    let limiter = MasterLimiter::from_options(options.master_limiter, options.limiter_threshold, options.limiter_ceiling)?;
+    let mut audio_filter = generate_filter_complex(&all_clips, &inputs, main_volume, crossfade_duration, lead_in, tail, limiter.as_ref())?;
+
+    // If background music is provided, mix it with the main audio
+    if let (Some(music), Some(bg_music_inputs)) = (bg_music.as_ref(), bg_music_inputs.as_deref()) {
+        eprintln!("Adding background music mixing (volume: {}%)", music.volume);
+
+        // The filter complex from generate_filter_complex outputs to [aout]; mix the music into it
+        audio_filter = format!(
+            "{};{};{}",
+            audio_filter,
+            music.filter(bg_music_inputs, padded_duration, bg_music_length),
+            bg_music_mix_filter(ducking.as_ref())
+        );
+    }
+
+    let mut audio_label = if bg_music_inputs.is_some() { "[final]" } else { "[aout]" };
+    if options.mono.unwrap_or(false) {
+        eprintln!("Downmixing to mono");
+        audio_filter = format!("{};{}", audio_filter, mono_downmix_filter(audio_label));
+        audio_label = "[mono]";
+    }
+
+    // Chapters go in through an FFMETADATA file added as the last input
+    let chapters = match options.chapters {
+        Some(ref chapters) if !chapters.is_empty() => {
+            validate_chapters(chapters, total_duration + tail).map_err(|reason| WavecastError::invalid("chapters", reason))?;
+            chapters.clone()
+        }
+        _ if options.auto_chapters.unwrap_or(false) => chapters_from_clips(&all_clips),
+        _ => Vec::new(),
+    };
+    let chapters_metadata = (!chapters.is_empty()).then(|| {
+        let metadata = build_chapters_metadata(&chapters, lead_in, padded_duration);
+        eprintln!("Chapters metadata:\n{}", metadata);
+        metadata
+    });
+
+    Ok(TimelineVideoPlan {
+        image_path: options.image_path.clone(),
+        output_format,
+        background_is_video: options.background_video.is_some(),
+        bg_music_path: bg_music.map(|music| music.path),
+        fps,
+        encoding,
+        metadata,
+        cover_image,
+        ducking,
+        audio_dir,
+        output_name,
+        preset,
+        width,
+        height,
+        inputs,
+        unique_sources,
+        watermark: options.watermark.clone(),
+        watermark_input,
+        waveform: options.waveform.clone(),
+        padded_duration,
+        video_filter,
+        slides,
+        audio_filter,
+        audio_label,
+        audio_format_args,
+        loudness_target,
+        prevent_clipping: options.prevent_clipping.unwrap_or(false),
+        chapters_metadata,
+    })
+}
+
+// Pick the requested encoder, or a hardware encoder when asked for one. Needs FFmpeg
+// to be available. Returns a warning when the requested encoder had to be replaced
+fn choose_timeline_encoder(plan: &mut TimelineVideoPlan, use_hardware_encoder: bool) -> Option<String> {
+    let warning = if plan.output_format == OutputFormat::Mp4 {
+        plan.encoding.choose_encoder(use_hardware_encoder)
+    } else {
+        if use_hardware_encoder {
+            eprintln!("WARNING: hardware encoding is only available for mp4 output");
+        }
+        None
+    };
+    eprintln!("Video encoder: {}", plan.encoding.encoder_name());
+    warning
+}
+
+// The FFmpeg arguments for encoding `plan`, up to but not including the output file.
+// `measured` is the loudness analysis when a target is set (single-pass loudnorm
+// without it) and `chapters_path` the FFMETADATA file holding plan.chapters_metadata
+fn timeline_video_args(
+    plan: &TimelineVideoPlan,
+    measured: Option<&LoudnessMeasurement>,
+    chapters_path: Option<&str>,
+) -> Result<Vec<String>, WavecastError> {
+    let fps = plan.fps.to_string();
+    let mut args: Vec<String> = Vec::new();
+    let mut push = |items: &[&str]| args.extend(items.iter().map(|item| item.to_string()));
+
+    // IMPORTANT: -loop 1 / -stream_loop -1 must come BEFORE the background input.
+    // Either way the background repeats forever and -t below cuts it to the program length.
+    let (image_rate_args, output_rate_args) = frame_rate_args(&fps);
+    if plan.background_is_video {
+        push(&["-stream_loop", "-1"]);
+    } else {
+        push(&image_rate_args);
+    }
+    for (index, path) in plan.inputs.paths().into_iter().enumerate() {
+        // Slideshow images loop like the still image, each only for as long as it shows
+        if let Some(slide) = plan.slides.iter().find(|slide| slide.input == index) {
+            if index > 0 {
+                push(&image_rate_args);
+            }
+            push(&["-t", &format!("{:.3}", slide.duration)]);
+        }
+        push(&["-i", path]);
+    }
+
+    if let Some(chapters_path) = chapters_path {
+        let chapters_input = plan.inputs.paths().len().to_string();
+        push(&["-f", "ffmetadata", "-i", chapters_path, "-map_chapters", &chapters_input]);
+    }
+
+    // Normalize the finished mix to the requested loudness
+    let mut audio_filter = plan.audio_filter.clone();
+    let mut audio_output_label = plan.audio_label;
+    if let Some(target) = plan.loudness_target {
+        audio_filter = format!("{};{}{}[norm]", audio_filter, audio_output_label, loudnorm_filter(target, measured));
+        audio_output_label = "[norm]";
+    }
+    if plan.prevent_clipping {
+        audio_filter = format!("{};{}", audio_filter, clip_guard_filter(audio_output_label));
+        audio_output_label = "[safe]";
+    }
+    eprintln!("Final audio filter complex: {}", audio_filter);
+
+    // With a slideshow, waveform or watermark the video is built in the filter graph too
+    push(&output_rate_args);
+    match (&plan.waveform, plan.watermark_input) {
+        (None, None) if plan.slides.is_empty() => {
+            push(&[
+                "-vf", &plan.video_filter,
+                "-filter_complex", &audio_filter,
+                "-map", "0:v:0",
+                "-map", audio_output_label,
+            ]);
+        }
+        _ => {
+            let mut chains = vec![audio_filter.clone()];
+            if plan.slides.is_empty() {
+                chains.push(format!("[0:v:0]{}[bg]", plan.video_filter));
+            } else {
+                let mut slide_labels = String::new();
+                for (i, slide) in plan.slides.iter().enumerate() {
+                    chains.push(format!("[{}:v:0]{}[slide{}]", slide.input, slide.filter, i));
+                    slide_labels.push_str(&format!("[slide{}]", i));
+                }
+                chains.push(format!("{}concat=n={}:v=1:a=0[bg]", slide_labels, plan.slides.len()));
+            }
+            let mut video_label = "[bg]";
+            let mut audio_label = audio_output_label;
+            if let Some(ref waveform) = plan.waveform {
+                chains.push(waveform.filter(audio_output_label, video_label, plan.width, plan.height, plan.fps));
+                video_label = "[vout]";
+                audio_label = "[amain]";
+            }
+            if let (Some(watermark), Some(input)) = (&plan.watermark, plan.watermark_input) {
+                chains.push(watermark.filter(input, video_label, plan.width));
+                video_label = "[vmark]";
+            }
+            let filter_complex = chains.join(";");
+            eprintln!("Filter complex with video overlays: {}", filter_complex);
+            push(&[
+                "-filter_complex", &filter_complex,
+                "-map", video_label,
+                "-map", audio_label,
+            ]);
+        }
+    }
+
+    args.extend(plan.output_format.codec_args(&plan.preset, &plan.encoding)?);
+    args.extend(plan.audio_format_args.iter().cloned());
+    args.extend(plan.metadata.args());
+    args.extend([
+        // The background loops forever, so stop at the end of the padded program
+        "-t".to_string(), format!("{:.3}", plan.padded_duration),
+        "-progress".to_string(), "pipe:1".to_string(),
+    ]);
+    Ok(args)
+}
+
+#[tauri::command]
+fn convert_timeline_to_video(app: tauri::AppHandle, options: TimelineVideoOptions) -> u64 {
+    start_export_job(app, move |app, job| render_timeline_video(app, job, options))
+}
+
+// The FFmpeg command line convert_timeline_to_video would run for `options`, program
+// first, without running anything. Nothing is measured, so a loudness target shows up as
+// single-pass loudnorm, a requested or hardware encoder is shown without the test encode
+// that would check it works, and source lengths aren't probed for trim_end (so music
+// trim_end isn't shown, and clip trim_end only where the length was sent). Without an
+// output_path the default file name is used instead of asking, and chapters are shown
+// at a placeholder path since no file is written
+#[tauri::command(async)]
+fn preview_export_command(options: TimelineVideoOptions) -> Result<Vec<String>, WavecastError> {
+    eprintln!("=== Previewing timeline export command ===");
+    let mut plan = plan_timeline_video(&options, false)?;
+    if plan.output_format == OutputFormat::Mp4 {
+        plan.encoding.assume_encoder(options.use_hardware_encoder.unwrap_or(false));
+    }
+
+    let output_path = match options.output_path {
+        Some(ref path) => {
+            let mut path = PathBuf::from(path);
+            if path.extension().is_none() {
+                path.set_extension(plan.output_format.extension());
+            }
+            path
+        }
+        None => plan.audio_dir.join(&plan.output_name),
+    };
+    let output = OutputTarget {
+        name: plan.output_name.clone(),
+        path: output_path,
+        replace: options.overwrite.unwrap_or(false),
+    };
+
+    let chapters_path = plan.chapters_metadata.as_ref()
+        .map(|_| std::env::temp_dir().join("wavecast-chapters.txt"));
+
+    let mut cmd = ffmpeg_command();
+    cmd.args(timeline_video_args(&plan, None, chapters_path.as_ref().and_then(|path| path.to_str()))?)
+        .args(output.args());
+    let inner = cmd.as_inner();
+    let command_line: Vec<String> = std::iter::once(inner.get_program())
+        .chain(inner.get_args())
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect();
+    eprintln!("Preview: {:?}", command_line);
+    Ok(command_line)
+}
+
+fn render_timeline_video(
+    app: tauri::AppHandle,
+    job: &ExportJob,
+    options: TimelineVideoOptions,
+) -> Result<String, WavecastError> {
+    eprintln!("=== Starting timeline-based video conversion ===");
+
+    // Download FFmpeg if not present. Planning probes source lengths, so this comes first
+    eprintln!("Checking for FFmpeg...");
+    ensure_ffmpeg(&app, job)?;
+    eprintln!("FFmpeg ready");
+    emit_export_stage(&app, job, ExportStage::PreparingInputs);
+    // Validate before planning so clip problems are reported with their track and clip
+    // rather than as whatever error planning runs into first
+    require_valid_timeline(&options.timeline)?;
+    let mut plan = plan_timeline_video(&options, true)?;
+
+    // Overlapping clips on one track play on top of each other; export them as they
+    // are, but tell the user which ones
+    let overlaps = describe_overlaps(&options.timeline);
+    if !overlaps.is_empty() {
+        for overlap in &overlaps {
+            eprintln!("WARNING: {}", overlap);
+        }
+        emit_export_warning(&app, job, format!(
+            "Some clips overlap and will play at the same time: {}",
+            overlaps.join("; ")
+        ));
+    }
+
+    if let Some(warning) = choose_timeline_encoder(&mut plan, options.use_hardware_encoder.unwrap_or(false)) {
+        emit_export_warning(&app, job, warning);
+    }
+    let _ = app.emit("export-encoder", plan.encoding.encoder_name());
+
+    let mut output = resolve_output_path(
+        &app,
+        options.output_path,
+        &plan.audio_dir,
+        &plan.output_name,
+        plan.output_format.extension(),
+        options.overwrite.unwrap_or(false),
+    )?;
+    app.state::<ExportJobs>().claim_output(job, &output.path)?;
+    eprintln!("Output path: {}", output.path.display());
+
+    // Two-pass loudnorm: measure the finished mix first
+    let measured = match plan.loudness_target {
+        Some(target) => {
+            eprintln!("Measuring loudness (target {} LUFS)...", target);
+            measure_loudness(&app, job, &plan.inputs.paths(), &plan.audio_filter, plan.audio_label, target)
+                .map_err(|e| {
+                    if job.is_cancelled() {
+                        eprintln!("Export cancelled during loudness analysis");
+                        finish_cancelled_export(&app, job, &[]);
+                        return WavecastError::Cancelled;
+                    }
+                    WavecastError::from(e)
+                })?
+        }
+        None => None,
+    };
+
+    let chapters_file = match plan.chapters_metadata {
+        Some(ref metadata) => {
+            let path = TempFile::unique("chapters", "txt");
+            std::fs::write(path.path(), metadata).map_err(|e| {
+                let err_msg = format!("Failed to write chapters file: {}", e);
+                eprintln!("ERROR: {}", err_msg);
+                err_msg
+            })?;
+            Some(path)
+        }
+        None => None,
+    };
+
+    let args = timeline_video_args(&plan, measured.as_ref(), chapters_file.as_ref().map(TempFile::to_str))?;
+
+    // Log the complete FFmpeg command for debugging
+    eprintln!("=== FFmpeg Command Debug ===");
+    eprintln!("Image path: {}", plan.image_path);
+    if let Some(ref music_path) = plan.bg_music_path {
+        eprintln!("Music path: {}", music_path);
+    }
+    eprintln!("Unique audio sources: {:?}", plan.unique_sources);
+    eprintln!("Video filter: {}", plan.video_filter);
+    eprintln!("Audio filter: {}", plan.audio_filter);
+    eprintln!("Output path: {}", output.path.display());
+    eprintln!("===========================");
+
+    // Spawn process and capture events
+    eprintln!("Spawning FFmpeg process...");
+    emit_export_stage(&app, job, ExportStage::Encoding);
+    let mut eta = EtaEstimate::default();
+    let build = || {
+        let mut cmd = ffmpeg_command();
+        cmd.args(&args);
+        cmd
+    };
+    let run_result = run_encode(&app, job, &mut output, build, |event| {
+        match event {
+            FfmpegEvent::Progress(progress) => {
+                // Emit progress event
+                emit_export_progress(&app, job, export_progress(job.id, &progress, plan.padded_duration, &mut eta));
+            }
+            FfmpegEvent::Log(_level, msg) => {
+                // Optionally log messages
+                eprintln!("FFmpeg: {}", msg);
+            }
+            _ => {}
+        }
+    });
+    drop(chapters_file);
+    let result = run_result.map_err(|e| {
+        if job.is_cancelled() {
+            eprintln!("Export cancelled, removing partial output");
+            finish_cancelled_export(&app, job, &[&output.path]);
+            return WavecastError::Cancelled;
+        }
+        e
+    })?;
+
+    if !result.success() {
+        eprintln!("ERROR: FFmpeg encoding failed");
+        eprintln!("ERROR CONTEXT:");
+        eprintln!("  - Image: {}", plan.image_path);
+        eprintln!("  - Audio sources: {:?}", plan.unique_sources);
+        eprintln!("  - Video filter: {}", plan.video_filter);
+        eprintln!("  - Audio filter: {}", plan.audio_filter);
+        eprintln!("  - Has BG music: {}", plan.bg_music_path.is_some());
+        eprintln!("  - Ducking: {:?}", plan.ducking);
+        return Err(result.error());
+    }
+
+    emit_export_stage(&app, job, ExportStage::Finalizing);
+    if let Some(ref cover) = plan.cover_image {
+        embed_cover_art(job, &output.path, cover).map_err(|e| {
+            if job.is_cancelled() {
+                eprintln!("Export cancelled while embedding cover art");
+                finish_cancelled_export(&app, job, &[&output.path]);
+                return WavecastError::Cancelled;
+            }
+            WavecastError::from(e)
+        })?;
+    }
+
+    if options.export_thumbnail.unwrap_or(false) {
+        export_poster_frame(&app, &output.path, options.thumbnail_timestamp, plan.width);
+    }
+
+    emit_export_stage(&app, job, ExportStage::Done);
+    eprintln!("=== Timeline video conversion completed successfully ===");
+    eprintln!("Output file: {}", output.path.display());
+    eprintln!("Video encoder: {}", plan.encoding.encoder_name());
+    Ok(output.path.to_str().unwrap().to_string())
+}
+
+// Everything a timeline audio export is configured with, passed by the frontend as one
+#[derive(Deserialize)]
+struct TimelineAudioOptions {
+    timeline: TimelineData,
+    bg_music: Option<BackgroundMusic>,
+    bg_music_path: Option<String>,
+    bg_music_paths: Option<Vec<String>>,
+    bg_music_volume: Option<i32>,
+    main_audio_volume: i32,
+    format: Option<String>,
+    bitrate: Option<String>,
+    output_filename: Option<String>,
+    output_path: Option<String>,
+    overwrite: Option<bool>,
+    crossfade_duration: Option<f64>,
+    bg_music_fade_out: Option<f64>,
+    loudness_target: Option<f64>,
+    // Fold the finished mix down to one channel
+    mono: Option<bool>,
+    // Output sample rate (44100 or 48000) and channel count (1 or 2), FFmpeg's choice when unset
+    sample_rate: Option<u32>,
+    channels: Option<u32>,
+    // Compress and limit the summed mix
+    master_limiter: Option<bool>,
+    limiter_threshold: Option<f64>,
+    limiter_ceiling: Option<f64>,
+    metadata: Option<VideoMetadata>,
+}
+
+#[tauri::command]
+fn export_timeline_audio(app: tauri::AppHandle, options: TimelineAudioOptions) -> u64 {
+    start_export_job(app, move |app, job| render_timeline_audio(app, job, options))
+}
+
+fn render_timeline_audio(app: tauri::AppHandle, job: &ExportJob, options: TimelineAudioOptions) -> Result<String, WavecastError> {
+    let TimelineAudioOptions {
+        timeline,
+        bg_music,
+        bg_music_path,
+        bg_music_paths,
+        bg_music_volume,
+        main_audio_volume,
+        format,
+        bitrate,
+        output_filename,
+        output_path,
+        overwrite,
+        crossfade_duration,
+        bg_music_fade_out,
+        loudness_target,
+        mono,
+        sample_rate,
+        channels,
+        master_limiter,
+        limiter_threshold,
+        limiter_ceiling,
+        metadata,
+    } = options;
+    eprintln!("=== Starting timeline audio export ===");
+    eprintln!("Timeline tracks: {}", timeline.tracks.len());
+    eprintln!("Main audio volume: {}", main_audio_volume);
+    let bg_music = BackgroundMusic::resolve(bg_music, bg_music_path, bg_music_paths, bg_music_volume, bg_music_fade_out);
+    eprintln!("BG music: {:?}", bg_music);
+
+    if let Some(ref music) = bg_music {
+        for path in music.paths() {
+            require_file(path)?;
+        }
+        music.validate().map_err(|reason| WavecastError::invalid("bg_music", reason))?;
+    }
+    let audio_format = AudioFormat::parse(format.as_deref().unwrap_or("mp3"))
+        .map_err(|reason| WavecastError::invalid("format", reason))?;
+    if !audio_format.has_bitrate() && bitrate.is_some() {
+        eprintln!("WARNING: {} is lossless, ignoring the bitrate", audio_format.extension());
+    }
+    let bitrate = bitrate.unwrap_or_else(|| "192k".to_string());
+    validate_audio_bitrate(&bitrate).map_err(|reason| WavecastError::invalid("bitrate", reason))?;
+    eprintln!("Audio format: {:?} at {}", audio_format, bitrate);
+    let audio_format_args = output_audio_args(sample_rate, channels)?;
+
+    let crossfade_duration = crossfade_duration.unwrap_or(0.0);
+    if !(0.0..=10.0).contains(&crossfade_duration) {
+        return Err(WavecastError::invalid("crossfade_duration", format!(
+            "Invalid crossfade duration {}: must be between 0 and 10 seconds",
+            crossfade_duration
+        )));
+    }
+
+    let loudness_target = validate_loudness_target(loudness_target)
+        .map_err(|reason| WavecastError::invalid("loudness_target", reason))?;
+    eprintln!("Loudness target: {:?}", loudness_target);
+
+    // Download FFmpeg if not present
+    eprintln!("Checking for FFmpeg...");
+    ensure_ffmpeg(&app, job)?;
+    eprintln!("FFmpeg ready");
+    emit_export_stage(&app, job, ExportStage::PreparingInputs);
+
+    let mut all_clips = audible_clips(&timeline)?;
+    for clip in &all_clips {
+        require_file(&clip.clip.source_file)?;
+    }
+    fill_source_durations(&mut all_clips);
+
+    // Default the save location to the first clip's directory
+    let audio_dir = PathBuf::from(&all_clips[0].clip.source_file)
+        .parent()
+        .ok_or_else(|| {
+            eprintln!("ERROR: Could not determine audio directory");
+            "Could not determine audio directory".to_string()
+        })?
+        .to_path_buf();
+
+    let output_name = output_filename
+        .map(|name| sanitize_output_filename(&name, audio_format.extension()))
+        .unwrap_or_else(|| format!("output.{}", audio_format.extension()));
+
+    let mut output = resolve_output_path(&app, output_path, &audio_dir, &output_name, audio_format.extension(), overwrite.unwrap_or(false))?;
+    app.state::<ExportJobs>().claim_output(job, &output.path)?;
+    eprintln!("Output path: {}", output.path.display());
+
+    let main_volume = main_audio_volume as f64 / 100.0;
+    let total_duration = timeline_duration(&all_clips);
+    eprintln!("Total duration: {:.2}s", total_duration);
+
+    // There's no image here: background music, if any, comes first and the clips follow it
+    let mut inputs = InputRegistry::default();
+    let bg_music_inputs = bg_music.as_ref().map(|music| music.paths().map(|path| inputs.add(path)).collect::<Vec<usize>>());
+    let has_bg_music = bg_music_inputs.is_some();
+    let unique_sources = unique_clip_sources(&all_clips);
+    for source in &unique_sources {
+        inputs.add_source(source);
+    }
+
+    let limiter = MasterLimiter::from_options(master_limiter, limiter_threshold, limiter_ceiling)?;
+    let mut audio_filter = generate_filter_complex(&all_clips, &inputs, main_volume, crossfade_duration, 0.0, 0.0, limiter.as_ref())?;
+    if let (Some(music), Some(bg_music_inputs)) = (bg_music.as_ref(), bg_music_inputs.as_deref()) {
+        let bg_music_length = music.source_length()?;
+        audio_filter = format!(
+            "{};{};{}",
+            audio_filter,
+            music.filter(bg_music_inputs, total_duration, bg_music_length),
+            bg_music_mix_filter(None)
+        );
+    }
+
+    let mut audio_output_label = if has_bg_music { "[final]" } else { "[aout]" };
+    if mono.unwrap_or(false) {
+        eprintln!("Downmixing to mono");
+        audio_filter = format!("{};{}", audio_filter, mono_downmix_filter(audio_output_label));
+        audio_output_label = "[mono]";
+    }
+
+    if let Some(target) = loudness_target {
+        eprintln!("Measuring loudness (target {} LUFS)...", target);
+        let measured = measure_loudness(&app, job, &inputs.paths(), &audio_filter, audio_output_label, target)
+            .map_err(|e| {
+                if job.is_cancelled() {
+                    eprintln!("Export cancelled during loudness analysis");
+                    finish_cancelled_export(&app, job, &[]);
+                    return WavecastError::Cancelled;
+                }
+                WavecastError::from(e)
+            })?;
+        audio_filter = format!("{};{}{}[norm]", audio_filter, audio_output_label, loudnorm_filter(target, measured.as_ref()));
+        audio_output_label = "[norm]";
+    }
+
+    eprintln!("Audio filter: {}", audio_filter);
+
+    let mut metadata_args = metadata.unwrap_or_default().args();
+    // ffmpeg writes ID3v2.4 by default, which older players and some car stereos ignore
+    if audio_format == AudioFormat::Mp3 && !metadata_args.is_empty() {
+        metadata_args.extend(["-id3v2_version".to_string(), "3".to_string()]);
+    }
+
+    let build = || {
+        let mut cmd = ffmpeg_command();
+        for input in inputs.paths() {
+            cmd.input(input);
+        }
+        cmd.args(&[
+            "-filter_complex", &audio_filter,
+            "-map", audio_output_label,
+        ])
+        .args(&audio_format.codec_args(&bitrate))
+        .args(&audio_format_args)
+        .args(&metadata_args)
+        .args(&[
+            "-t", &format!("{:.3}", total_duration),
+            "-progress", "pipe:1"
+        ]);
+        cmd
+    };
+
+    eprintln!("Spawning FFmpeg process...");
+    emit_export_stage(&app, job, ExportStage::Encoding);
+    let mut eta = EtaEstimate::default();
+    let result = run_encode(&app, job, &mut output, build, |event| match event {
+        FfmpegEvent::Progress(progress) => {
+            emit_export_progress(&app, job, export_progress(job.id, &progress, total_duration, &mut eta));
+        }
+        FfmpegEvent::Log(_level, msg) => {
+            eprintln!("FFmpeg: {}", msg);
+        }
+        _ => {}
+    })
+    .map_err(|e| {
+        if job.is_cancelled() {
+            eprintln!("Export cancelled, removing partial output");
+            finish_cancelled_export(&app, job, &[&output.path]);
+            return WavecastError::Cancelled;
+        }
+        e
+    })?;
+
+    if !result.success() {
+        eprintln!("ERROR: FFmpeg audio export failed");
+        eprintln!("ERROR CONTEXT:");
+        eprintln!("  - Audio sources: {:?}", unique_sources);
+        eprintln!("  - Audio filter: {}", audio_filter);
+        return Err(result.error());
+    }
+
+    emit_export_stage(&app, job, ExportStage::Done);
+    eprintln!("=== Timeline audio export completed successfully ===");
+    eprintln!("Output file: {}", output.path.display());
+    Ok(output.path.to_str().unwrap().to_string())
+}
+
+// Everything a still image video export is configured with, passed by the frontend as one
+#[derive(Deserialize)]
+struct StillVideoOptions {
+    image_path: String,
+    audio_paths: Vec<String>,
+    background_style: String,
+    bg_music_path: Option<String>,
+    bg_music_volume: i32,
+    main_audio_volume: i32,
+    preset: Option<VideoPreset>,
+    fps: Option<u32>,
+    output_path: Option<String>,
+    loudness_target: Option<f64>,
+    master_limiter: Option<bool>,
+    limiter_threshold: Option<f64>,
+    limiter_ceiling: Option<f64>,
+    overwrite: Option<bool>,
+    encoding: Option<EncodingOptions>,
+    use_hardware_encoder: Option<bool>,
+    metadata: Option<VideoMetadata>,
+    export_thumbnail: Option<bool>,
+    thumbnail_timestamp: Option<f64>,
+}
+
+#[tauri::command]
+fn convert_to_video(app: tauri::AppHandle, options: StillVideoOptions) -> u64 {
+    start_export_job(app, move |app, job| render_still_video(app, job, options))
+}
+
+fn render_still_video(app: tauri::AppHandle, job: &ExportJob, options: StillVideoOptions) -> Result<String, WavecastError> {
+    let StillVideoOptions {
+        image_path,
+        audio_paths,
+        background_style,
+        bg_music_path,
+        bg_music_volume,
+        main_audio_volume,
+        preset,
+        fps,
+        output_path,
+        loudness_target,
+        master_limiter,
+        limiter_threshold,
+        limiter_ceiling,
+        overwrite,
+        encoding,
+        use_hardware_encoder,
+        metadata,
+        export_thumbnail,
+        thumbnail_timestamp,
+    } = options;
+    eprintln!("=== Starting video conversion ===");
+    eprintln!("Image path: {}", image_path);
+    eprintln!("Audio paths: {:?}", audio_paths);
+    eprintln!("Background style: {}", background_style);
+    eprintln!("BG music path: {:?}", bg_music_path);
+    eprintln!("BG music volume: {}", bg_music_volume);
+    eprintln!("Main audio volume: {}", main_audio_volume);
+
+    require_file(&image_path)?;
+    for path in audio_paths.iter().chain(bg_music_path.iter()) {
+        require_file(path)?;
+    }
+
+    // The video chain is only the scaled still image
+    let fps = output_frame_rate(fps, false)
+        .map_err(|reason| WavecastError::invalid("fps", reason))?
+        .to_string();
+    let (image_rate_args, output_rate_args) = frame_rate_args(&fps);
+    let loudness_target = validate_loudness_target(loudness_target)
+        .map_err(|reason| WavecastError::invalid("loudness_target", reason))?;
+    eprintln!("Loudness target: {:?}", loudness_target);
+    let mut encoding = encoding.unwrap_or_default();
+    encoding.validate().map_err(|reason| WavecastError::invalid("encoding", reason))?;
+    eprintln!("Encoding: {:?}", encoding);
+    let metadata = metadata.unwrap_or_default();
+    eprintln!("Metadata: {:?}", metadata);
+    let metadata_args = metadata.args();
+
+    // Download FFmpeg if not present (will use cached version if available)
+    eprintln!("Checking for FFmpeg...");
+    ensure_ffmpeg(&app, job)?;
+    eprintln!("FFmpeg ready");
+    emit_export_stage(&app, job, ExportStage::PreparingInputs);
+
+    // Length of the program for the progress bar: the audio files back to back
+    let audio_durations = audio_paths
+        .iter()
+        .map(|path| probe_duration(path))
+        .collect::<Result<Vec<f64>, String>>()
+        .unwrap_or_else(|e| {
+            eprintln!("Warning: could not probe the audio length, progress will follow FFmpeg: {}", e);
+            Vec::new()
+        });
+    let total_duration: f64 = audio_durations.iter().sum();
+    eprintln!("Total duration: {:.2}s", total_duration);
+
+    if let Some(warning) = encoding.choose_encoder(use_hardware_encoder.unwrap_or(false)) {
+        emit_export_warning(&app, job, warning);
+    }
+    eprintln!("Video encoder: {}", encoding.encoder_name());
+    let _ = app.emit("export-encoder", encoding.encoder_name());
+
+    // Default the save location to the directory of the first audio file
+    let first_audio = audio_paths.first()
+        .ok_or_else(|| {
+            eprintln!("ERROR: No audio files provided");
+            "No audio files provided".to_string()
+        })?;
+    eprintln!("First audio file: {}", first_audio);
+
+    let audio_dir = PathBuf::from(first_audio)
+        .parent()
+        .ok_or_else(|| {
+            eprintln!("ERROR: Could not determine audio directory from path: {}", first_audio);
+            "Could not determine audio directory".to_string()
+        })?
+        .to_path_buf();
+
+    let mut output = resolve_output_path(&app, output_path, &audio_dir, "output.mp4", "mp4", overwrite.unwrap_or(false))?;
+    app.state::<ExportJobs>().claim_output(job, &output.path)?;
+    eprintln!("Output path: {}", output.path.display());
+
+    // Stream copy only works when every input shares a codec and sample layout,
+    // otherwise the joined file is re-encoded to PCM
+    let stream_copy = audio_paths.len() > 1 && concat_can_copy(&audio_paths);
+
+    // Intermediate file holding the concatenated audio when there are multiple inputs.
+    // It goes in the OS temp dir so nothing is left in the user's folders
+    let temp_audio = (audio_paths.len() > 1).then(|| {
+        let extension = if stream_copy {
+            std::path::Path::new(&audio_paths[0])
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("mp3")
+                .to_lowercase()
+        } else {
+            "wav".to_string()
+        };
+        TempFile::unique("combined", &extension)
+    });
+
+    // If multiple audio files, concatenate them first
+    let final_audio_path = if let Some(ref temp_audio) = temp_audio {
+        eprintln!("Multiple audio files detected, concatenating {} files...", audio_paths.len());
+        eprintln!("Concatenating to: {}", temp_audio.path().display());
+        let mut concat_cmd = ffmpeg_command();
+
+        // Kept until the concat has run
+        let _concat_list = if stream_copy {
+            let concat_list = TempFile::unique("concat", "txt");
+
+            // Create concat file
+            // Convert backslashes to forward slashes for FFmpeg compatibility on Windows
+            let concat_content = audio_paths
+                .iter()
+                .map(|p| {
+                    // The list lives in the temp dir, so relative paths would resolve against it
+                    let absolute_path = std::path::absolute(p).unwrap_or_else(|_| PathBuf::from(p));
+                    concat_list_entry(&absolute_path.to_string_lossy())
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            std::fs::write(concat_list.path(), &concat_content)
+                .map_err(|e| {
+                    let err_msg = format!("Failed to create concat list: {}", e);
+                    eprintln!("ERROR: {}", err_msg);
+                    err_msg
+                })?;
+            eprintln!("Created concat list at: {}", concat_list.path().display());
+
+            // -safe 0 is a demuxer option, so it has to come before the list input
+            concat_cmd
+                .format("concat")
+                .args(&["-safe", "0"])
+                .input(concat_list.to_str())
+                .args(&["-c", "copy"]);
+            Some(concat_list)
+        } else {
+            eprintln!("Audio inputs differ in format, re-encoding while concatenating");
+            for path in &audio_paths {
+                concat_cmd.input(path);
+            }
+            let concat_filter = resampled_concat_filter(audio_paths.len());
+            eprintln!("Concat filter: {}", concat_filter);
+            concat_cmd.args(&[
+                "-filter_complex",
+                concat_filter.as_str(),
+                "-map",
+                "[joined]",
+                "-c:a",
+                "pcm_s16le",
+            ]);
+            None
+        };
+        concat_cmd.overwrite().output(temp_audio.to_str());
+
+        eprintln!("Running FFmpeg concat command...");
+        let _ = app.emit("concat-progress", StageProgress {
+            job_id: job.id,
+            phase: "preparing audio".to_string(),
+            time: "00:00:00.00".to_string(),
+            progress: Some(0.0),
+        });
+        emit_export_stage(&app, job, ExportStage::ConcatenatingAudio { current: 0, total: audio_paths.len() });
+        // The concat demuxer only reports the first file's duration, so use the probed total
+        let mut concat_duration = total_duration;
+        let mut files_joined = 0;
+        let concat_result = run_ffmpeg(job, &mut concat_cmd, |event| match event {
+            FfmpegEvent::ParsedDuration(duration) if concat_duration <= 0.0 => concat_duration = duration.duration,
+            FfmpegEvent::Progress(progress) => {
+                let current_time = parse_time_to_seconds(&progress.time);
+                // A file is joined once the output is past its end
+                let joined = audio_durations
+                    .iter()
+                    .scan(0.0, |end, duration| {
+                        *end += duration;
+                        Some(*end)
+                    })
+                    .take_while(|end| *end <= current_time)
+                    .count();
+                if joined != files_joined {
+                    files_joined = joined;
+                    emit_export_stage(&app, job, ExportStage::ConcatenatingAudio { current: joined, total: audio_paths.len() });
+                }
+                let _ = app.emit("concat-progress", StageProgress {
+                    job_id: job.id,
+                    phase: "preparing audio".to_string(),
+                    time: progress.time.clone(),
+                    progress: if concat_duration > 0.0 {
+                        Some((current_time / concat_duration * 100.0).min(100.0))
+                    } else {
+                        None
+                    },
+                });
+            }
+            _ => {}
+        })
+            .map_err(|e| {
+                if job.is_cancelled() {
+                    eprintln!("Export cancelled during concatenation");
+                    finish_cancelled_export(&app, job, &[]);
+                    return WavecastError::Cancelled;
+                }
+                let err_msg = format!("Failed to concatenate audio: {}", e);
+                eprintln!("ERROR: {}", err_msg);
+                WavecastError::from(err_msg)
+            })?;
+
+        if !concat_result.success() {
+            eprintln!("ERROR: FFmpeg concatenation failed");
+            return Err(concat_result.error());
+        }
+        eprintln!("Audio concatenation successful");
+        emit_export_stage(&app, job, ExportStage::ConcatenatingAudio { current: audio_paths.len(), total: audio_paths.len() });
+
+        temp_audio.to_str().to_string()
+    } else {
+        eprintln!("Single audio file, no concatenation needed");
+        audio_paths[0].clone()
+    };
+    eprintln!("Final audio path: {}", final_audio_path);
+
+    // Determine filter based on background style and resolution preset
+    let preset = preset.unwrap_or_default();
+    let (width, height) = preset.dimensions().map_err(|reason| WavecastError::invalid("preset", reason))?;
+    let codec_args = OutputFormat::Mp4.codec_args(&preset, &encoding)?;
+    eprintln!("Resolution preset: {:?} ({}x{})", preset, width, height);
+    let video_filter = build_video_filter(&background_style, width, height);
+    eprintln!("Video filter: {}", video_filter);
+
+    // Calculate volumes as decimals (0-100 -> 0.0-1.0)
+    let main_volume = main_audio_volume as f32 / 100.0;
+    eprintln!("Main volume: {}", main_volume);
+
+    let bg_volume = bg_music_volume as f32 / 100.0;
+
+    // The master limiter, if any, goes straight after the mix
+    let limiter = MasterLimiter::from_options(master_limiter, limiter_threshold, limiter_ceiling)?
+        .map(|limiter| format!(",{}", limiter.filter()))
+        .unwrap_or_default();
+
+    // Create audio filter for mixing: loop bg music (input 1), adjust volumes, and mix
+    // with the main audio (input 2). The main audio goes first so duration=first ends
+    // the mix with it rather than with the endless loop.
+    let bg_mix_filter = format!(
+        "[1:a]aloop=loop=-1:size=2e+09[bg];[bg]volume={}[bg_vol];[2:a]volume={}[main];[main][bg_vol]amix=inputs=2:duration=first:dropout_transition=2:normalize=0{}",
+        bg_volume, main_volume, limiter
+    );
+
+    // Loudness normalization goes on the end of whichever audio chain is used below
+    let loudnorm = match loudness_target {
+        Some(target) => {
+            let mut inputs = vec![image_path.as_str()];
+            let graph = match bg_music_path {
+                Some(ref bg_music) => {
+                    inputs.push(bg_music);
+                    inputs.push(&final_audio_path);
+                    format!("{}[mixed]", bg_mix_filter)
+                }
+                None => {
+                    inputs.push(&final_audio_path);
+                    format!("[1:a]volume={}{}[mixed]", main_volume, limiter)
+                }
+            };
+
+            eprintln!("Measuring loudness (target {} LUFS)...", target);
+            let measured = measure_loudness(&app, job, &inputs, &graph, "[mixed]", target)
+                .map_err(|e| {
+                    if job.is_cancelled() {
+                        eprintln!("Export cancelled during loudness analysis");
+                        finish_cancelled_export(&app, job, &[]);
+                        return WavecastError::Cancelled;
+                    }
+                    WavecastError::from(e)
+                })?;
+            Some(loudnorm_filter(target, measured.as_ref()))
+        }
+        None => None,
+    };
+
+    // If background music is provided, we need to mix the audio
+    let _ = if let Some(bg_music) = bg_music_path {
+        eprintln!("Background music detected: {}", bg_music);
+        eprintln!("Background music volume: {}", bg_volume);
+
+        let audio_filter = match loudnorm {
+            Some(ref loudnorm) => format!("{}[mixed];[mixed]{}", bg_mix_filter, loudnorm),
+            None => bg_mix_filter,
+        };
+        eprintln!("Audio filter: {}", audio_filter);
+
+        let build = || {
+            let mut cmd = ffmpeg_command();
+            cmd
+                .args(&image_rate_args)
+                .input(&image_path)
+                .input(&bg_music)
+                .input(&final_audio_path)
+                .args(&output_rate_args)
+                .args(&[
+                    "-vf", &video_filter,
+                    "-filter_complex", &audio_filter,
+                    "-shortest",
+                    "-progress", "pipe:1"
+                ])
+                .args(&codec_args)
+                .args(&metadata_args);
+            cmd
+        };
+
+        eprintln!("Running FFmpeg with background music...");
+        emit_export_stage(&app, job, ExportStage::Encoding);
+        // Input 0: image, input 1: background music, input 2: main audio
+        let result = run_encode(&app, job, &mut output, build, encode_progress_handler(&app, job, 2, total_duration))
+            .map_err(|e| {
+                if job.is_cancelled() {
+                    eprintln!("Export cancelled, removing partial output");
+                    finish_cancelled_export(&app, job, &[&output.path]);
+                    return WavecastError::Cancelled;
+                }
+                e
+            })?;
+
+        if !result.success() {
+            eprintln!("ERROR: FFmpeg encoding failed (with background music)");
+            return Err(result.error());
+        }
+        eprintln!("FFmpeg encoding successful (with background music)");
+        result
+    } else {
+        // No background music, but still apply main audio volume
+        eprintln!("No background music, encoding with main audio only");
+        let audio_filter = match loudnorm {
+            Some(ref loudnorm) => format!("volume={}{},{}", main_volume, limiter, loudnorm),
+            None => format!("volume={}{}", main_volume, limiter),
+        };
+        eprintln!("Audio filter: {}", audio_filter);
+
+        let build = || {
+            let mut cmd = ffmpeg_command();
+            cmd
+                .args(&image_rate_args)
+                .input(&image_path)
+                .input(&final_audio_path)
+                .args(&output_rate_args)
+                .args(&[
+                    "-vf", &video_filter,
+                    "-af", &audio_filter,
+                    "-shortest",
+                    "-progress", "pipe:1"
+                ])
+                .args(&codec_args)
+                .args(&metadata_args);
+            cmd
+        };
+
+        eprintln!("Running FFmpeg without background music...");
+        emit_export_stage(&app, job, ExportStage::Encoding);
+        // Input 0: image, input 1: main audio
+        let result = run_encode(&app, job, &mut output, build, encode_progress_handler(&app, job, 1, total_duration))
+            .map_err(|e| {
+                if job.is_cancelled() {
+                    eprintln!("Export cancelled, removing partial output");
+                    finish_cancelled_export(&app, job, &[&output.path]);
+                    return WavecastError::Cancelled;
+                }
+                e
+            })?;
+
+        if !result.success() {
+            eprintln!("ERROR: FFmpeg encoding failed (without background music)");
+            return Err(result.error());
+        }
+        eprintln!("FFmpeg encoding successful (without background music)");
+        result
+    };
+
+    // Done with the concatenated audio
+    drop(temp_audio);
+
+    emit_export_stage(&app, job, ExportStage::Finalizing);
+    if let Some(cover) = metadata.cover_image(&image_path) {
+        embed_cover_art(job, &output.path, cover).map_err(|e| {
+            if job.is_cancelled() {
+                eprintln!("Export cancelled while embedding cover art");
+                finish_cancelled_export(&app, job, &[&output.path]);
+                return WavecastError::Cancelled;
+            }
+            WavecastError::from(e)
+        })?;
+    }
+
+    if export_thumbnail.unwrap_or(false) {
+        export_poster_frame(&app, &output.path, thumbnail_timestamp, width);
+    }
+
+    emit_export_stage(&app, job, ExportStage::Done);
+    eprintln!("=== Video conversion completed successfully ===");
+    eprintln!("Output file: {}", output.path.display());
+    eprintln!("Video encoder: {}", encoding.encoder_name());
+    Ok(output.path.to_str().unwrap().to_string())
+}
+
+// Stop export `id`, or every running export when no id is given
+#[tauri::command]
+fn cancel_export(jobs: tauri::State<'_, ExportJobs>, id: Option<u64>) -> Result<(), WavecastError> {
+    let targets: Vec<std::sync::Arc<ExportJob>> = match id {
+        Some(id) => vec![jobs.get(id)?],
+        None => jobs.jobs.lock().unwrap().values().cloned().collect(),
+    };
+
+    for job in targets.iter().filter(|job| job.is_running()) {
+        eprintln!("Cancelling export job {}...", job.id);
+        job.cancel()?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn get_export_status(jobs: tauri::State<'_, ExportJobs>, id: u64) -> Result<ExportJobStatus, WavecastError> {
+    let job = jobs.get(id)?;
+    let status = job.status.lock().unwrap().clone();
+    Ok(status)
+}
+
+// The frontend has seen how export `id` ended, so it can leave the registry
+#[tauri::command]
+fn acknowledge_export(jobs: tauri::State<'_, ExportJobs>, id: u64) -> Result<(), WavecastError> {
+    let job = jobs.get(id)?;
+    if job.is_running() {
+        return Err(WavecastError::invalid("id", format!("Export job {} is still running", id)));
+    }
+    jobs.jobs.lock().unwrap().remove(&id);
+    Ok(())
+}
+
+#[tauri::command]
+async fn upload_to_vimeo(
+    app: tauri::AppHandle,
+    video_path: String,
+    access_token: String,
+    title: String,
+    description: Option<String>,
+    privacy: Option<String>,
+    password: Option<String>,
+    tags: Option<Vec<String>>,
+    chunk_size_mb: Option<u64>,
+    max_retries: Option<u32>,
+    comments: Option<String>,
+    embed: Option<String>,
+    thumbnail_path: Option<String>,
+    folder_uri: Option<String>,
+    showcase_id: Option<String>,
+) -> Result<VimeoUploadResult, WavecastError> {
+    let details = VimeoVideoDetails {
+        description,
+        privacy,
+        password,
+        tags: tags.unwrap_or_default(),
+        comments,
+        embed,
+        thumbnail_path,
+        folder_uri,
+        showcase_id,
+    };
+    require_file(&video_path)?;
+    details.validate()?;
+    let tus = TusSettings::new(chunk_size_mb, max_retries)?;
+
+    let result = vimeo_upload(&app, &video_path, &access_token, &title, &details, tus).await;
+    emit_upload_result(&app, &result);
+    result
+}
+
+#[tauri::command]
+async fn resume_upload(
+    app: tauri::AppHandle,
+    video_path: String,
+    chunk_size_mb: Option<u64>,
+    max_retries: Option<u32>,
+    access_token: Option<String>,
+) -> Result<VimeoUploadResult, WavecastError> {
+    require_file(&video_path)?;
+    let tus = TusSettings::new(chunk_size_mb, max_retries)?;
+    let result = resume_vimeo_upload(&app, &video_path, access_token.as_deref(), tus).await;
+    emit_upload_result(&app, &result);
+    result
+}
+
+// Replace the file behind an existing Vimeo video (/videos/<id>) with a new export
+#[tauri::command]
+async fn replace_vimeo_video(
+    app: tauri::AppHandle,
+    video_uri: String,
+    video_path: String,
+    access_token: String,
+    chunk_size_mb: Option<u64>,
+    max_retries: Option<u32>,
+) -> Result<VimeoUploadResult, WavecastError> {
+    let video_uri = vimeo_video_uri(&video_uri).map_err(|reason| WavecastError::invalid("video_uri", reason))?;
+    require_file(&video_path)?;
+    let tus = TusSettings::new(chunk_size_mb, max_retries)?;
+    let result = replace_vimeo_upload(&app, &video_uri, &video_path, &access_token, tus).await;
+    emit_upload_result(&app, &result);
+    result
+}
+
+// Let the frontend know an upload finished either way
+fn emit_upload_result(app: &tauri::AppHandle, result: &Result<VimeoUploadResult, WavecastError>) {
+    match result {
+        Ok(upload) => {
+            let _ = app.emit("upload-complete", upload.clone());
+        }
+        Err(e) => {
+            let _ = app.emit("upload-error", e.clone());
+        }
+    }
+}
+
+impl VimeoUploadResult {
+    // Links derived from the video URI alone, used until (or if) Vimeo's own
+    // metadata for the clip can be fetched
+    fn from_uri(video_uri: &str) -> Self {
+        let video_id = video_uri.rsplit('/').next().unwrap_or_default();
+        VimeoUploadResult {
+            link: format!("https://vimeo.com/{}", video_id),
+            uri: video_uri.to_string(),
+            manage_url: format!("https://vimeo.com/manage/videos/{}", video_id),
+            embed_html: format!(
+                "<iframe src=\"https://player.vimeo.com/video/{}\" width=\"640\" height=\"360\" frameborder=\"0\" allow=\"autoplay; fullscreen; picture-in-picture\" allowfullscreen></iframe>",
+                video_id
+            ),
+        }
+    }
+}
+
+// Look up the uploaded clip's links and embed code, keeping the derived
+// fallbacks for anything Vimeo doesn't have ready yet
+async fn fetch_vimeo_upload_result(
+    client: &reqwest::Client,
+    access_token: &str,
+    video_uri: &str,
+) -> VimeoUploadResult {
+    let mut result = VimeoUploadResult::from_uri(video_uri);
+
+    let response = send_with_retry("fetch video details", || {
+        client
+            .get(format!("https://api.vimeo.com{}", video_uri))
+            .header("Authorization", format!("bearer {}", access_token))
+            .query(&[("fields", "uri,link,manage_link,embed.html")])
+    })
+    .await;
+
+    let json: serde_json::Value = match response {
+        Ok(response) if response.status().is_success() => match response.json().await {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("Warning: failed to parse video details for {}: {}", video_uri, e);
+                return result;
+            }
+        },
+        Ok(response) => {
+            eprintln!("Warning: failed to fetch video details for {}: HTTP {}", video_uri, response.status());
+            return result;
+        }
+        Err(e) => {
+            eprintln!("Warning: failed to fetch video details for {}: {}", video_uri, e);
+            return result;
+        }
+    };
+
+    if let Some(link) = json["link"].as_str() {
+        result.link = link.to_string();
+    }
+    if let Some(manage_link) = json["manage_link"].as_str() {
+        result.manage_url = format!("https://vimeo.com{}", manage_link);
+    }
+    // The player embed can lag behind the upload; keep the fallback iframe until it exists
+    if let Some(embed_html) = json["embed"]["html"].as_str().filter(|html| !html.is_empty()) {
+        result.embed_html = embed_html.to_string();
+    }
+
+    result
+}
+
+async fn set_vimeo_tags(
+    client: &reqwest::Client,
+    access_token: &str,
+    video_uri: &str,
+    tags: &[String],
+) -> Result<(), WavecastError> {
+    let body: Vec<serde_json::Value> = tags
+        .iter()
+        .map(|tag| serde_json::json!({ "name": tag }))
+        .collect();
+
+    let response = send_with_retry("set tags", || {
+        client
+            .put(format!("https://api.vimeo.com{}/tags", video_uri))
+            .header("Authorization", format!("bearer {}", access_token))
+            .json(&body)
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(WavecastError::vimeo(response).await);
+    }
+
+    Ok(())
+}
+
+// The numeric id at the end of a Vimeo folder or showcase URI ("/users/1/projects/2" -> "2")
+fn vimeo_resource_id<'a>(uri: &'a str, what: &str) -> Result<&'a str, String> {
+    let id = uri.trim().trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Not a Vimeo {}: {}", what, uri));
+    }
+    Ok(id)
+}
+
+// Bodyless PUT to an API path, as used to add a video to a folder or showcase
+async fn vimeo_put(client: &reqwest::Client, access_token: &str, what: &str, path: &str) -> Result<(), WavecastError> {
+    let response = send_with_retry(what, || {
+        client
+            .put(format!("https://api.vimeo.com{}", path))
+            .header("Authorization", format!("bearer {}", access_token))
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        return Err(WavecastError::vimeo(response).await);
+    }
+    Ok(())
+}
+
+fn emit_upload_warning(app: &tauri::AppHandle, warning: &str) {
+    eprintln!("Warning: {}", warning);
+    let _ = app.emit("upload-warning", warning.to_string());
+}
+
+// A folder (Vimeo's API calls them projects) for the frontend's folder picker
+#[derive(Clone, Serialize, Debug)]
+struct VimeoFolder {
+    id: String,
+    uri: String,
+    name: String,
+}
+
+// Every folder of the token's account, following Vimeo's pagination
+#[tauri::command]
+async fn list_vimeo_folders(access_token: String) -> Result<Vec<VimeoFolder>, WavecastError> {
+    let client = reqwest::Client::new();
+    let mut folders = Vec::new();
+    let mut next_page = Some("/me/projects?fields=uri,name&per_page=100".to_string());
+
+    while let Some(page) = next_page {
+        let response = send_with_retry("list folders", || {
+            client
+                .get(format!("https://api.vimeo.com{}", page))
+                .header("Authorization", format!("bearer {}", access_token))
+        })
+        .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(WavecastError::vimeo(response).await);
+        }
+
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse response: {}", e))?;
+        for folder in json["data"].as_array().into_iter().flatten() {
+            let uri = folder["uri"].as_str().unwrap_or_default();
+            folders.push(VimeoFolder {
+                id: uri.rsplit('/').next().unwrap_or_default().to_string(),
+                uri: uri.to_string(),
+                name: folder["name"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+        next_page = json["paging"]["next"].as_str().map(str::to_string);
+    }
+
+    Ok(folders)
+}
+
+// MIME type Vimeo expects for a thumbnail, from the image's extension
+fn thumbnail_content_type(image_path: &str) -> Result<&'static str, String> {
+    let extension = std::path::Path::new(image_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => Ok("image/jpeg"),
+        "png" => Ok("image/png"),
+        _ => Err(format!("Unsupported thumbnail image '{}' (expected a JPEG or PNG)", image_path)),
+    }
+}
+
+// "/videos/123", "123" or "https://vimeo.com/123" -> "/videos/123"
+fn vimeo_video_uri(video: &str) -> Result<String, String> {
+    let video_id = video.trim().trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+    if video_id.is_empty() || !video_id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Not a Vimeo video: {}", video));
+    }
+    Ok(format!("/videos/{}", video_id))
+}
+
+// Attempts at creating the thumbnail slot while a fresh upload is still transcoding
+const THUMBNAIL_ATTEMPTS: u32 = 5;
+
+// Upload `image_path` as a new picture for the video and make it the active thumbnail:
+// POST /videos/{id}/pictures, PUT the image to the returned link, then PATCH it active
+async fn upload_vimeo_thumbnail(
+    client: &reqwest::Client,
+    access_token: &str,
+    video_uri: &str,
+    image_path: &str,
+) -> Result<String, WavecastError> {
+    let content_type = thumbnail_content_type(image_path)?;
+    let image = std::fs::read(image_path)
+        .map_err(|e| format!("Failed to read thumbnail image: {}", e))?;
+
+    // Vimeo refuses new pictures until the video has been processed, so back off and retry
+    let mut attempt = 1;
+    let picture: serde_json::Value = loop {
+        let response = send_with_retry("create thumbnail", || {
+            client
+                .post(format!("https://api.vimeo.com{}/pictures", video_uri))
+                .header("Authorization", format!("bearer {}", access_token))
+                .header("Accept", "application/vnd.vimeo.*+json;version=3.4")
+        })
+        .await?;
+
+        if response.status().is_success() {
+            break response
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse response: {}", e))?;
+        }
+        if attempt >= THUMBNAIL_ATTEMPTS {
+            return Err(WavecastError::vimeo(response).await);
+        }
+        let error_text = response.text().await.unwrap_or_default();
+        let delay = std::time::Duration::from_secs(5 << attempt);
+        eprintln!(
+            "Vimeo is not ready for a thumbnail yet ({}), retrying in {:?} ({}/{})...",
+            error_text, delay, attempt, THUMBNAIL_ATTEMPTS
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    };
+
+    let picture_uri = picture["uri"].as_str().ok_or("No picture URI in response")?;
+    let upload_link = picture["link"].as_str().ok_or("No picture upload link in response")?;
+
+    let response = send_with_retry("upload thumbnail", || {
+        client
+            .put(upload_link)
+            .header("Content-Type", content_type)
+            .body(image.clone())
+    })
+    .await?;
+    if !response.status().is_success() {
+        return Err(WavecastError::vimeo(response).await);
+    }
+
+    let response = send_with_retry("activate thumbnail", || {
+        client
+            .patch(format!("https://api.vimeo.com{}", picture_uri))
+            .header("Authorization", format!("bearer {}", access_token))
+            .json(&serde_json::json!({ "active": true }))
+    })
+    .await?;
+    if !response.status().is_success() {
+        return Err(WavecastError::vimeo(response).await);
+    }
+
+    eprintln!("Thumbnail {} set on {}", picture_uri, video_uri);
+    Ok(picture_uri.to_string())
+}
+
+// Seconds between transcode status checks, and how long to wait in total by default
+const TRANSCODE_POLL_SECS: u64 = 10;
+const DEFAULT_TRANSCODE_TIMEOUT_MINUTES: u64 = 30;
+
+// Transcode waits in progress, keyed by video URI, each with its cancel flag
+#[derive(Default)]
+struct TranscodeWatchState {
+    watching: Mutex<std::collections::HashMap<String, std::sync::Arc<AtomicBool>>>,
+}
+
+// Payload of the vimeo-transcode-status event and the result of wait_for_vimeo_transcode.
+// `status` is Vimeo's (in_progress, complete, error) or "cancelled".
+#[derive(Clone, Serialize, Debug)]
+struct TranscodeStatus {
+    video_uri: String,
+    status: String,
+    link: Option<String>,
+    elapsed_seconds: u64,
+}
+
+// Poll GET /videos/{id} until Vimeo has finished transcoding the video, emitting
+// vimeo-transcode-status after every check
+#[tauri::command]
+async fn wait_for_vimeo_transcode(
+    app: tauri::AppHandle,
+    video_uri: String,
+    access_token: String,
+    timeout_minutes: Option<u64>,
+) -> Result<TranscodeStatus, WavecastError> {
+    let video_uri = vimeo_video_uri(&video_uri).map_err(|reason| WavecastError::invalid("video_uri", reason))?;
+    let timeout_minutes = timeout_minutes.unwrap_or(DEFAULT_TRANSCODE_TIMEOUT_MINUTES);
+    if !(1..=240).contains(&timeout_minutes) {
+        return Err(WavecastError::invalid("timeout_minutes", format!(
+            "Invalid transcode timeout {} minutes: must be between 1 and 240",
+            timeout_minutes
+        )));
+    }
+
+    // A second wait on the same video takes over from the first
+    let cancelled = std::sync::Arc::new(AtomicBool::new(false));
+    {
+        let state = app.state::<TranscodeWatchState>();
+        let mut watching = state.watching.lock().unwrap();
+        if let Some(previous) = watching.insert(video_uri.clone(), cancelled.clone()) {
+            previous.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let result = poll_vimeo_transcode(&app, &video_uri, &access_token, timeout_minutes * 60, &cancelled).await;
+
+    let state = app.state::<TranscodeWatchState>();
+    let mut watching = state.watching.lock().unwrap();
+    if watching.get(&video_uri).is_some_and(|flag| std::sync::Arc::ptr_eq(flag, &cancelled)) {
+        watching.remove(&video_uri);
+    }
+    result
+}
+
+async fn poll_vimeo_transcode(
+    app: &tauri::AppHandle,
+    video_uri: &str,
+    access_token: &str,
+    timeout_secs: u64,
+    cancelled: &AtomicBool,
+) -> Result<TranscodeStatus, WavecastError> {
+    let client = reqwest::Client::new();
+    let started = std::time::Instant::now();
+
+    loop {
+        let response = send_with_retry("check transcode status", || {
+            client
+                .get(format!("https://api.vimeo.com{}", video_uri))
+                .header("Authorization", format!("bearer {}", access_token))
+                .query(&[("fields", "transcode.status,link")])
+        })
+        .await?;
+
+        if !response.status().is_success() {
+            return Err(WavecastError::vimeo(response).await);
+        }
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse response: {}", e))?;
+
+        let status = TranscodeStatus {
+            video_uri: video_uri.to_string(),
+            status: json["transcode"]["status"].as_str().unwrap_or("in_progress").to_string(),
+            link: json["link"].as_str().map(str::to_string),
+            elapsed_seconds: started.elapsed().as_secs(),
+        };
+        eprintln!("Transcode status of {}: {} after {}s", video_uri, status.status, status.elapsed_seconds);
+        let _ = app.emit("vimeo-transcode-status", status.clone());
+
+        match status.status.as_str() {
+            "complete" => return Ok(status),
+            "error" => return Err(format!("Vimeo failed to transcode {}", video_uri).into()),
+            _ => {}
+        }
+
+        if started.elapsed().as_secs() + TRANSCODE_POLL_SECS > timeout_secs {
+            return Err(format!(
+                "Vimeo is still processing {} after {} minutes; it should appear on Vimeo once done",
+                video_uri,
+                timeout_secs / 60
+            ).into());
+        }
+
+        // Sleep in short steps so a cancel takes effect quickly
+        for _ in 0..TRANSCODE_POLL_SECS {
+            if cancelled.load(Ordering::SeqCst) {
+                eprintln!("Stopped waiting for {} to transcode", video_uri);
+                let status = TranscodeStatus {
+                    status: "cancelled".to_string(),
+                    elapsed_seconds: started.elapsed().as_secs(),
+                    ..status
+                };
+                let _ = app.emit("vimeo-transcode-status", status.clone());
+                return Ok(status);
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+    }
+}
+
+// Stop waiting on a video's transcode, or on every video when no URI is given
+#[tauri::command]
+fn cancel_vimeo_transcode_wait(
+    state: tauri::State<'_, TranscodeWatchState>,
+    video_uri: Option<String>,
+) -> Result<(), WavecastError> {
+    let watching = state.watching.lock().unwrap();
+    match video_uri {
+        Some(video_uri) => {
+            let video_uri = vimeo_video_uri(&video_uri).map_err(|reason| WavecastError::invalid("video_uri", reason))?;
+            if let Some(flag) = watching.get(&video_uri) {
+                flag.store(true, Ordering::SeqCst);
+            }
+        }
+        None => {
+            for flag in watching.values() {
+                flag.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Set a custom thumbnail on an already uploaded video; returns the picture's URI
+#[tauri::command]
+async fn set_vimeo_thumbnail(
+    video_uri: String,
+    image_path: String,
+    access_token: String,
+) -> Result<String, WavecastError> {
+    let video_uri = vimeo_video_uri(&video_uri).map_err(|reason| WavecastError::invalid("video_uri", reason))?;
+    require_file(&image_path)?;
+    let client = reqwest::Client::new();
+    upload_vimeo_thumbnail(&client, &access_token, &video_uri, &image_path).await
+}
+
+#[tauri::command]
+async fn verify_vimeo_token(access_token: String) -> Result<String, WavecastError> {
+    let client = reqwest::Client::new();
+    vimeo_user_name(&client, &access_token).await
+}
+
+// Check the token against GET /me and return the account's display name
+async fn vimeo_user_name(client: &reqwest::Client, access_token: &str) -> Result<String, WavecastError> {
+    if access_token.trim().is_empty() {
+        return Err(WavecastError::invalid("access_token", "No Vimeo access token set"));
+    }
+
+    let response = send_with_retry("verify access token", || {
+        client
+            .get("https://api.vimeo.com/me")
+            .header("Authorization", format!("bearer {}", access_token))
+            .query(&[("fields", "name")])
+    })
+    .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(WavecastError::vimeo(response).await);
+    }
+
+    let json: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    Ok(json["name"].as_str().unwrap_or_default().to_string())
+}
+
+// The account behind a token and how much it may still upload. Quota fields are
+// bytes, and None where Vimeo reports no limit.
+#[derive(Clone, Serialize, Debug)]
+struct VimeoAccountInfo {
+    name: String,
+    // Membership tier, e.g. basic, plus, pro, business
+    account: String,
+    // Upload allowance for the current period (usually a week)
+    periodic_free: Option<u64>,
+    periodic_max: Option<u64>,
+    periodic_reset_date: Option<String>,
+    // Total storage
+    space_free: Option<u64>,
+    space_max: Option<u64>,
+}
+
+impl VimeoAccountInfo {
+    // Free bytes under the tightest of the limits
+    fn upload_limit(&self) -> Option<u64> {
+        match (self.periodic_free, self.space_free) {
+            (Some(periodic), Some(space)) => Some(periodic.min(space)),
+            (periodic, space) => periodic.or(space),
+        }
+    }
+}
+
+#[tauri::command]
+async fn check_vimeo_account(
+    access_token: String,
+    video_path: Option<String>,
+) -> Result<VimeoAccountInfo, WavecastError> {
+    if access_token.trim().is_empty() {
+        return Err(WavecastError::invalid("access_token", "No Vimeo access token set"));
+    }
+
+    let client = reqwest::Client::new();
+    let response = send_with_retry("check account", || {
+        client
+            .get("https://api.vimeo.com/me")
+            .header("Authorization", format!("bearer {}", access_token))
+            .query(&[("fields", "name,account,upload_quota")])
+    })
+    .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(WavecastError::vimeo(response).await);
+    }
+
+    let json: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let quota = &json["upload_quota"];
+    let info = VimeoAccountInfo {
+        name: json["name"].as_str().unwrap_or_default().to_string(),
+        account: json["account"].as_str().unwrap_or_default().to_string(),
+        periodic_free: quota["periodic"]["free"].as_u64(),
+        periodic_max: quota["periodic"]["max"].as_u64(),
+        periodic_reset_date: quota["periodic"]["reset_date"].as_str().map(str::to_string),
+        space_free: quota["space"]["free"].as_u64(),
+        space_max: quota["space"]["max"].as_u64(),
+    };
+    eprintln!("Vimeo account: {:?}", info);
+
+    // Optionally make sure a file will fit before it is uploaded
+    if let Some(video_path) = video_path {
+        let video_size = std::fs::metadata(&video_path)
+            .map_err(|e| format!("Failed to read video file: {}", e))?
+            .len();
+        if let Some(limit) = info.upload_limit().filter(|limit| video_size > *limit) {
+            return Err(format!(
+                "Your remaining Vimeo upload quota ({}) is smaller than this file ({})",
+                format_bytes(limit),
+                format_bytes(video_size)
+            ).into());
+        }
+    }
+
+    Ok(info)
+}
+
+// 3400000000 -> "3.4 GB"
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1000.0 {
+            break;
+        }
+        value /= 1000.0;
+        unit = next;
+    }
+    format!("{:.1} {}", value, unit)
+}
+
+// Attempts made for an API request before giving up
+const API_ATTEMPTS: u32 = 3;
+
+// Send an API request built by `request`, retrying rate limits (429), server
+// errors (5xx) and dropped connections with exponential backoff. Anything else,
+// including auth errors, is returned straight away for the caller to report.
+async fn send_with_retry<F>(what: &str, request: F) -> Result<reqwest::Response, String>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    send_with_retry_events(None, what, request).await
+}
+
+// send_with_retry that also emits upload-retrying before each retry. A 429's
+// Retry-After is honoured in place of the backoff.
+async fn send_with_retry_events<F>(
+    app: Option<&tauri::AppHandle>,
+    what: &str,
+    request: F,
+) -> Result<reqwest::Response, String>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let mut attempt = 1;
+    loop {
+        let mut delay = std::time::Duration::from_secs(1 << attempt);
+        let error = match request().send().await {
+            Ok(response) => {
+                let status = response.status();
+                if !(status.is_server_error() || status.as_u16() == 429) || attempt >= API_ATTEMPTS {
+                    // Past the last attempt the caller surfaces the service's error text
+                    return Ok(response);
+                }
+                if status.as_u16() == 429 {
+                    delay = retry_after(&response).unwrap_or(delay);
+                }
+                format!("HTTP {}", status)
+            }
+            Err(e) if attempt >= API_ATTEMPTS => {
+                return Err(format!("Failed to {}: {} (gave up after {} attempts)", what, e, attempt));
+            }
+            Err(e) => e.to_string(),
+        };
+
+        eprintln!("Failed to {} ({}), retrying in {:?} ({}/{})...", what, error, delay, attempt, API_ATTEMPTS);
+        if let Some(app) = app {
+            emit_upload_retrying(app, &format!("Failed to {}: {}", what, error), attempt, API_ATTEMPTS - 1, delay);
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+// Where the TUS session for a video is remembered until its upload completes
+fn upload_session_path(video_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.vimeo-upload.json", video_path))
+}
+
+async fn vimeo_upload(
+    app: &tauri::AppHandle,
+    video_path: &str,
+    access_token: &str,
+    title: &str,
+    details: &VimeoVideoDetails,
+    tus: TusSettings,
+) -> Result<VimeoUploadResult, WavecastError> {
+    // Only the size is needed up front; the file itself is streamed from disk
+    let video_size = std::fs::metadata(video_path)
+        .map_err(|e| format!("Failed to read video file: {}", e))?
+        .len();
+
+    // Create HTTP client
     let client = reqwest::Client::new();
 
+    // Catch a bad token before anything is created on Vimeo
+    let user_name = vimeo_user_name(&client, access_token).await?;
+    eprintln!("Uploading to Vimeo as {}", user_name);
+
     // Step 1: Create upload request
-    let create_response = client
-        .post("https://api.vimeo.com/me/videos")
-        .header("Authorization", format!("bearer {}", access_token))
-        .header("Content-Type", "application/json")
-        .json(&serde_json::json!({
-            "upload": {
-                "approach": "post",
-                "size": video_data.len().to_string()
+    let create_body = details.create_body(title, video_size);
+    let create_response = send_with_retry_events(Some(app), "create upload", || {
+        client
+            .post("https://api.vimeo.com/me/videos")
+            .header("Authorization", format!("bearer {}", access_token))
+            .header("Content-Type", "application/json")
+            .json(&create_body)
+    })
+    .await?;
+
+    if !create_response.status().is_success() {
+        return Err(WavecastError::vimeo(create_response).await);
+    }
+
+    let create_json: serde_json::Value = create_response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse response: {}", e))?;
+
+    let upload_link = create_json["upload"]["upload_link"]
+        .as_str()
+        .ok_or("No upload link in response")?;
+
+    let video_uri = create_json["uri"]
+        .as_str()
+        .ok_or("No video URI in response")?;
+
+    // Remember the session so resume_upload can pick it up after a crash or restart
+    let session_path = save_upload_session(video_path, upload_link, video_uri, video_size);
+
+    // Step 2: Upload the video file
+    tus_upload(app, &client, video_path, upload_link, 0, video_size, tus).await?;
+
+    let _ = std::fs::remove_file(&session_path);
+
+    // Step 3: Tag the video. The upload itself succeeded, so a failure here is only logged
+    if !details.tags.is_empty() {
+        if let Err(e) = set_vimeo_tags(&client, access_token, video_uri, &details.tags).await {
+            eprintln!("Warning: failed to set tags on {}: {}", video_uri, e);
+        }
+    }
+
+    // Step 4: Replace Vimeo's automatic thumbnail. Also only logged on failure
+    if let Some(ref thumbnail) = details.thumbnail_path {
+        if let Err(e) = upload_vimeo_thumbnail(&client, access_token, video_uri, thumbnail).await {
+            eprintln!("Warning: failed to set thumbnail on {}: {}", video_uri, e);
+        }
+    }
+
+    // Step 5: File the video in a folder and/or showcase. The UI is warned on failure
+    let video_id = video_uri.rsplit('/').next().unwrap_or_default();
+    if let Some(ref folder) = details.folder_uri {
+        let path = format!("/me/projects/{}/videos/{}", vimeo_resource_id(folder, "folder")?, video_id);
+        if let Err(e) = vimeo_put(&client, access_token, "add video to folder", &path).await {
+            emit_upload_warning(app, &format!("The video was uploaded but could not be added to the folder: {}", e));
+        }
+    }
+    if let Some(ref showcase) = details.showcase_id {
+        let path = format!("/albums/{}/videos/{}", vimeo_resource_id(showcase, "showcase")?, video_id);
+        if let Err(e) = vimeo_put(&client, access_token, "add video to showcase", &path).await {
+            emit_upload_warning(app, &format!("The video was uploaded but could not be added to the showcase: {}", e));
+        }
+    }
+
+    Ok(fetch_vimeo_upload_result(&client, access_token, video_uri).await)
+}
+
+// Save the TUS session next to the video and return where it was written
+fn save_upload_session(video_path: &str, upload_link: &str, video_uri: &str, video_size: u64) -> PathBuf {
+    let session = UploadSession {
+        upload_link: upload_link.to_string(),
+        video_uri: video_uri.to_string(),
+        size: video_size,
+    };
+    let session_path = upload_session_path(video_path);
+    if let Err(e) = serde_json::to_string(&session)
+        .map_err(|e| e.to_string())
+        .and_then(|json| std::fs::write(&session_path, json).map_err(|e| e.to_string()))
+    {
+        eprintln!("Warning: could not save upload session, resume will not be possible: {}", e);
+    }
+    session_path
+}
+
+// Upload a new file as a version of an existing video, keeping its link and embeds
+async fn replace_vimeo_upload(
+    app: &tauri::AppHandle,
+    video_uri: &str,
+    video_path: &str,
+    access_token: &str,
+    tus: TusSettings,
+) -> Result<VimeoUploadResult, WavecastError> {
+    let video_size = std::fs::metadata(video_path)
+        .map_err(|e| format!("Failed to read video file: {}", e))?
+        .len();
+    let file_name = std::path::Path::new(video_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "video.mp4".to_string());
+
+    let client = reqwest::Client::new();
+    let user_name = vimeo_user_name(&client, access_token).await?;
+    eprintln!("Replacing {} as {}", video_uri, user_name);
+
+    // Step 1: Create a new version of the video
+    let version_body = serde_json::json!({
+        "file_name": file_name,
+        "upload": {
+            "approach": "tus",
+            "size": video_size.to_string()
+        }
+    });
+    let version_response = send_with_retry_events(Some(app), "create video version", || {
+        client
+            .post(format!("https://api.vimeo.com{}/versions", video_uri))
+            .header("Authorization", format!("bearer {}", access_token))
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/vnd.vimeo.*+json;version=3.4")
+            .json(&version_body)
+    })
+    .await?;
+
+    let status = version_response.status();
+    if !status.is_success() {
+        // Includes the "your plan doesn't support replacing videos" case
+        return Err(WavecastError::vimeo(version_response).await);
+    }
+
+    let version_json: serde_json::Value = version_response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let upload_link = version_json["upload"]["upload_link"]
+        .as_str()
+        .ok_or("No upload link in response")?;
+
+    let session_path = save_upload_session(video_path, upload_link, video_uri, video_size);
+
+    // Step 2: Upload the new file
+    tus_upload(app, &client, video_path, upload_link, 0, video_size, tus).await?;
+
+    let _ = std::fs::remove_file(&session_path);
+
+    Ok(fetch_vimeo_upload_result(&client, access_token, video_uri).await)
+}
+
+async fn resume_vimeo_upload(
+    app: &tauri::AppHandle,
+    video_path: &str,
+    access_token: Option<&str>,
+    tus: TusSettings,
+) -> Result<VimeoUploadResult, WavecastError> {
+    let session_path = upload_session_path(video_path);
+    let session: UploadSession = std::fs::read_to_string(&session_path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .ok_or_else(|| format!("No interrupted upload found for {}", video_path))?;
+
+    let video_size = std::fs::metadata(video_path)
+        .map_err(|e| format!("Failed to read video file: {}", e))?
+        .len();
+    if video_size != session.size {
+        return Err("The video file has changed since the upload started; please upload it again".into());
+    }
+
+    let client = reqwest::Client::new();
+    let offset = tus_offset(&client, &session.upload_link)
+        .await
+        .map_err(TusFailure::into_message)?;
+    eprintln!("Resuming upload of {} at byte {} of {}", video_path, offset, video_size);
+
+    tus_upload(app, &client, video_path, &session.upload_link, offset, video_size, tus).await?;
+
+    let _ = std::fs::remove_file(&session_path);
+    // Without a token we can only derive the links from the URI
+    Ok(match access_token {
+        Some(token) => fetch_vimeo_upload_result(&client, token, &session.video_uri).await,
+        None => VimeoUploadResult::from_uri(&session.video_uri),
+    })
+}
+
+// Why a TUS request failed: transient failures are retried from the server's offset
+enum TusFailure {
+    Transient(String),
+    // A 429, to be retried once the server's Retry-After has passed
+    RateLimited(String, std::time::Duration),
+    Fatal(String),
+}
+
+impl TusFailure {
+    fn into_message(self) -> String {
+        match self {
+            TusFailure::Transient(msg) | TusFailure::RateLimited(msg, _) | TusFailure::Fatal(msg) => msg,
+        }
+    }
+
+    // How long to wait before retry number `retries`
+    fn retry_delay(&self, retries: u32) -> std::time::Duration {
+        match self {
+            TusFailure::RateLimited(_, delay) => *delay,
+            _ => std::time::Duration::from_secs(1 << retries.min(6)),
+        }
+    }
+
+    // 429 with Retry-After, other 429/5xx (plus any `also_transient`) as retryable, the rest fatal
+    fn from_status(
+        status: reqwest::StatusCode,
+        retry_after: Option<std::time::Duration>,
+        msg: String,
+        also_transient: bool,
+    ) -> Self {
+        match retry_after {
+            Some(delay) if status.as_u16() == 429 => TusFailure::RateLimited(msg, delay),
+            _ if status.is_server_error() || status.as_u16() == 429 || also_transient => TusFailure::Transient(msg),
+            _ => TusFailure::Fatal(msg),
+        }
+    }
+}
+
+// Send the file to a TUS upload link in PATCH chunks, starting at `offset`.
+// After a dropped connection we ask Vimeo how much it has and carry on from there.
+async fn tus_upload(
+    app: &tauri::AppHandle,
+    client: &reqwest::Client,
+    video_path: &str,
+    upload_link: &str,
+    offset: u64,
+    video_size: u64,
+    tus: TusSettings,
+) -> Result<(), String> {
+    let mut video_file = tokio::fs::File::open(video_path)
+        .await
+        .map_err(|e| format!("Failed to read video file: {}", e))?;
+
+    let mut offset = offset;
+    let mut retries = 0;
+    while offset < video_size {
+        match tus_patch_chunk(app, client, &mut video_file, upload_link, offset, video_size, tus.chunk_size).await {
+            Ok(new_offset) => {
+                offset = new_offset;
+                retries = 0;
+            }
+            Err(TusFailure::Fatal(e)) => return Err(e),
+            Err(failure) => {
+                retries += 1;
+                let delay = failure.retry_delay(retries);
+                let e = failure.into_message();
+                if retries > tus.max_retries {
+                    return Err(format!("{} (gave up after {} retries)", e, tus.max_retries));
+                }
+                eprintln!("Upload interrupted: {}. Retrying in {:?} ({}/{})...", e, delay, retries, tus.max_retries);
+                emit_upload_retrying(app, &e, retries, tus.max_retries, delay);
+                tokio::time::sleep(delay).await;
+
+                match tus_offset(client, upload_link).await {
+                    Ok(server_offset) => offset = server_offset,
+                    Err(TusFailure::Fatal(e)) => return Err(e),
+                    Err(failure) => {
+                        eprintln!("Could not query upload offset: {}", failure.into_message());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// PATCH one chunk starting at `offset` and return the offset the server now reports
+async fn tus_patch_chunk(
+    app: &tauri::AppHandle,
+    client: &reqwest::Client,
+    video_file: &mut tokio::fs::File,
+    upload_link: &str,
+    offset: u64,
+    video_size: u64,
+    chunk_size: u64,
+) -> Result<u64, TusFailure> {
+    let chunk_len = chunk_size.min(video_size - offset);
+
+    let pieces = read_chunk_pieces(video_file, offset, chunk_len).await?;
+
+    let upload_response = client
+        .patch(upload_link)
+        .header("Tus-Resumable", "1.0.0")
+        .header("Upload-Offset", offset.to_string())
+        .header("Content-Type", "application/offset+octet-stream")
+        .header("Content-Length", chunk_len.to_string())
+        .body(progress_body(app.clone(), pieces, offset, video_size))
+        .send()
+        .await
+        .map_err(|e| TusFailure::Transient(format!("Failed to upload video: {}", e)))?;
+
+    let status = upload_response.status();
+    if !status.is_success() {
+        let delay = retry_after(&upload_response);
+        let error_text = upload_response.text().await.unwrap_or_default();
+        let msg = format!("Upload failed: {}", error_text);
+        // 409 means our offset is out of sync with the server, which a resync fixes
+        return Err(TusFailure::from_status(status, delay, msg, status.as_u16() == 409));
+    }
+
+    // The server is the source of truth for how much it has received
+    Ok(upload_response
+        .headers()
+        .get("Upload-Offset")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(offset + chunk_len))
+}
+
+// Ask the TUS endpoint how many bytes it has received
+async fn tus_offset(client: &reqwest::Client, upload_link: &str) -> Result<u64, TusFailure> {
+    let response = client
+        .head(upload_link)
+        .header("Tus-Resumable", "1.0.0")
+        .send()
+        .await
+        .map_err(|e| TusFailure::Transient(format!("Failed to check upload status: {}", e)))?;
+
+    let status = response.status();
+    if status.as_u16() == 404 || status.as_u16() == 410 {
+        return Err(TusFailure::Fatal(
+            "The Vimeo upload session has expired; please start a new upload".to_string(),
+        ));
+    }
+    if !status.is_success() {
+        let msg = format!("Failed to check upload status: HTTP {}", status);
+        return Err(if status.is_server_error() {
+            TusFailure::Transient(msg)
+        } else {
+            TusFailure::Fatal(msg)
+        });
+    }
+
+    response
+        .headers()
+        .get("Upload-Offset")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .ok_or_else(|| TusFailure::Fatal("Vimeo did not report an upload offset".to_string()))
+}
+
+// Read `len` bytes at `offset` in small pieces so progress can be reported as they are sent
+async fn read_chunk_pieces(
+    video_file: &mut tokio::fs::File,
+    offset: u64,
+    len: u64,
+) -> Result<Vec<Vec<u8>>, TusFailure> {
+    video_file.seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(|e| TusFailure::Fatal(format!("Failed to read video file: {}", e)))?;
+
+    let mut pieces: Vec<Vec<u8>> = Vec::new();
+    let mut remaining = len;
+    while remaining > 0 {
+        let mut piece = vec![0u8; UPLOAD_PROGRESS_STEP.min(remaining) as usize];
+        video_file.read_exact(&mut piece)
+            .await
+            .map_err(|e| TusFailure::Fatal(format!("Failed to read video file: {}", e)))?;
+        remaining -= piece.len() as u64;
+        pieces.push(piece);
+    }
+    Ok(pieces)
+}
+
+// Wrap a chunk's pieces in a request body that emits upload-progress as each piece is sent
+fn progress_body(app: tauri::AppHandle, pieces: Vec<Vec<u8>>, offset: u64, total_bytes: u64) -> reqwest::Body {
+    let mut bytes_sent = offset;
+    let stream = futures_util::stream::iter(pieces.into_iter().map(move |piece| {
+        bytes_sent += piece.len() as u64;
+        let progress = UploadProgress {
+            bytes_sent,
+            total_bytes,
+            progress: if total_bytes > 0 {
+                bytes_sent as f64 / total_bytes as f64 * 100.0
+            } else {
+                100.0
             },
-            "name": title
-        }))
+        };
+        let _ = app.emit("upload-progress", progress);
+        Ok::<Vec<u8>, std::io::Error>(piece)
+    }));
+    reqwest::Body::wrap_stream(stream)
+}
+
+#[tauri::command]
+async fn upload_to_youtube(
+    app: tauri::AppHandle,
+    video_path: String,
+    access_token: String,
+    title: String,
+    description: Option<String>,
+    privacy_status: Option<String>,
+    tags: Option<Vec<String>>,
+    chunk_size_mb: Option<u64>,
+    max_retries: Option<u32>,
+) -> Result<String, WavecastError> {
+    let privacy_status = privacy_status.unwrap_or_else(|| "private".to_string());
+    if !["public", "unlisted", "private"].contains(&privacy_status.as_str()) {
+        return Err(WavecastError::invalid("privacy_status", format!(
+            "Unknown privacy status '{}' (expected public, unlisted or private)",
+            privacy_status
+        )));
+    }
+    require_file(&video_path)?;
+    let tus = TusSettings::new(chunk_size_mb, max_retries)?;
+
+    let metadata = serde_json::json!({
+        "snippet": {
+            "title": title,
+            "description": description.unwrap_or_default(),
+            "tags": tags.unwrap_or_default(),
+        },
+        "status": {
+            "privacyStatus": privacy_status,
+        }
+    });
+
+    let result = youtube_upload(&app, &video_path, &access_token, &metadata, tus)
+        .await
+        .map_err(WavecastError::from);
+    match &result {
+        Ok(link) => {
+            let _ = app.emit("upload-complete", link.clone());
+        }
+        Err(e) => {
+            let _ = app.emit("upload-error", e.clone());
+        }
+    }
+    result
+}
+
+// Where a resumable YouTube upload stands after a request
+enum ResumableStatus {
+    Incomplete(u64),
+    Done(String),
+}
+
+async fn youtube_upload(
+    app: &tauri::AppHandle,
+    video_path: &str,
+    access_token: &str,
+    metadata: &serde_json::Value,
+    tus: TusSettings,
+) -> Result<String, String> {
+    let video_size = std::fs::metadata(video_path)
+        .map_err(|e| format!("Failed to read video file: {}", e))?
+        .len();
+
+    if video_size == 0 {
+        return Err("The video file is empty".to_string());
+    }
+
+    let client = reqwest::Client::new();
+
+    // Step 1: Start a resumable session; YouTube answers with its URI in Location
+    let create_response = send_with_retry_events(Some(app), "create YouTube upload", || {
+        client
+            .post("https://www.googleapis.com/upload/youtube/v3/videos")
+            .query(&[("uploadType", "resumable"), ("part", "snippet,status")])
+            .header("Authorization", format!("Bearer {}", access_token))
+            .header("X-Upload-Content-Length", video_size.to_string())
+            .header("X-Upload-Content-Type", "video/*")
+            .json(metadata)
+    })
+    .await?;
+
+    let status = create_response.status();
+    if status.as_u16() == 401 {
+        return Err("YouTube rejected the access token: it is invalid or expired".to_string());
+    }
+    if !status.is_success() {
+        let error_text = create_response.text().await.unwrap_or_default();
+        return Err(format!("YouTube API error: {}", error_text));
+    }
+
+    let session_uri = create_response
+        .headers()
+        .get("Location")
+        .and_then(|v| v.to_str().ok())
+        .ok_or("No upload session in YouTube response")?
+        .to_string();
+
+    // Step 2: Upload the video file in chunks, resuming from what YouTube has
+    let mut video_file = tokio::fs::File::open(video_path)
+        .await
+        .map_err(|e| format!("Failed to read video file: {}", e))?;
+
+    let mut offset = 0;
+    let mut retries = 0;
+    let video_id = loop {
+        match youtube_put_chunk(app, &client, &mut video_file, &session_uri, offset, video_size, tus.chunk_size).await {
+            Ok(ResumableStatus::Done(video_id)) => break video_id,
+            Ok(ResumableStatus::Incomplete(next_offset)) => {
+                offset = next_offset;
+                retries = 0;
+            }
+            Err(TusFailure::Fatal(e)) => return Err(e),
+            Err(failure) => {
+                retries += 1;
+                let delay = failure.retry_delay(retries);
+                let e = failure.into_message();
+                if retries > tus.max_retries {
+                    return Err(format!("{} (gave up after {} retries)", e, tus.max_retries));
+                }
+                eprintln!("Upload interrupted: {}. Retrying in {:?} ({}/{})...", e, delay, retries, tus.max_retries);
+                emit_upload_retrying(app, &e, retries, tus.max_retries, delay);
+                tokio::time::sleep(delay).await;
+
+                match youtube_upload_status(&client, &session_uri, video_size).await {
+                    Ok(ResumableStatus::Done(video_id)) => break video_id,
+                    Ok(ResumableStatus::Incomplete(server_offset)) => offset = server_offset,
+                    Err(TusFailure::Fatal(e)) => return Err(e),
+                    Err(failure) => {
+                        eprintln!("Could not query upload status: {}", failure.into_message());
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(format!("https://www.youtube.com/watch?v={}", video_id))
+}
+
+// PUT one chunk of a resumable YouTube upload starting at `offset`
+async fn youtube_put_chunk(
+    app: &tauri::AppHandle,
+    client: &reqwest::Client,
+    video_file: &mut tokio::fs::File,
+    session_uri: &str,
+    offset: u64,
+    video_size: u64,
+    chunk_size: u64,
+) -> Result<ResumableStatus, TusFailure> {
+    let chunk_len = chunk_size.min(video_size.saturating_sub(offset));
+    if chunk_len == 0 {
+        // Everything has been sent; only the final response is missing
+        return youtube_upload_status(client, session_uri, video_size).await;
+    }
+    let pieces = read_chunk_pieces(video_file, offset, chunk_len).await?;
+
+    let response = client
+        .put(session_uri)
+        .header("Content-Length", chunk_len.to_string())
+        .header("Content-Range", format!("bytes {}-{}/{}", offset, offset + chunk_len - 1, video_size))
+        .body(progress_body(app.clone(), pieces, offset, video_size))
+        .send()
+        .await
+        .map_err(|e| TusFailure::Transient(format!("Failed to upload video: {}", e)))?;
+
+    youtube_resumable_status(response).await
+}
+
+// Ask YouTube how much of the upload it has received
+async fn youtube_upload_status(
+    client: &reqwest::Client,
+    session_uri: &str,
+    video_size: u64,
+) -> Result<ResumableStatus, TusFailure> {
+    let response = client
+        .put(session_uri)
+        .header("Content-Length", "0")
+        .header("Content-Range", format!("bytes */{}", video_size))
         .send()
         .await
-        .map_err(|e| format!("Failed to create upload: {}", e))?;
+        .map_err(|e| TusFailure::Transient(format!("Failed to check upload status: {}", e)))?;
+
+    youtube_resumable_status(response).await
+}
+
+// 308 means more bytes are wanted (Range says how many arrived), 200/201 carries the new video
+async fn youtube_resumable_status(response: reqwest::Response) -> Result<ResumableStatus, TusFailure> {
+    let status = response.status();
+    if status.as_u16() == 308 {
+        // "bytes=0-N" is the last byte received; no Range means nothing has arrived yet
+        let received = response
+            .headers()
+            .get("Range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit('-').next())
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|last_byte| last_byte + 1)
+            .unwrap_or(0);
+        return Ok(ResumableStatus::Incomplete(received));
+    }
+
+    if status.is_success() {
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| TusFailure::Fatal(format!("Failed to parse response: {}", e)))?;
+        return json["id"]
+            .as_str()
+            .map(|id| ResumableStatus::Done(id.to_string()))
+            .ok_or_else(|| TusFailure::Fatal("No video id in YouTube response".to_string()));
+    }
+
+    if status.as_u16() == 404 || status.as_u16() == 410 {
+        return Err(TusFailure::Fatal(
+            "The YouTube upload session has expired; please start a new upload".to_string(),
+        ));
+    }
+
+    let delay = retry_after(&response);
+    let error_text = response.text().await.unwrap_or_default();
+    let msg = format!("Upload failed: {}", error_text);
+    Err(TusFailure::from_status(status, delay, msg, false))
+}
+
+// Layout of a .wavecast bundle: the project JSON plus copies of every file it uses
+const BUNDLE_PROJECT_ENTRY: &str = "project.json";
+const BUNDLE_ASSETS_DIR: &str = "assets";
+
+// Write `project` and its audio files and background image into a zip bundle, with the
+// paths in the bundled JSON rewritten to point inside it
+fn write_project_bundle(path: &std::path::Path, mut project: ProjectData) -> Result<(), String> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    // Original path -> path inside the bundle. The index prefix keeps same-named files apart.
+    let mut assets: Vec<(String, String)> = Vec::new();
+    let mut bundle_path = |original: &str| -> Result<String, String> {
+        if let Some((_, inside)) = assets.iter().find(|(path, _)| path == original) {
+            return Ok(inside.clone());
+        }
+        if !std::path::Path::new(original).is_file() {
+            return Err(format!("Cannot embed missing file: {}", original));
+        }
+        let file_name = std::path::Path::new(original)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "asset".to_string());
+        let inside = format!("{}/{}-{}", BUNDLE_ASSETS_DIR, assets.len() + 1, file_name);
+        assets.push((original.to_string(), inside.clone()));
+        Ok(inside)
+    };
+
+    if let Some(ref image) = project.background_image {
+        project.background_image = Some(bundle_path(image)?);
+    }
+    if let Some(ref mut music) = project.background_music {
+        for path in music.paths_mut() {
+            *path = bundle_path(path)?;
+        }
+    }
+    for clip in project.tracks.iter_mut().flat_map(|track| track.clips.iter_mut()) {
+        clip.source_file = bundle_path(&clip.source_file)?;
+    }
 
-    if !create_response.status().is_success() {
-        let error_text = create_response.text().await.unwrap_or_default();
-        return Err(format!("Vimeo API error: {}", error_text));
+    let json_string = serde_json::to_string_pretty(&project)
+        .map_err(|e| format!("Failed to serialize project: {}", e))?;
+
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create bundle: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+
+    zip.start_file(BUNDLE_PROJECT_ENTRY, SimpleFileOptions::default())
+        .map_err(|e| format!("Failed to write project to bundle: {}", e))?;
+    zip.write_all(json_string.as_bytes())
+        .map_err(|e| format!("Failed to write project to bundle: {}", e))?;
+
+    // Audio and images are already compressed, so store them as-is
+    for (original, inside) in &assets {
+        eprintln!("Bundling {} as {}", original, inside);
+        let mut source = std::fs::File::open(original)
+            .map_err(|e| format!("Failed to read {}: {}", original, e))?;
+        let size = source.metadata().map(|m| m.len()).unwrap_or(0);
+        let options = SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .large_file(size >= u32::MAX as u64);
+        zip.start_file(inside.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to bundle: {}", original, e))?;
+        std::io::copy(&mut source, &mut zip)
+            .map_err(|e| format!("Failed to add {} to bundle: {}", original, e))?;
     }
 
-    let create_json: serde_json::Value = create_response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    zip.finish()
+        .map_err(|e| format!("Failed to finish bundle: {}", e))?;
+    Ok(())
+}
 
-    let upload_link = create_json["upload"]["upload_link"]
-        .as_str()
-        .ok_or("No upload link in response")?;
+// Unpack a bundle's assets into a folder next to it and return its project with
+// absolute paths. Re-importing the same bundle reuses the folder.
+fn read_project_bundle(path: &std::path::Path) -> Result<ProjectData, String> {
+    use std::io::Read;
 
-    let video_uri = create_json["uri"]
-        .as_str()
-        .ok_or("No video URI in response")?;
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Failed to open project bundle: {}", e))?;
 
-    // Step 2: Upload the video file
-    let upload_response = client
-        .post(upload_link)
-        .header("Tus-Resumable", "1.0.0")
-        .header("Upload-Offset", "0")
-        .header("Content-Type", "application/offset+octet-stream")
-        .body(video_data)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to upload video: {}", e))?;
+    let mut json_string = String::new();
+    archive.by_name(BUNDLE_PROJECT_ENTRY)
+        .map_err(|_| "Project bundle does not contain project.json".to_string())?
+        .read_to_string(&mut json_string)
+        .map_err(|e| format!("Failed to read project from bundle: {}", e))?;
 
-    if !upload_response.status().is_success() {
-        let error_text = upload_response.text().await.unwrap_or_default();
-        return Err(format!("Upload failed: {}", error_text));
+    let stem = path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "project".to_string());
+    let extract_dir = path.with_file_name(format!("{} assets", stem));
+    eprintln!("Unpacking project bundle into {}", extract_dir.display());
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)
+            .map_err(|e| format!("Failed to read project bundle: {}", e))?;
+        // enclosed_name rejects entries that would land outside the folder
+        let relative = match entry.enclosed_name() {
+            Some(relative) if relative.starts_with(BUNDLE_ASSETS_DIR) && entry.is_file() => relative,
+            _ => continue,
+        };
+        let target = extract_dir.join(&relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let mut out = std::fs::File::create(&target)
+            .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+        std::io::copy(&mut entry, &mut out)
+            .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    }
+
+    let json: serde_json::Value = serde_json::from_str(&json_string)
+        .map_err(|e| format!("Failed to parse project file: {}", e))?;
+    let mut project = migrate_project(json)?;
+
+    let absolute = |bundled: &str| -> String {
+        if std::path::Path::new(bundled).is_absolute() {
+            bundled.to_string()
+        } else {
+            extract_dir.join(bundled).to_string_lossy().to_string()
+        }
+    };
+    project.background_image = project.background_image.as_deref().map(absolute);
+    if let Some(ref mut music) = project.background_music {
+        for path in music.paths_mut() {
+            *path = absolute(path);
+        }
+    }
+    for clip in project.tracks.iter_mut().flat_map(|track| track.clips.iter_mut()) {
+        clip.source_file = absolute(&clip.source_file);
     }
+    Ok(project)
+}
 
-    let video_link = format!("https://vimeo.com{}", video_uri.replace("/videos/", "/"));
-    Ok(video_link)
+// Zip local file header signature
+fn is_zip_file(path: &std::path::Path) -> bool {
+    use std::io::Read;
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| magic == *b"PK\x03\x04")
+        .unwrap_or(false)
 }
 
 #[tauri::command]
 async fn export_project(
     app: tauri::AppHandle,
     project_data: ProjectData,
-) -> Result<String, String> {
+    embed_assets: Option<bool>,
+) -> Result<String, WavecastError> {
+    let embed_assets = embed_assets.unwrap_or(false);
+
     // Show save dialog
-    let file_path = app.dialog()
-        .file()
-        .set_title("Export Project")
-        .add_filter("JSON", &["json"])
-        .set_file_name("project.json")
-        .blocking_save_file();
+    let dialog = app.dialog().file().set_title("Export Project");
+    let dialog = if embed_assets {
+        dialog.add_filter("Wavecast Bundle", &["wavecast"]).set_file_name("project.wavecast")
+    } else {
+        dialog.add_filter("JSON", &["json"]).set_file_name("project.json")
+    };
+    let file_path = dialog.blocking_save_file();
 
     if let Some(path) = file_path {
-        let json_string = serde_json::to_string_pretty(&project_data)
-            .map_err(|e| format!("Failed to serialize project: {}", e))?;
-
         let path_str = path.as_path()
             .ok_or("Failed to get path")?;
 
-        std::fs::write(path_str, json_string)
-            .map_err(|e| format!("Failed to write file: {}", e))?;
+        if embed_assets {
+            write_project_bundle(path_str, project_data).map_err(|e| {
+                let _ = std::fs::remove_file(path_str);
+                eprintln!("ERROR: {}", e);
+                e
+            })?;
+        } else {
+            let json_string = serde_json::to_string_pretty(&project_data)
+                .map_err(|e| format!("Failed to serialize project: {}", e))?;
+
+            std::fs::write(path_str, json_string)
+                .map_err(|e| format!("Failed to write file: {}", e))?;
+        }
 
         Ok(path_str.to_string_lossy().to_string())
     } else {
-        Err("Save cancelled".to_string())
+        Err(WavecastError::DialogCancelled)
+    }
+}
+
+// An imported project plus the clip sources that no longer exist on disk
+#[derive(Serialize, Debug)]
+struct ProjectImport {
+    project: ProjectData,
+    missing_files: Vec<String>,
+}
+
+// Point clips and the background music at moved files using `relink` (old path ->
+// new path), then list the sources that still can't be found, each once
+fn relink_project_sources(
+    project: &mut ProjectData,
+    relink: &std::collections::HashMap<String, String>,
+) -> Vec<String> {
+    let mut missing_files: Vec<String> = Vec::new();
+    for clip in project.tracks.iter_mut().flat_map(|track| track.clips.iter_mut()) {
+        if let Some(new_path) = relink.get(&clip.source_file) {
+            eprintln!("Relinking {} -> {}", clip.source_file, new_path);
+            clip.source_file = new_path.clone();
+        }
+        if !std::path::Path::new(&clip.source_file).exists() && !missing_files.contains(&clip.source_file) {
+            missing_files.push(clip.source_file.clone());
+        }
+    }
+    if let Some(ref mut music) = project.background_music {
+        for path in music.paths_mut() {
+            if let Some(new_path) = relink.get(path.as_str()) {
+                eprintln!("Relinking {} -> {}", path, new_path);
+                *path = new_path.clone();
+            }
+            if !std::path::Path::new(path.as_str()).exists() && !missing_files.contains(path) {
+                missing_files.push(path.clone());
+            }
+        }
     }
+    if !missing_files.is_empty() {
+        eprintln!("Project references missing files: {:?}", missing_files);
+    }
+    missing_files
+}
+
+// Apply a relink map to an already imported project and check it again
+#[tauri::command]
+fn relink_project(
+    project_data: ProjectData,
+    relink_missing: std::collections::HashMap<String, String>,
+) -> Result<ProjectImport, WavecastError> {
+    let mut project = project_data;
+    let missing_files = relink_project_sources(&mut project, &relink_missing);
+    Ok(ProjectImport { project, missing_files })
 }
 
 #[tauri::command]
 async fn import_project(
     app: tauri::AppHandle,
-) -> Result<ProjectData, String> {
+    relink_missing: Option<std::collections::HashMap<String, String>>,
+) -> Result<ProjectImport, WavecastError> {
     // Show open dialog
     let file_path = app.dialog()
         .file()
         .set_title("Import Project")
-        .add_filter("JSON", &["json"])
+        .add_filter("Wavecast Project", &["json", "wavecast"])
         .blocking_pick_file();
 
     if let Some(path) = file_path {
         let path_str = path.as_path()
             .ok_or("Failed to get path")?;
 
-        let json_string = std::fs::read_to_string(path_str)
-            .map_err(|e| format!("Failed to read file: {}", e))?;
+        let mut project = if is_zip_file(path_str) {
+            read_project_bundle(path_str)?
+        } else {
+            let json_string = std::fs::read_to_string(path_str)
+                .map_err(|e| format!("Failed to read file: {}", e))?;
 
-        let project_data: ProjectData = serde_json::from_str(&json_string)
-            .map_err(|e| format!("Failed to parse project file: {}", e))?;
+            let json: serde_json::Value = serde_json::from_str(&json_string)
+                .map_err(|e| format!("Failed to parse project file: {}", e))?;
+            migrate_project(json)?
+        };
+        let missing_files = relink_project_sources(&mut project, &relink_missing.unwrap_or_default());
 
-        Ok(project_data)
+        Ok(ProjectImport { project, missing_files })
     } else {
-        Err("Open cancelled".to_string())
+        Err(WavecastError::DialogCancelled)
     }
 }
 
 #[tauri::command]
-fn reveal_in_folder(path: String) -> Result<(), String> {
+fn reveal_in_folder(path: String) -> Result<(), WavecastError> {
     let path = PathBuf::from(&path);
 
     #[cfg(target_os = "macos")]
@@ -831,12 +7209,17 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![convert_to_video, convert_timeline_to_video, upload_to_vimeo, export_project, import_project, create_solid_color_image, reveal_in_folder])
+        .manage(ExportJobs::default())
+        .manage(TranscodeWatchState::default())
+        .invoke_handler(tauri::generate_handler![convert_to_video, convert_timeline_to_video, export_timeline_audio, render_output_filename, validate_timeline, cancel_export, get_export_status, preview_export_command, acknowledge_export, upload_to_vimeo, resume_upload, replace_vimeo_video, verify_vimeo_token, check_vimeo_account, list_vimeo_folders, set_vimeo_thumbnail, wait_for_vimeo_transcode, cancel_vimeo_transcode_wait, upload_to_youtube, export_project, import_project, relink_project, probe_audio_file, get_waveform_peaks, denoise_clip, create_solid_color_image, generate_thumbnail, generate_midpoint_thumbnail, configure_ffmpeg, get_ffmpeg_status, reveal_in_folder])
         .setup(|app| {
             // File menu
             let export_project_item = MenuItemBuilder::with_id("export_project", "Export Project")
                 .accelerator("CmdOrCtrl+E")
                 .build(app)?;
+            let export_bundle_item = MenuItemBuilder::with_id("export_project_bundle", "Export Project with Audio...")
+                .accelerator("CmdOrCtrl+Shift+E")
+                .build(app)?;
             let import_project_item = MenuItemBuilder::with_id("import_project", "Import Project")
                 .accelerator("CmdOrCtrl+I")
                 .build(app)?;
@@ -852,6 +7235,7 @@ pub fn run() {
 
             let file_menu = SubmenuBuilder::new(app, "File")
                 .item(&export_project_item)
+                .item(&export_bundle_item)
                 .item(&import_project_item)
                 .item(&clear_project_item)
                 .separator()
@@ -901,6 +7285,9 @@ pub fn run() {
                     "export_project" => {
                         let _ = app.emit("export-project", ());
                     }
+                    "export_project_bundle" => {
+                        let _ = app.emit("export-project-bundle", ());
+                    }
                     "import_project" => {
                         let _ = app.emit("import-project", ());
                     }
@@ -919,3 +7306,831 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_presets_have_their_resolutions() {
+        assert_eq!(VideoPreset::HD720.dimensions(), Ok((1280, 720)));
+        assert_eq!(VideoPreset::FHD1080.dimensions(), Ok((1920, 1080)));
+        assert_eq!(VideoPreset::UHD4K.dimensions(), Ok((3840, 2160)));
+        assert_eq!(VideoPreset::Portrait1080.dimensions(), Ok((1080, 1920)));
+        assert_eq!(VideoPreset::Square1080.dimensions(), Ok((1080, 1080)));
+        assert!(matches!(VideoPreset::default(), VideoPreset::FHD1080));
+    }
+
+    #[test]
+    fn custom_preset_accepts_even_sizes_in_range() {
+        assert_eq!(VideoPreset::Custom { w: 2, h: 2 }.dimensions(), Ok((2, 2)));
+        assert_eq!(VideoPreset::Custom { w: 1280, h: 720 }.dimensions(), Ok((1280, 720)));
+        assert_eq!(VideoPreset::Custom { w: 7680, h: 4320 }.dimensions(), Ok((7680, 4320)));
+    }
+
+    #[test]
+    fn custom_preset_rejects_zero_and_one() {
+        for (w, h) in [(0, 720), (1280, 0), (1, 720), (1280, 1)] {
+            let err = VideoPreset::Custom { w, h }.dimensions().unwrap_err();
+            assert!(err.contains("between 2 and 7680"), "{}x{}: {}", w, h, err);
+        }
+    }
+
+    #[test]
+    fn custom_preset_rejects_odd_sizes() {
+        for (w, h) in [(1281, 720), (1280, 721)] {
+            let err = VideoPreset::Custom { w, h }.dimensions().unwrap_err();
+            assert!(err.contains("must be even"), "{}x{}: {}", w, h, err);
+        }
+    }
+
+    #[test]
+    fn custom_preset_rejects_sizes_over_the_limit() {
+        for (w, h) in [(7682, 720), (1280, 7682)] {
+            let err = VideoPreset::Custom { w, h }.dimensions().unwrap_err();
+            assert!(err.contains("between 2 and 7680"), "{}x{}: {}", w, h, err);
+        }
+    }
+
+    #[test]
+    fn unknown_preset_names_are_rejected() {
+        assert!(matches!(serde_json::from_str::<VideoPreset>("\"1080p\""), Ok(VideoPreset::FHD1080)));
+        let err = serde_json::from_str::<VideoPreset>("\"8k\"").unwrap_err();
+        assert!(err.to_string().contains("unknown variant `8k`"), "{}", err);
+    }
+
+    #[test]
+    fn output_format_parses_known_containers() {
+        assert_eq!(OutputFormat::parse(" MP4 "), Ok(OutputFormat::Mp4));
+        assert_eq!(OutputFormat::parse("webm"), Ok(OutputFormat::Webm));
+        assert_eq!(OutputFormat::parse("mov"), Ok(OutputFormat::Mov));
+        assert!(OutputFormat::parse("avi").unwrap_err().contains("Unsupported output format 'avi'"));
+    }
+
+    #[test]
+    fn mp4_codec_args_use_x264_and_aac() {
+        let args = OutputFormat::Mp4.codec_args(&VideoPreset::FHD1080, &EncodingOptions::default()).unwrap();
+        assert_eq!(args, [
+            "-c:v", "libx264", "-tune", "stillimage",
+            "-crf", "21", "-maxrate", "5000k", "-bufsize", "10000k",
+            "-pix_fmt", "yuv420p",
+            "-c:a", "aac", "-b:a", "192k",
+        ]);
+    }
+
+    #[test]
+    fn webm_codec_args_use_vp9_and_opus() {
+        let args = OutputFormat::Webm.codec_args(&VideoPreset::FHD1080, &EncodingOptions::default()).unwrap();
+        assert_eq!(args, [
+            "-c:v", "libvpx-vp9", "-crf", "32", "-b:v", "0",
+            "-deadline", "good", "-cpu-used", "4", "-row-mt", "1",
+            "-pix_fmt", "yuv420p", "-c:a", "libopus", "-b:a", "160k",
+        ]);
+    }
+
+    #[test]
+    fn mov_codec_args_use_prores_and_pcm() {
+        let args = OutputFormat::Mov.codec_args(&VideoPreset::FHD1080, &EncodingOptions::default()).unwrap();
+        assert_eq!(args, ["-c:v", "prores_ks", "-profile:v", "2", "-pix_fmt", "yuv422p10le", "-c:a", "pcm_s16le"]);
+    }
+
+    #[test]
+    fn frame_rate_defaults_to_30_and_is_bounded() {
+        assert_eq!(output_frame_rate(None, false), Ok(30));
+        assert_eq!(output_frame_rate(None, true), Ok(30));
+        assert_eq!(output_frame_rate(Some(1), false), Ok(1));
+        assert_eq!(output_frame_rate(Some(60), false), Ok(60));
+        assert_eq!(output_frame_rate(Some(120), false), Ok(120));
+        assert!(output_frame_rate(Some(0), false).is_err());
+        assert!(output_frame_rate(Some(121), false).is_err());
+    }
+
+    #[test]
+    fn animated_backgrounds_need_enough_frames() {
+        assert!(output_frame_rate(Some(MIN_ANIMATED_FPS - 1), true).is_err());
+        assert_eq!(output_frame_rate(Some(MIN_ANIMATED_FPS), true), Ok(MIN_ANIMATED_FPS));
+        assert_eq!(output_frame_rate(Some(2), false), Ok(2));
+    }
+
+    #[test]
+    fn frame_rate_is_applied_to_image_input_and_output() {
+        let (image_args, output_args) = frame_rate_args("24");
+        assert_eq!(image_args, ["-loop", "1", "-framerate", "24"]);
+        assert_eq!(output_args, ["-r", "24"]);
+    }
+
+    #[test]
+    fn parse_time_reads_all_ffmpeg_formats() {
+        assert_eq!(parse_time_to_seconds("01:02:03.50"), 3723.5);
+        assert_eq!(parse_time_to_seconds("02:03.25"), 123.25);
+        assert_eq!(parse_time_to_seconds("123.45"), 123.45);
+    }
+
+    #[test]
+    fn parse_time_treats_unknown_and_negative_as_zero() {
+        assert_eq!(parse_time_to_seconds("N/A"), 0.0);
+        assert_eq!(parse_time_to_seconds("n/a"), 0.0);
+        assert_eq!(parse_time_to_seconds("-00:00:00.02"), 0.0);
+        assert_eq!(parse_time_to_seconds("-1.5"), 0.0);
+        assert_eq!(parse_time_to_seconds(""), 0.0);
+        assert_eq!(parse_time_to_seconds("garbage"), 0.0);
+    }
+
+    // A clip of `source` playing from its start, with everything else left at the default
+    fn clip(source: &str, start_time: f64, duration: f64) -> TimelineClip {
+        TimelineClip {
+            source_file: source.to_string(),
+            start_time,
+            duration,
+            trim_start: 0.0,
+            trim_end: 0.0,
+            source_duration: None,
+            volume: None,
+            fade_in: 0.0,
+            fade_out: 0.0,
+            crossfade: None,
+            speed: None,
+            voice_enhance: false,
+            enhance: None,
+            reverse: false,
+        }
+    }
+
+    // `clips` on track 0 at full volume, centered
+    fn on_track(clips: Vec<TimelineClip>) -> Vec<ClipWithVolume> {
+        clips.into_iter()
+            .map(|clip| ClipWithVolume {
+                clip,
+                track_volume: 1.0,
+                track_pan: 0.0,
+                track_enhance: None,
+                track_denoise: None,
+                track_index: 0,
+            })
+            .collect()
+    }
+
+    // `count` five-second clips of one file with gaps between them
+    fn spaced_clips(count: usize) -> Vec<ClipWithVolume> {
+        on_track((0..count).map(|i| clip("a.mp3", i as f64 * 10.0, 5.0)).collect())
+    }
+
+    // Each clip's source added as an input, as the exports do
+    fn source_inputs(clips: &[ClipWithVolume]) -> InputRegistry {
+        let mut inputs = InputRegistry::default();
+        for source in unique_clip_sources(clips) {
+            inputs.add_source(&source);
+        }
+        inputs
+    }
+
+    fn mix(clips: &[ClipWithVolume]) -> String {
+        generate_filter_complex(clips, &source_inputs(clips), 1.0, 0.0, 0.0, 0.0, None).unwrap()
+    }
+
+    #[test]
+    fn clip_fades_are_relative_to_the_clip() {
+        let clips = on_track(vec![TimelineClip { fade_in: 1.0, fade_out: 2.0, ..clip("a.mp3", 3.0, 5.0) }]);
+        let filter = mix(&clips);
+        assert!(filter.contains("afade=t=in:st=0:d=1,afade=t=out:st=3:d=2"));
+        assert!(filter.contains("adelay=3000|3000[a0]"));
+    }
+
+    #[test]
+    fn clips_without_fades_get_no_afade() {
+        assert!(!mix(&spaced_clips(2)).contains("afade"));
+    }
+
+    #[test]
+    fn crossfade_plan_links_touching_clips_on_a_track() {
+        let clips = on_track(vec![
+            clip("a.mp3", 0.0, 5.0),
+            clip("b.mp3", 5.0, 5.0),
+            clip("c.mp3", 20.0, 5.0),
+        ]);
+        let plan = crossfade_plan(&clips, 1.0);
+        assert_eq!((plan[0].into, plan[0].out, plan[0].next), (0.0, 1.0, Some(1)));
+        assert_eq!((plan[1].into, plan[1].out, plan[1].next), (1.0, 0.0, None));
+        assert_eq!((plan[2].into, plan[2].out, plan[2].next), (0.0, 0.0, None));
+    }
+
+    #[test]
+    fn crossfade_plan_prefers_the_clip_value_and_caps_it_at_the_clip_length() {
+        let clips = on_track(vec![
+            clip("a.mp3", 0.0, 5.0),
+            TimelineClip { crossfade: Some(3.0), ..clip("b.mp3", 5.0, 2.0) },
+        ]);
+        let plan = crossfade_plan(&clips, 1.0);
+        assert_eq!((plan[0].out, plan[1].into), (2.0, 2.0));
+
+        // A clip can also turn off the default crossfade
+        let clips = on_track(vec![
+            clip("a.mp3", 0.0, 5.0),
+            TimelineClip { crossfade: Some(0.0), ..clip("b.mp3", 5.0, 5.0) },
+        ]);
+        assert_eq!(crossfade_plan(&clips, 1.0)[0].next, None);
+    }
+
+    #[test]
+    fn crossfade_plan_ignores_clips_on_other_tracks() {
+        let mut clips = on_track(vec![clip("a.mp3", 0.0, 5.0), clip("b.mp3", 5.0, 5.0)]);
+        clips[1].track_index = 1;
+        assert!(crossfade_plan(&clips, 1.0).iter().all(|link| link.next.is_none()));
+    }
+
+    #[test]
+    fn crossfaded_clips_are_joined_before_the_delay() {
+        let clips = on_track(vec![
+            TimelineClip { fade_out: 2.0, ..clip("a.mp3", 0.0, 5.0) },
+            TimelineClip { crossfade: Some(1.0), ..clip("b.mp3", 5.0, 5.0) },
+        ]);
+        let filter = mix(&clips);
+        assert!(filter.contains("[c0][c1]acrossfade=d=1:c1=qsin:c2=qsin[x1]"));
+        assert!(filter.contains("[x1]adelay=0|0[a0]"));
+        // The crossfaded edge is shaped by acrossfade, not the clip's own fade
+        assert!(!filter.contains("afade=t=out"));
+        // Without source audio to spare, the first clip is padded with silence
+        assert!(filter.contains("apad=pad_dur=1"));
+    }
+
+    #[test]
+    fn overlapping_fades_are_scaled_to_fit_the_clip() {
+        let clips = on_track(vec![TimelineClip { fade_in: 2.0, fade_out: 2.0, ..clip("a.mp3", 0.0, 2.0) }]);
+        assert!(mix(&clips).contains("afade=t=in:st=0:d=1,afade=t=out:st=1:d=1"));
+    }
+
+    #[test]
+    fn a_fade_longer_than_the_clip_is_clamped() {
+        let clips = on_track(vec![TimelineClip { fade_in: 10.0, ..clip("a.mp3", 0.0, 4.0) }]);
+        assert!(mix(&clips).contains("afade=t=in:st=0:d=4"));
+    }
+
+    // An empty directory of its own under the OS temp dir
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wavecast-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn next_available_path_keeps_a_free_name() {
+        let dir = test_dir("free-name");
+        assert_eq!(next_available_path(&dir, "output.mp4"), dir.join("output.mp4"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn next_available_path_numbers_past_existing_files() {
+        let dir = test_dir("collision");
+        std::fs::write(dir.join("output.mp4"), b"").unwrap();
+        assert_eq!(next_available_path(&dir, "output.mp4"), dir.join("output (1).mp4"));
+
+        std::fs::write(dir.join("output (1).mp4"), b"").unwrap();
+        std::fs::write(dir.join("output (2).mp4"), b"").unwrap();
+        assert_eq!(next_available_path(&dir, "output.mp4"), dir.join("output (3).mp4"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Background music as described by the older bg_music_path arguments
+    fn bg_music(volume: i32, fade_out: f64) -> BackgroundMusic {
+        BackgroundMusic::resolve(None, Some("bg.mp3".to_string()), None, Some(volume), Some(fade_out)).unwrap()
+    }
+
+    #[test]
+    fn bg_music_is_looped_and_cut_to_the_program_length() {
+        assert_eq!(
+            bg_music(30, 0.0).filter(&[1], 42.5, None),
+            "[1:a]aloop=loop=-1:size=2e+09,atrim=end=42.500,asetpts=PTS-STARTPTS,volume=0.3[bgmusic]"
+        );
+    }
+
+    #[test]
+    fn bg_music_fade_out_ends_with_the_program() {
+        assert!(bg_music(30, 5.0).filter(&[1], 60.0, None).ends_with("volume=0.3,afade=t=out:st=55.000:d=5.000[bgmusic]"));
+        // A fade longer than the program fades over all of it
+        assert!(bg_music(30, 5.0).filter(&[1], 3.0, None).ends_with("afade=t=out:st=0.000:d=3.000[bgmusic]"));
+    }
+
+    #[test]
+    fn unlooped_bg_music_is_trimmed_delayed_and_fades_where_it_ends() {
+        let music = BackgroundMusic {
+            start_offset: 2.0,
+            trim_start: 1.0,
+            trim_end: 4.0,
+            loop_enabled: false,
+            fade_out: 2.0,
+            ..bg_music(50, 0.0)
+        };
+        assert_eq!(
+            music.filter(&[1], 60.0, Some(20.0)),
+            "[1:a]atrim=start=1.000:end=16.000,asetpts=PTS-STARTPTS,atrim=end=58.000,asetpts=PTS-STARTPTS,volume=0.5,afade=t=out:st=13.000:d=2.000,adelay=2000|2000[bgmusic]"
+        );
+    }
+
+    #[test]
+    fn timeline_duration_is_the_latest_clip_end() {
+        assert_eq!(timeline_duration(&[]), 0.0);
+        let mut clips = on_track(vec![clip("a.mp3", 0.0, 30.0), clip("b.mp3", 5.0, 10.0)]);
+        clips[1].track_index = 1;
+        assert_eq!(timeline_duration(&clips), 30.0);
+        clips.push(on_track(vec![clip("c.mp3", 40.0, 2.5)]).remove(0));
+        assert_eq!(timeline_duration(&clips), 42.5);
+    }
+
+    #[test]
+    fn mix_pads_to_lead_in_plus_timeline_plus_tail() {
+        let clips = on_track(vec![clip("a.mp3", 0.0, 10.0)]);
+        let filter = generate_filter_complex(&clips, &source_inputs(&clips), 1.0, 0.0, 1.5, 2.25, None).unwrap();
+        assert!(filter.ends_with("apad=whole_dur=13.750[aout]"));
+        assert!(filter.contains("adelay=1500|1500[a0]"));
+    }
+
+    #[test]
+    fn migrates_a_0_9_project() {
+        let json = serde_json::json!({
+            "background_image": "/images/cover.png",
+            "tracks": [{
+                "id": "track-1",
+                "type": "audio",
+                "name": "Audio Files",
+                "clips": [{
+                    "id": "clip-1",
+                    "source_file": "/audio/episode.mp3",
+                    "track_id": "track-1",
+                    "start_time": 0.0,
+                    "duration": 30.0,
+                    "trim_start": 5.0,
+                    "trim_end": 2.5
+                }]
+            }]
+        });
+
+        let project = migrate_project(json).unwrap();
+        assert_eq!(project.version, "1.0.0");
+        assert_eq!(project.background_type, "image");
+        assert_eq!(project.background_style, "cover");
+        assert_eq!(project.video_title, "");
+
+        let track = &project.tracks[0];
+        assert_eq!(track.track_type, "audio");
+        assert_eq!(track.volume, 1.0);
+        assert!(!track.muted);
+
+        let clip = &track.clips[0];
+        assert_eq!(clip.source_name, "episode.mp3");
+        assert_eq!(clip.source_duration, 37.5);
+        assert_eq!(clip.volume, 1.0);
+        assert_eq!(clip.speed, 1.0);
+    }
+
+    #[test]
+    fn refuses_projects_from_a_newer_version() {
+        let err = migrate_project(serde_json::json!({ "version": "2.0.0" })).unwrap_err();
+        assert!(err.contains("newer version"));
+    }
+
+    #[test]
+    fn input_registry_numbers_inputs_in_order() {
+        let mut inputs = InputRegistry::default();
+        assert_eq!(inputs.add("/images/cover.png"), 0);
+        assert_eq!(inputs.add("/music/bed.mp3"), 1);
+        assert_eq!(inputs.add_source("/audio/a.mp3"), 2);
+        assert_eq!(inputs.add_source("/audio/b.mp3"), 3);
+        // A source used by several clips is only added once
+        assert_eq!(inputs.add_source("/audio/a.mp3"), 2);
+        assert_eq!(inputs.add_source("/audio/c.mp3"), 4);
+
+        assert_eq!(inputs.source_index("/audio/b.mp3"), Ok(3));
+        assert!(inputs.source_index("/music/bed.mp3").is_err());
+        assert_eq!(inputs.paths(), vec![
+            "/images/cover.png",
+            "/music/bed.mp3",
+            "/audio/a.mp3",
+            "/audio/b.mp3",
+            "/audio/c.mp3",
+        ]);
+    }
+
+    #[test]
+    fn invalid_input_serializes_field_and_reason() {
+        let json = serde_json::to_value(WavecastError::invalid("fps", "Invalid frame rate 0")).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "kind": "invalid_input",
+            "message": "Invalid frame rate 0",
+            "field": "fps",
+            "reason": "Invalid frame rate 0",
+        }));
+    }
+
+    #[test]
+    fn dialog_cancelled_serializes_kind_and_message_only() {
+        let json = serde_json::to_value(WavecastError::DialogCancelled).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "kind": "dialog_cancelled",
+            "message": "Cancelled",
+        }));
+    }
+
+    #[test]
+    fn string_errors_serialize_as_other() {
+        let err: WavecastError = "Failed to read project".to_string().into();
+        let json = serde_json::to_value(err).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "kind": "other",
+            "message": "Failed to read project",
+        }));
+    }
+
+    #[test]
+    fn parse_hex_color_reads_short_and_long_forms() {
+        assert_eq!(parse_hex_color("#fff"), Ok(vec![255, 255, 255]));
+        assert_eq!(parse_hex_color("#667eea"), Ok(vec![0x66, 0x7e, 0xea]));
+        assert_eq!(parse_hex_color("#ffffff80"), Ok(vec![255, 255, 255, 0x80]));
+    }
+
+    #[test]
+    fn parse_hex_color_rejects_bad_input() {
+        assert!(parse_hex_color("#xyz").unwrap_err().contains("Invalid color '#xyz'"));
+        assert!(parse_hex_color("#12345").is_err());
+    }
+
+    #[test]
+    fn temp_file_is_deleted_on_drop() {
+        let dir = test_dir("temp-drop");
+        let file = TempFile::new(dir.join("list.txt"));
+        std::fs::write(file.path(), b"list").unwrap();
+        let path = file.path().clone();
+        assert!(path.exists());
+        drop(file);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn temp_file_that_was_never_written_drops_quietly() {
+        let dir = test_dir("temp-missing");
+        drop(TempFile::new(dir.join("never-written.txt")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn temp_file_survives_after_keep() {
+        let file = TempFile::unique("test-keep", "txt");
+        std::fs::write(file.path(), b"list").unwrap();
+        let path = file.keep();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn temp_file_names_are_unique() {
+        let (a, b) = (TempFile::unique("test-name", "txt"), TempFile::unique("test-name", "txt"));
+        assert_ne!(a.path(), b.path());
+        assert!(a.path().starts_with(std::env::temp_dir()));
+        assert_eq!(a.path().extension().unwrap(), "txt");
+    }
+
+    #[test]
+    fn concat_list_entry_escapes_quotes() {
+        assert_eq!(concat_list_entry("/music/it's here.mp3"), r"file '/music/it'\''s here.mp3'");
+    }
+
+    #[test]
+    fn concat_list_entry_uses_forward_slashes() {
+        assert_eq!(concat_list_entry(r"C:\Users\me\o'brien.wav"), r"file 'C:/Users/me/o'\''brien.wav'");
+    }
+
+    #[test]
+    fn resampled_concat_filter_converts_every_input() {
+        assert_eq!(
+            resampled_concat_filter(2),
+            "[0:a:0]aresample=48000,aformat=sample_fmts=s16:channel_layouts=stereo[c0];\
+             [1:a:0]aresample=48000,aformat=sample_fmts=s16:channel_layouts=stereo[c1];\
+             [c0][c1]concat=n=2:v=0:a=1[joined]"
+        );
+    }
+
+    #[test]
+    fn preview_shows_the_export_command_without_running_anything() {
+        let dir = test_dir("preview");
+        for name in ["cover.png", "a.mp3"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let options: TimelineVideoOptions = serde_json::from_value(serde_json::json!({
+            "image_path": dir.join("cover.png"),
+            "timeline": { "tracks": [{ "clips": [clip(dir.join("a.mp3").to_str().unwrap(), 0.0, 5.0)], "volume": 1.0 }] },
+            "background_style": "cover",
+            "bg_music_volume": 30,
+            "main_audio_volume": 100,
+            "fps": 24,
+            "use_hardware_encoder": true,
+            "chapters": [{ "title": "Intro", "start_time": 0.0 }],
+        })).unwrap();
+        let args = preview_export_command(options).unwrap();
+
+        assert!(args.windows(4).any(|w| w == ["-loop", "1", "-framerate", "24"]), "{:?}", args);
+        assert!(args.windows(2).any(|w| w == ["-r", "24"]), "{:?}", args);
+        // The hardware encoder is shown, not probed
+        let encoder = hardware_encoder_candidates(false)[0];
+        assert!(args.windows(2).any(|w| w == ["-c:v", encoder]), "{:?}", args);
+        // Chapters get a placeholder path instead of a written file
+        let chapters = std::env::temp_dir().join("wavecast-chapters.txt");
+        assert!(args.contains(&chapters.to_string_lossy().to_string()));
+        assert!(!chapters.exists());
+        assert_eq!(args.last().unwrap(), &dir.join("output.mp4").to_string_lossy());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn preview_assumes_the_requested_encoder_if_it_fits_the_codec() {
+        let mut encoding = EncodingOptions {
+            codec: Some("h265".to_string()),
+            encoder: Some("hevc_nvenc".to_string()),
+            ..EncodingOptions::default()
+        };
+        encoding.assume_encoder(false);
+        assert_eq!(encoding.encoder_name(), "hevc_nvenc");
+
+        encoding.encoder = Some("h264_nvenc".to_string());
+        encoding.assume_encoder(true);
+        assert_eq!(encoding.encoder_name(), "libx265");
+
+        encoding.encoder = None;
+        encoding.assume_encoder(true);
+        assert_eq!(encoding.hardware_encoder, hardware_encoder_candidates(true).first().copied());
+    }
+
+    #[test]
+    fn source_range_without_source_duration_ignores_trim_end() {
+        let clip = TimelineClip { trim_start: 2.0, trim_end: 3.0, ..clip("a.mp3", 0.0, 5.0) };
+        assert_eq!(clip.source_range(), Ok((2.0, 7.0)));
+    }
+
+    #[test]
+    fn source_range_stops_at_trim_end_of_the_source() {
+        let clip = TimelineClip {
+            trim_start: 2.0,
+            trim_end: 1.0,
+            source_duration: Some(10.0),
+            ..clip("a.mp3", 0.0, 10.0)
+        };
+        assert_eq!(clip.source_range(), Ok((2.0, 9.0)));
+
+        // Room to spare: the duration decides
+        let clip = TimelineClip { duration: 4.0, ..clip };
+        assert_eq!(clip.source_range(), Ok((2.0, 6.0)));
+    }
+
+    #[test]
+    fn source_range_rejects_trims_past_the_source() {
+        let clip = TimelineClip {
+            trim_start: 8.0,
+            trim_end: 3.0,
+            source_duration: Some(10.0),
+            ..clip("a.mp3", 0.0, 5.0)
+        };
+        assert!(clip.source_range().unwrap_err().contains("no audio left"));
+    }
+
+    #[test]
+    fn source_range_rejects_zero_length_and_negative_trims() {
+        assert!(clip("a.mp3", 0.0, 0.0).source_range().unwrap_err().contains("no audio left"));
+        let clip = TimelineClip { trim_start: -1.0, ..clip("a.mp3", 0.0, 5.0) };
+        assert!(clip.source_range().unwrap_err().contains("negative trim"));
+    }
+
+    #[test]
+    fn x264_args_use_the_preset_crf_and_bitrate_cap() {
+        assert_eq!(
+            VideoPreset::FHD1080.x264_args(None, None).unwrap(),
+            ["-crf", "21", "-maxrate", "5000k", "-bufsize", "10000k"]
+        );
+        assert_eq!(
+            VideoPreset::HD720.x264_args(None, None).unwrap(),
+            ["-crf", "23", "-maxrate", "2500k", "-bufsize", "5000k"]
+        );
+        assert_eq!(
+            VideoPreset::UHD4K.x264_args(None, None).unwrap(),
+            ["-crf", "20", "-maxrate", "16000k", "-bufsize", "32000k"]
+        );
+    }
+
+    #[test]
+    fn x264_args_take_overrides() {
+        assert_eq!(
+            VideoPreset::FHD1080.x264_args(Some(28), Some(1200)).unwrap(),
+            ["-crf", "28", "-maxrate", "1200k", "-bufsize", "2400k"]
+        );
+    }
+
+    #[test]
+    fn video_args_pass_the_x264_preset_and_crf() {
+        let encoding = EncodingOptions {
+            crf: Some(19),
+            preset: Some("slow".to_string()),
+            ..EncodingOptions::default()
+        };
+        let args = encoding.video_args(&VideoPreset::FHD1080).unwrap();
+        let flag = |name: &str| args.iter().position(|arg| arg == name).map(|i| args[i + 1].as_str());
+        assert_eq!(flag("-c:v"), Some("libx264"));
+        assert_eq!(flag("-preset"), Some("slow"));
+        assert_eq!(flag("-crf"), Some("19"));
+    }
+
+    #[test]
+    fn mix_of_one_clip_has_no_groups() {
+        let filter = mix(&spaced_clips(1));
+        assert!(!filter.contains("[m0_"));
+        assert!(filter.ends_with("[a0]amix=inputs=1:duration=longest:normalize=0,volume=1,apad=whole_dur=5.000[aout]"));
+    }
+
+    #[test]
+    fn mix_of_sixteen_clips_is_one_amix() {
+        let filter = mix(&spaced_clips(16));
+        assert!(!filter.contains("[m0_"));
+        let labels: String = (0..16).map(|i| format!("[a{}]", i)).collect();
+        assert!(filter.ends_with(&format!("{}amix=inputs=16:duration=longest:normalize=0,volume=1,apad=whole_dur=155.000[aout]", labels)));
+    }
+
+    #[test]
+    fn mix_of_seventeen_clips_is_grouped() {
+        let filter = mix(&spaced_clips(17));
+        let first: String = (0..16).map(|i| format!("[a{}]", i)).collect();
+        assert!(filter.contains(&format!("{}amix=inputs=16:duration=longest:normalize=0[m0_0]", first)));
+        assert!(filter.contains("[a16]amix=inputs=1:duration=longest:normalize=0[m0_1]"));
+        assert!(!filter.contains("[m1_"));
+        assert!(filter.ends_with("[m0_0][m0_1]amix=inputs=2:duration=longest:normalize=0,volume=1,apad=whole_dur=165.000[aout]"));
+    }
+
+    #[test]
+    fn mix_of_a_hundred_clips_is_grouped_once() {
+        let filter = mix(&spaced_clips(100));
+        for group in 0..7 {
+            assert!(filter.contains(&format!("[m0_{}]", group)));
+        }
+        assert!(!filter.contains("[m0_7]"));
+        assert!(!filter.contains("[m1_"));
+        assert!(filter.contains("[a96][a97][a98][a99]amix=inputs=4:duration=longest:normalize=0[m0_6]"));
+        let groups: String = (0..7).map(|i| format!("[m0_{}]", i)).collect();
+        assert!(filter.ends_with(&format!("{}amix=inputs=7:duration=longest:normalize=0,volume=1,apad=whole_dur=995.000[aout]", groups)));
+    }
+
+    #[test]
+    fn merge_keeps_split_pieces_with_an_explicit_crossfade() {
+        let first = TimelineClip { source_duration: Some(20.0), ..clip("a.mp3", 0.0, 5.0) };
+        let second = TimelineClip { trim_start: 5.0, ..first.clone() };
+        let second = TimelineClip { start_time: 5.0, ..second };
+        assert_eq!(merge_contiguous_clips(&on_track(vec![first.clone(), second.clone()])).len(), 1);
+
+        let second = TimelineClip { crossfade: Some(1.0), ..second };
+        assert_eq!(merge_contiguous_clips(&on_track(vec![first, second])).len(), 2);
+    }
+
+    #[test]
+    fn every_amix_sums_without_normalizing() {
+        let filter = mix(&spaced_clips(20));
+        let amixes: Vec<&str> = filter.split(';').filter(|part| part.contains("amix=")).collect();
+        assert_eq!(amixes.len(), 3);
+        for part in amixes {
+            assert!(part.contains(":normalize=0"), "{}", part);
+        }
+        assert!(bg_music_mix_filter(None).contains("amix=inputs=2:duration=first:dropout_transition=2:normalize=0"));
+    }
+
+    #[test]
+    fn contain_filter_fits_and_pads_to_even_sizes() {
+        let expected = "scale=1080:1920:force_original_aspect_ratio=decrease:force_divisible_by=2,pad=1080:1920:(ow-iw)/2:(oh-ih)/2";
+        assert_eq!(build_video_filter("contain", 1080, 1920), expected);
+        assert_eq!(build_video_filter("center", 1080, 1920), expected);
+    }
+
+    #[test]
+    fn cover_filter_fills_and_crops() {
+        let expected = "scale=1920:1080:force_original_aspect_ratio=increase,crop=1920:1080";
+        assert_eq!(build_video_filter("cover", 1920, 1080), expected);
+        assert_eq!(build_video_filter("unknown", 1920, 1080), expected);
+        assert_eq!(build_video_filter("repeat", 1920, 1080), format!("tile=2x2,{}", expected));
+    }
+
+    #[test]
+    fn bg_music_playlist_is_concatenated_before_looping() {
+        let music = BackgroundMusic::resolve(None, None, Some(vec!["a.mp3".to_string(), "b.mp3".to_string()]), Some(100), None).unwrap();
+        assert_eq!(music.paths().collect::<Vec<_>>(), ["a.mp3", "b.mp3"]);
+        let filter = music.filter(&[1, 2], 30.0, None);
+        assert!(filter.starts_with(
+            "[1:a:0]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo[track0];\
+             [2:a:0]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo[track1];\
+             [track0][track1]concat=n=2:v=0:a=1"
+        ));
+        assert!(filter.contains("aloop=loop=-1:size=2e+09,atrim=end=30.000"));
+    }
+
+    #[test]
+    fn metadata_args_pair_each_field_and_skip_empty_ones() {
+        let metadata = VideoMetadata {
+            title: Some(" Episode \"1\" ".to_string()),
+            artist: Some("   ".to_string()),
+            album: Some("Season Two".to_string()),
+            date: None,
+            comment: Some("Café".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(metadata.args(), [
+            "-metadata", "title=Episode \"1\"",
+            "-metadata", "album=Season Two",
+            "-metadata", "comment=Café",
+        ]);
+        assert!(VideoMetadata::default().args().is_empty());
+    }
+
+    // An unmuted track of `clips` at full volume, centered
+    fn track(clips: Vec<TimelineClip>) -> TimelineTrack {
+        TimelineTrack {
+            clips,
+            volume: 1.0,
+            pan: 0.0,
+            muted: false,
+            solo: false,
+            voice_enhance: false,
+            enhance: None,
+            denoise_strength: None,
+        }
+    }
+
+    #[test]
+    fn touching_clips_dont_overlap() {
+        let track = track(vec![clip("a.mp3", 0.0, 5.0), clip("b.mp3", 5.0, 5.0)]);
+        assert!(find_overlaps(&track).is_empty());
+    }
+
+    #[test]
+    fn overlapping_clips_are_paired_earliest_first() {
+        // Listed out of order; the long first clip covers both of the others
+        let track = track(vec![
+            clip("b.mp3", 4.0, 2.0),
+            clip("a.mp3", 0.0, 10.0),
+            clip("c.mp3", 7.0, 5.0),
+        ]);
+        assert_eq!(find_overlaps(&track), vec![(1, 0), (1, 2)]);
+    }
+
+    #[test]
+    fn clips_on_different_tracks_dont_overlap() {
+        let timeline = TimelineData {
+            tracks: vec![
+                track(vec![clip("a.mp3", 0.0, 5.0)]),
+                track(vec![clip("b.mp3", 2.0, 5.0)]),
+            ],
+        };
+        assert!(describe_overlaps(&timeline).is_empty());
+    }
+
+    #[test]
+    fn overlaps_are_described_per_track() {
+        let mut muted = track(vec![clip("c.mp3", 0.0, 5.0), clip("d.mp3", 1.0, 5.0)]);
+        muted.muted = true;
+        let timeline = TimelineData {
+            tracks: vec![
+                track(vec![clip("a.mp3", 0.0, 5.0)]),
+                track(vec![clip("b.mp3", 0.0, 5.0), clip("b.mp3", 3.0, 5.0)]),
+                muted,
+            ],
+        };
+        assert_eq!(describe_overlaps(&timeline), vec!["Track 2: clips 1 and 2 overlap"]);
+    }
+
+    #[test]
+    fn atempo_chain_splits_fast_speeds() {
+        assert_eq!(atempo_chain(3.0), vec!["atempo=2.0", "atempo=1.5"]);
+        assert_eq!(atempo_chain(1.25), vec!["atempo=1.25"]);
+        assert!(atempo_chain(1.0).is_empty());
+    }
+
+    #[test]
+    fn atempo_chain_splits_slow_speeds() {
+        assert_eq!(atempo_chain(0.25), vec!["atempo=0.5", "atempo=0.5"]);
+        assert_eq!(atempo_chain(0.3), vec!["atempo=0.5", "atempo=0.6"]);
+    }
+
+    #[test]
+    fn sped_up_clip_is_shorter_on_the_timeline() {
+        let clips = on_track(vec![TimelineClip { speed: Some(3.0), ..clip("a.mp3", 0.0, 6.0) }]);
+        assert_eq!(timeline_duration(&clips), 2.0);
+        assert!(mix(&clips).contains("aresample=48000:async=1:first_pts=0,atempo=2.0,atempo=1.5"));
+    }
+
+    #[test]
+    fn reversed_clip_reverses_right_after_the_trim() {
+        let clips = on_track(vec![TimelineClip { reverse: true, ..clip("a.mp3", 0.0, 5.0) }]);
+        assert!(mix(&clips).starts_with("[0:a]atrim=start=0:end=5,asetpts=PTS-STARTPTS,areverse,aresample=48000"));
+        assert!(!mix(&spaced_clips(1)).contains("areverse"));
+    }
+
+    #[test]
+    fn reversing_a_long_clip_is_refused() {
+        let clips = on_track(vec![TimelineClip { reverse: true, ..clip("a.mp3", 0.0, MAX_REVERSE_SECONDS + 1.0) }]);
+        let err = generate_filter_complex(&clips, &source_inputs(&clips), 1.0, 0.0, 0.0, 0.0, None).unwrap_err();
+        assert!(err.contains("too long to reverse"));
+    }
+}
@@ -2,6 +2,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { open } from "@tauri-apps/plugin-dialog";
 import { convertFileSrc } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
+import { dirname, join } from "@tauri-apps/api/path";
 import WaveSurfer from 'wavesurfer.js';
 
 // Timeline-based data model
@@ -15,6 +16,8 @@ interface Clip {
   trimStart: number;      // Trim from source start (seconds)
   trimEnd: number;        // Trim from source end (seconds)
   sourceDuration: number; // Original file duration
+  volume?: number;        // Clip gain on top of the track volume (1.0 = unchanged)
+  speed?: number;         // Playback rate at the same pitch (1.0 = unchanged); duration stays in source seconds
 }
 
 interface Track {
@@ -23,6 +26,9 @@ interface Track {
   name: string;
   clips: Clip[];
   volume: number;
+  pan?: number; // -1 (left) to 1 (right), centered when unset
+  voiceEnhance?: boolean; // High-pass, de-click, de-ess and compress the track's clips on export
+  denoiseStrength?: number; // Noise reduction in dB applied to the track's clips on export
   muted: boolean;
   mode?: 'single' | 'random'; // Random mode for random file selection
   randomPool?: string[]; // Pool of file paths for random selection
@@ -34,6 +40,14 @@ interface Timeline {
   playheadPosition: number;
 }
 
+// Returned by upload_to_vimeo / resume_upload
+interface VimeoUploadResult {
+  link: string;
+  uri: string;
+  manage_url: string;
+  embed_html: string;
+}
+
 // Legacy interface for backward compatibility during migration
 interface AudioFile {
   path: string;
@@ -84,13 +98,37 @@ let selectedImage: string | null = null;
 let audioFiles: AudioFile[] = []; // Keep for backward compatibility during migration
 let backgroundStyle: string = "cover";
 let lastGeneratedVideo: string | null = null;
+let lastGeneratedThumbnail: string | null = null;
 let vimeoToken: string = "";
+let ffmpegPath: string = "";
+let ffmpegAllowDownload: boolean = true;
 let videoTitle: string = "Converted Video";
 let autoUpload: boolean = false;
 let bgMusicFile: string | null = null;
 let bgMusicVolume: number = 30;
+// Background music settings without controls of their own yet, kept so project files round-trip
+let bgMusicSettings: BackgroundMusicSettings = defaultBgMusicSettings();
 let mainAudioVolume: number = 100;
 
+// Background music placement, matching the backend's BackgroundMusic (seconds)
+interface BackgroundMusicSettings {
+  start_offset: number;
+  trim_start: number;
+  trim_end: number;
+  loop_enabled: boolean;
+  fade_in: number;
+  fade_out: number;
+}
+
+function defaultBgMusicSettings(): BackgroundMusicSettings {
+  return { start_offset: 0, trim_start: 0, trim_end: 0, loop_enabled: true, fade_in: 0, fade_out: 0 };
+}
+
+// The selected background music as the backend's BackgroundMusic, or null without one
+function backgroundMusic() {
+  return bgMusicFile ? { path: bgMusicFile, volume: bgMusicVolume, ...bgMusicSettings } : null;
+}
+
 // ID generation
 let nextClipId = 1;
 let nextTrackId = 1;
@@ -344,6 +382,86 @@ function updateToastError(toast: HTMLElement, message: string) {
   }, 5000);
 }
 
+// ============================================================================
+// Command Errors
+// ============================================================================
+
+// Shape of the WavecastError every command rejects with
+interface CommandError {
+  kind: string;
+  message: string;
+}
+
+function isCommandError(error: unknown): error is CommandError {
+  return !!error && typeof error === 'object' && 'kind' in error && 'message' in error;
+}
+
+function errorMessage(error: unknown): string {
+  return isCommandError(error) ? error.message : String(error);
+}
+
+// The user closed a save or open dialog, nothing to report
+function isDialogCancelled(error: unknown): boolean {
+  return isCommandError(error) && error.kind === 'dialog_cancelled';
+}
+
+// ============================================================================
+// Export Jobs
+// ============================================================================
+
+// How an export job ended, as sent with export-finished
+type ExportJobStatus =
+  | { state: 'done'; output_path: string }
+  | { state: 'failed'; error: CommandError }
+  | { state: 'cancelled' }
+  | { state: 'running' };
+
+// Job whose progress events the export screen shows
+let currentExportJob: number | null = null;
+
+// Start an export command and wait for its job to finish; resolves with the output path
+async function runExport(command: string, args: Record<string, unknown>): Promise<string> {
+  let jobId: number | null = null;
+  // export-finished can arrive before invoke returns the id for a job that fails fast
+  const finishedEarly = new Map<number, ExportJobStatus>();
+  let settle: (status: ExportJobStatus) => void = () => {};
+  const finished = new Promise<ExportJobStatus>((resolve) => { settle = resolve; });
+
+  const unlisten = await listen<{ job_id: number; status: ExportJobStatus }>('export-finished', (event) => {
+    const { job_id, status } = event.payload;
+    if (jobId === null) {
+      finishedEarly.set(job_id, status);
+    } else if (job_id === jobId) {
+      settle(status);
+    }
+  });
+
+  try {
+    jobId = await invoke<number>(command, args);
+    currentExportJob = jobId;
+    const early = finishedEarly.get(jobId);
+    if (early) settle(early);
+
+    const status = await finished;
+    await invoke('acknowledge_export', { id: jobId });
+    switch (status.state) {
+      case 'done':
+        return status.output_path;
+      case 'failed':
+        throw status.error;
+      default:
+        throw { kind: 'cancelled', message: 'Export cancelled' } as CommandError;
+    }
+  } finally {
+    unlisten();
+  }
+}
+
+// Progress events from an export other than the one on screen
+function isOtherExport(payload: { job_id?: number }): boolean {
+  return currentExportJob !== null && payload.job_id !== currentExportJob;
+}
+
 // ============================================================================
 // Timeline Utility Functions
 // ============================================================================
@@ -767,7 +885,8 @@ async function processAllVideos() {
               start_time: 0, // Start at 0 for individual video
               duration: audioClip.duration,
               trim_start: audioClip.trimStart,
-              trim_end: audioClip.trimEnd
+              trim_end: audioClip.trimEnd,
+              source_duration: audioClip.sourceDuration
             }],
             volume: 1.0
           }]
@@ -780,15 +899,21 @@ async function processAllVideos() {
         // Generate output filename from audio file name (remove audio extension)
         const outputFilename = audioClip.sourceName.replace(/\.(mp3|wav|m4a|ogg|flac|aac)$/i, '');
 
+        // Batch videos are written next to their audio instead of prompting for each one
+        const outputPath = await join(await dirname(audioClip.sourceFile), `${outputFilename}.mp4`);
+
         // Call Rust backend to create video
-        const result = await invoke<string>('convert_timeline_to_video', {
-          imagePath: imagePathToUse,
-          timeline: singleAudioTimeline,
-          backgroundStyle: backgroundStyle,
-          bgMusicPath: bgMusicPath,
-          bgMusicVolume: bgMusicVolume,
-          mainAudioVolume: mainAudioVolume,
-          outputFilename: outputFilename
+        const result = await runExport('convert_timeline_to_video', {
+          options: {
+            image_path: imagePathToUse,
+            timeline: singleAudioTimeline,
+            background_style: backgroundStyle,
+            bg_music_path: bgMusicPath,
+            bg_music_volume: bgMusicVolume,
+            main_audio_volume: mainAudioVolume,
+            output_filename: outputFilename,
+            output_path: outputPath
+          }
         });
 
         console.log(`✅ Video ${videoNum} created:`, result);
@@ -812,7 +937,7 @@ async function processAllVideos() {
           videoPath: '',
           metadata: metadata,
           status: 'failed',
-          error: String(error)
+          error: errorMessage(error)
         });
       }
     }
@@ -842,7 +967,7 @@ async function processAllVideos() {
 
   } catch (error) {
     console.error('Bulk processing error:', error);
-    showToast('Bulk processing failed: ' + error, 'error');
+    showToast('Bulk processing failed: ' + errorMessage(error), 'error');
 
     if (progressOverlay) {
       progressOverlay.style.display = 'none';
@@ -882,12 +1007,17 @@ function getTrackById(trackId: string): Track | null {
   return timeline.tracks.find(t => t.id === trackId) || null;
 }
 
+// Seconds a clip takes up on the timeline; a sped-up clip is shorter than the source it plays
+function clipLength(clip: Clip): number {
+  return clip.duration / (clip.speed ?? 1);
+}
+
 // Will be used in Phase 2+
 function _getTotalTimelineDuration(): number {
   let maxDuration = 0;
   for (const track of timeline.tracks) {
     for (const clip of track.clips) {
-      const clipEnd = clip.startTime + clip.duration;
+      const clipEnd = clip.startTime + clipLength(clip);
       if (clipEnd > maxDuration) {
         maxDuration = clipEnd;
       }
@@ -1241,7 +1371,7 @@ function getSnapPoints(trackId: string, excludeClipId: string): number[] {
     for (const clip of track.clips) {
       if (clip.id === excludeClipId) continue;
       snapPoints.push(clip.startTime);
-      snapPoints.push(clip.startTime + clip.duration);
+      snapPoints.push(clip.startTime + clipLength(clip));
     }
   }
 
@@ -1288,7 +1418,7 @@ function _getClipAtPosition(trackId: string, time: number, excludeClipId?: strin
 
   for (const clip of track.clips) {
     if (excludeClipId && clip.id === excludeClipId) continue;
-    if (time >= clip.startTime && time < clip.startTime + clip.duration) {
+    if (time >= clip.startTime && time < clip.startTime + clipLength(clip)) {
       return clip;
     }
   }
@@ -1345,10 +1475,10 @@ function updateDrag(mouseX: number) {
   const track = getTrackById(dragState.trackId);
   let hasOverlap = false;
   if (track) {
-    const draggedEnd = newStartTime + clip.duration;
+    const draggedEnd = newStartTime + clipLength(clip);
     for (const otherClip of track.clips) {
       if (otherClip.id === clip.id) continue;
-      const otherEnd = otherClip.startTime + otherClip.duration;
+      const otherEnd = otherClip.startTime + clipLength(otherClip);
       if (!(draggedEnd <= otherClip.startTime || newStartTime >= otherEnd)) {
         hasOverlap = true;
         break;
@@ -1459,28 +1589,29 @@ function updateTrim(mouseX: number) {
   const clip = _getClipById(trimState.clipId);
   if (!clip) return;
 
+  // The mouse moves in timeline seconds; trims and durations are in source seconds
+  const speed = clip.speed ?? 1;
   const deltaX = mouseX - trimState.startX;
-  const deltaTime = deltaX / pixelsPerSecond;
+  const deltaTime = deltaX / pixelsPerSecond * speed;
 
   const minDuration = 0.1; // Minimum clip duration
 
   if (trimState.edge === 'left') {
     // Trim from start
     let newTrimStart = trimState.originalTrimStart + deltaTime;
-    let newStartTime = trimState.originalStartTime + deltaTime;
     let newDuration = trimState.originalDuration - deltaTime;
 
     // Constraints
     if (newTrimStart < 0) {
       newTrimStart = 0;
-      newStartTime = trimState.originalStartTime - trimState.originalTrimStart;
       newDuration = trimState.originalDuration + trimState.originalTrimStart;
     }
     if (newDuration < minDuration) {
       newDuration = minDuration;
       newTrimStart = trimState.originalTrimStart + (trimState.originalDuration - minDuration);
-      newStartTime = trimState.originalStartTime + (trimState.originalDuration - minDuration);
     }
+    // The clip's start moves with its left edge
+    const newStartTime = trimState.originalStartTime + (newTrimStart - trimState.originalTrimStart) / speed;
     if (newTrimStart + newDuration + trimState.originalTrimEnd > clip.sourceDuration) {
       return; // Can't trim beyond source
     }
@@ -1515,7 +1646,7 @@ function updateTrim(mouseX: number) {
   const clipEl = document.querySelector(`[data-clip-id="${clip.id}"]`) as HTMLElement;
   if (clipEl) {
     clipEl.style.left = `${clip.startTime * pixelsPerSecond}px`;
-    clipEl.style.width = `${clip.duration * pixelsPerSecond}px`;
+    clipEl.style.width = `${clipLength(clip) * pixelsPerSecond}px`;
   }
 }
 
@@ -1559,7 +1690,7 @@ function updateSplitButtonState() {
     const { clip } = result;
     const relativeTime = timeline.playheadPosition - clip.startTime;
     // Enable if within valid split range (not too close to edges)
-    const canSplit = relativeTime > 0.1 && relativeTime < clip.duration - 0.1;
+    const canSplit = relativeTime > 0.1 && relativeTime < clipLength(clip) - 0.1;
     splitClipBtn.disabled = !canSplit;
   } else {
     splitClipBtn.disabled = true;
@@ -1657,7 +1788,7 @@ function preloadUpcomingAudio(currentTime: number) {
 
     for (const clip of track.clips) {
       const clipStart = clip.startTime;
-      const clipEnd = clip.startTime + clip.duration;
+      const clipEnd = clip.startTime + clipLength(clip);
 
       // Check if clip is upcoming (within preload window)
       if (clipStart >= currentTime && clipStart <= currentTime + PRELOAD_AHEAD) {
@@ -1686,7 +1817,7 @@ function updateTimelineAudio(currentTime: number) {
 
     for (const clip of track.clips) {
       const clipStart = clip.startTime;
-      const clipEnd = clip.startTime + clip.duration;
+      const clipEnd = clip.startTime + clipLength(clip);
 
       if (currentTime >= clipStart && currentTime < clipEnd) {
         newActiveClips.add(clip.id);
@@ -1706,8 +1837,11 @@ function updateTimelineAudio(currentTime: number) {
         const targetVolume = (track.volume / 100) * (mainAudioVolume / 100);
         audio.volume = targetVolume;
 
-        // Calculate where the audio should be playing
-        const relativeTime = currentTime - clipStart;
+        // Calculate where the audio should be playing; sped-up clips move through
+        // the source faster (the browser keeps the pitch)
+        const speed = clip.speed ?? 1;
+        audio.playbackRate = speed;
+        const relativeTime = (currentTime - clipStart) * speed;
 
         // ✅ FIX 4: Check if audio needs to play or resync
         const needsToPlay = audio.paused || audio.ended;
@@ -1918,6 +2052,14 @@ function handleKeyboardShortcuts(e: KeyboardEvent) {
       e.preventDefault();
       closeGapAtPlayhead();
       break;
+    case '[':
+      e.preventDefault();
+      changeClipSpeed(-1);
+      break;
+    case ']':
+      e.preventDefault();
+      changeClipSpeed(1);
+      break;
   }
 }
 
@@ -1960,7 +2102,7 @@ function findClipAtPlayhead(): { clip: Clip, track: Track } | null {
 
   for (const track of timeline.tracks) {
     for (const clip of track.clips) {
-      if (playheadTime >= clip.startTime && playheadTime < clip.startTime + clip.duration) {
+      if (playheadTime >= clip.startTime && playheadTime < clip.startTime + clipLength(clip)) {
         return { clip, track };
       }
     }
@@ -1975,10 +2117,10 @@ function splitClipAtPlayhead() {
   // Find ALL clips at playhead position across ALL tracks
   for (const track of timeline.tracks) {
     for (const clip of track.clips) {
-      if (splitTime >= clip.startTime && splitTime < clip.startTime + clip.duration) {
+      if (splitTime >= clip.startTime && splitTime < clip.startTime + clipLength(clip)) {
         const relativeTime = splitTime - clip.startTime;
         // Only split if not too close to edges
-        if (relativeTime > 0.1 && relativeTime < clip.duration - 0.1) {
+        if (relativeTime > 0.1 && relativeTime < clipLength(clip) - 0.1) {
           clipsToSplit.push({ clip, track });
         }
       }
@@ -1993,6 +2135,8 @@ function splitClipAtPlayhead() {
   // Split all clips at playhead
   for (const { clip, track } of clipsToSplit) {
     const relativeTime = splitTime - clip.startTime;
+    // Durations and trims are in source seconds
+    const sourceOffset = relativeTime * (clip.speed ?? 1);
 
     // Create two new clips from the original
     const clipA: Clip = {
@@ -2001,10 +2145,11 @@ function splitClipAtPlayhead() {
       sourceName: clip.sourceName,
       trackId: track.id,
       startTime: clip.startTime,
-      duration: relativeTime,
+      duration: sourceOffset,
       trimStart: clip.trimStart,
-      trimEnd: clip.trimEnd + (clip.duration - relativeTime), // Trim end of first part
-      sourceDuration: clip.sourceDuration
+      trimEnd: clip.trimEnd + (clip.duration - sourceOffset), // Trim end of first part
+      sourceDuration: clip.sourceDuration,
+      speed: clip.speed
     };
 
     const clipB: Clip = {
@@ -2013,10 +2158,11 @@ function splitClipAtPlayhead() {
       sourceName: clip.sourceName,
       trackId: track.id,
       startTime: splitTime,
-      duration: clip.duration - relativeTime,
-      trimStart: clip.trimStart + relativeTime, // Trim start of second part
+      duration: clip.duration - sourceOffset,
+      trimStart: clip.trimStart + sourceOffset, // Trim start of second part
       trimEnd: clip.trimEnd,
-      sourceDuration: clip.sourceDuration
+      sourceDuration: clip.sourceDuration,
+      speed: clip.speed
     };
 
     // Remove original clip
@@ -2059,6 +2205,40 @@ function deleteClipAtPlayhead() {
   renderTimeline();
 }
 
+const CLIP_SPEEDS = [0.5, 0.75, 1, 1.25, 1.5, 2];
+
+// Step the selected clip (or the one under the playhead) to the next slower or faster
+// speed. Later clips on the track move by the change in length so they stay back to back
+function changeClipSpeed(direction: 1 | -1) {
+  const result = getSelectedOrPlayheadClip();
+  if (!result) {
+    showToast('No clip selected or at playhead position', 'warning');
+    return;
+  }
+
+  const { clip, track } = result;
+  const current = clip.speed ?? 1;
+  const next = direction > 0
+    ? CLIP_SPEEDS.find(speed => speed > current + 1e-9)
+    : CLIP_SPEEDS.filter(speed => speed < current - 1e-9).pop();
+  if (next === undefined) {
+    showToast(`Clip speed is already at the ${direction > 0 ? 'maximum' : 'minimum'}`, 'warning');
+    return;
+  }
+
+  const oldEnd = clip.startTime + clipLength(clip);
+  clip.speed = next;
+  const shift = clip.startTime + clipLength(clip) - oldEnd;
+  for (const c of track.clips) {
+    if (c !== clip && c.startTime >= oldEnd - 0.001) {
+      c.startTime = Math.max(0, c.startTime + shift);
+    }
+  }
+
+  renderTimeline();
+  showToast(`Clip speed: ${clip.speed}x`, 'info', 1500);
+}
+
 function rippleDeleteClipAtPlayhead() {
   const result = getSelectedOrPlayheadClip();
   if (!result) {
@@ -2067,8 +2247,8 @@ function rippleDeleteClipAtPlayhead() {
   }
 
   const { clip, track } = result;
-  const gapSize = clip.duration;
-  const clipEndTime = clip.startTime + clip.duration;
+  const gapSize = clipLength(clip);
+  const clipEndTime = clip.startTime + clipLength(clip);
 
   // Remove the clip
   const index = track.clips.indexOf(clip);
@@ -2142,7 +2322,8 @@ function copyClipAtPlayhead() {
     duration: clip.duration,
     trimStart: clip.trimStart,
     trimEnd: clip.trimEnd,
-    sourceDuration: clip.sourceDuration
+    sourceDuration: clip.sourceDuration,
+    speed: clip.speed
   };
 
   showToast(`Copied clip: ${clip.sourceName}`, 'success', 2000);
@@ -2178,7 +2359,8 @@ function pasteClipAtPlayhead() {
     duration: clipboardClip.duration,
     trimStart: clipboardClip.trimStart,
     trimEnd: clipboardClip.trimEnd,
-    sourceDuration: clipboardClip.sourceDuration
+    sourceDuration: clipboardClip.sourceDuration,
+    speed: clipboardClip.speed
   };
 
   // Add the clip to the track
@@ -2223,8 +2405,8 @@ function duplicateClipAtPlayhead() {
   let newStartTime = timeline.playheadPosition;
 
   // If playhead is over the original clip, place duplicate right after it
-  if (newStartTime >= clip.startTime && newStartTime < clip.startTime + clip.duration) {
-    newStartTime = clip.startTime + clip.duration;
+  if (newStartTime >= clip.startTime && newStartTime < clip.startTime + clipLength(clip)) {
+    newStartTime = clip.startTime + clipLength(clip);
   }
 
   const newClip: Clip = {
@@ -2236,7 +2418,8 @@ function duplicateClipAtPlayhead() {
     duration: clip.duration,
     trimStart: clip.trimStart,
     trimEnd: clip.trimEnd,
-    sourceDuration: clip.sourceDuration
+    sourceDuration: clip.sourceDuration,
+    speed: clip.speed
   };
 
   // Add the clip to the track
@@ -2324,7 +2507,7 @@ function renderClip(clip: Clip): HTMLElement {
   const clipEl = document.createElement('div');
   clipEl.className = 'timeline-clip';
   clipEl.dataset.clipId = clip.id;
-  const clipWidth = clip.duration * pixelsPerSecond;
+  const clipWidth = clipLength(clip) * pixelsPerSecond;
   clipEl.style.left = `${clip.startTime * pixelsPerSecond}px`;
   clipEl.style.width = `${clipWidth}px`;
 
@@ -2350,7 +2533,9 @@ function renderClip(clip: Clip): HTMLElement {
 
   const clipDuration = document.createElement('div');
   clipDuration.className = 'timeline-clip-duration';
-  clipDuration.textContent = formatTime(clip.duration);
+  clipDuration.textContent = (clip.speed ?? 1) !== 1
+    ? `${formatTime(clipLength(clip))} · ${clip.speed}x`
+    : formatTime(clip.duration);
 
   clipInfo.appendChild(clipName);
   clipInfo.appendChild(clipDuration);
@@ -2377,7 +2562,7 @@ function renderClip(clip: Clip): HTMLElement {
 
   // Add hover split preview (but not if playhead is already over this clip)
   const playheadIsOverClip = timeline.playheadPosition >= clip.startTime &&
-                             timeline.playheadPosition < clip.startTime + clip.duration;
+                             timeline.playheadPosition < clip.startTime + clipLength(clip);
 
   if (!playheadIsOverClip) {
     const hoverSplitPreview = document.createElement('div');
@@ -2391,7 +2576,7 @@ function renderClip(clip: Clip): HTMLElement {
       const relativeTime = mouseX / pixelsPerSecond;
 
       // Check if within valid split range (not too close to edges)
-      if (relativeTime > 0.1 && relativeTime < clip.duration - 0.1) {
+      if (relativeTime > 0.1 && relativeTime < clipLength(clip) - 0.1) {
         hoverSplitPreview.style.left = `${mouseX}px`;
         hoverSplitPreview.style.opacity = '1';
       } else {
@@ -2424,7 +2609,7 @@ function renderClip(clip: Clip): HTMLElement {
 
   // Highlight if playhead is over this clip
   if (timeline.playheadPosition >= clip.startTime &&
-      timeline.playheadPosition < clip.startTime + clip.duration) {
+      timeline.playheadPosition < clip.startTime + clipLength(clip)) {
     clipEl.classList.add('clip-at-playhead');
 
     // Add split line indicator - position it at the playhead's absolute position
@@ -2763,7 +2948,7 @@ function updateClipHighlighting() {
   for (const track of timeline.tracks) {
     for (const clip of track.clips) {
       if (timeline.playheadPosition >= clip.startTime &&
-          timeline.playheadPosition < clip.startTime + clip.duration) {
+          timeline.playheadPosition < clip.startTime + clipLength(clip)) {
         const clipEl = document.querySelector(`[data-clip-id="${clip.id}"]`) as HTMLElement;
         if (clipEl) {
           clipEl.classList.add('clip-at-playhead');
@@ -3060,7 +3245,7 @@ async function selectAudioForTrack(trackId: string) {
         // Find the end position of the last clip in this track
         const lastClip = track.clips.length > 0
           ? track.clips.reduce((latest, clip) =>
-              (clip.startTime + clip.duration > latest.startTime + latest.duration) ? clip : latest
+              (clip.startTime + clipLength(clip) > latest.startTime + clipLength(latest)) ? clip : latest
             )
           : null;
 
@@ -3461,24 +3646,29 @@ async function selectBgMusic() {
     });
 
     if (selected) {
-      bgMusicFile = selected as string;
-      const fileName = bgMusicFile.split('/').pop() || bgMusicFile.split('\\').pop() || 'Unknown';
-
-      // Show the file info and hide placeholder
-      if (bgMusicPlaceholder && bgMusicInfo && bgMusicName && bgMusicOptions) {
-        bgMusicPlaceholder.style.display = 'none';
-        bgMusicInfo.style.display = 'flex';
-        bgMusicName.textContent = fileName;
-        bgMusicOptions.style.display = 'block';
-      }
+      showBgMusic(selected as string);
     }
   } catch (error) {
     console.error('Error selecting background music:', error);
   }
 }
 
+function showBgMusic(path: string) {
+  bgMusicFile = path;
+  const fileName = bgMusicFile.split('/').pop() || bgMusicFile.split('\\').pop() || 'Unknown';
+
+  // Show the file info and hide placeholder
+  if (bgMusicPlaceholder && bgMusicInfo && bgMusicName && bgMusicOptions) {
+    bgMusicPlaceholder.style.display = 'none';
+    bgMusicInfo.style.display = 'flex';
+    bgMusicName.textContent = fileName;
+    bgMusicOptions.style.display = 'block';
+  }
+}
+
 function removeBgMusic() {
   bgMusicFile = null;
+  bgMusicSettings = defaultBgMusicSettings();
 
   // Hide file info and show placeholder
   if (bgMusicPlaceholder && bgMusicInfo && bgMusicOptions) {
@@ -3531,10 +3721,17 @@ async function convertToVideo() {
   if (convertBtn) {
     convertBtn.disabled = true;
   }
+  lastGeneratedThumbnail = null;
 
   try {
     let result: string;
 
+    // Tag the output with the video title; the background image doubles as cover art
+    const metadata = {
+      title: videoTitle,
+      embed_cover: !!selectedImage
+    };
+
     // Handle solid color background - need to generate a temporary image
     let imagePathToUse = selectedImage;
     if (!selectedImage && bgTypeColor?.checked) {
@@ -3581,9 +3778,16 @@ async function convertToVideo() {
             start_time: clip.startTime,
             duration: clip.duration,
             trim_start: clip.trimStart,
-            trim_end: clip.trimEnd
+            trim_end: clip.trimEnd,
+            source_duration: clip.sourceDuration,
+            volume: clip.volume ?? 1.0,
+            speed: clip.speed ?? 1.0
           })),
-          volume: track.volume / 100.0
+          volume: track.volume / 100.0,
+          pan: track.pan ?? 0,
+          voice_enhance: track.voiceEnhance ?? false,
+          denoise_strength: track.denoiseStrength ?? null,
+          muted: track.muted
         }))
       };
 
@@ -3595,13 +3799,16 @@ async function convertToVideo() {
       });
 
       console.log('Invoking convert_timeline_to_video...');
-      result = await invoke<string>('convert_timeline_to_video', {
-        imagePath: imagePathToUse,
-        timeline: timelineData,
-        backgroundStyle: backgroundStyle,
-        bgMusicPath: bgMusicFile,
-        bgMusicVolume: bgMusicVolume,
-        mainAudioVolume: mainAudioVolume
+      result = await runExport('convert_timeline_to_video', {
+        options: {
+          image_path: imagePathToUse,
+          timeline: timelineData,
+          background_style: backgroundStyle,
+          bg_music: backgroundMusic(),
+          main_audio_volume: mainAudioVolume,
+          metadata: metadata,
+          export_thumbnail: true
+        }
       });
       console.log('Timeline conversion result:', result);
     } else {
@@ -3611,13 +3818,17 @@ async function convertToVideo() {
       console.log('Audio paths:', audioPaths);
 
       console.log('Invoking convert_to_video...');
-      result = await invoke<string>('convert_to_video', {
-        imagePath: imagePathToUse,
-        audioPaths: audioPaths,
-        backgroundStyle: backgroundStyle,
-        bgMusicPath: bgMusicFile,
-        bgMusicVolume: bgMusicVolume,
-        mainAudioVolume: mainAudioVolume
+      result = await runExport('convert_to_video', {
+        options: {
+          image_path: imagePathToUse,
+          audio_paths: audioPaths,
+          background_style: backgroundStyle,
+          bg_music_path: bgMusicFile,
+          bg_music_volume: bgMusicVolume,
+          main_audio_volume: mainAudioVolume,
+          metadata: metadata,
+          export_thumbnail: true
+        }
       });
       console.log('Legacy conversion result:', result);
     }
@@ -3669,10 +3880,13 @@ async function convertToVideo() {
       }
     }
 
-    if (progressSection && resultSection && resultMessage) {
+    if (isDialogCancelled(error)) {
+      // User dismissed the save dialog, nothing was exported
+      if (progressSection) progressSection.style.display = 'none';
+    } else if (progressSection && resultSection && resultMessage) {
       progressSection.style.display = 'none';
       resultSection.style.display = 'block';
-      resultMessage.textContent = `Error: ${error}`;
+      resultMessage.textContent = `Error: ${errorMessage(error)}`;
       console.log('UI updated to show error');
     }
   } finally {
@@ -3709,25 +3923,47 @@ async function uploadToVimeo() {
   }
 
   const loadingToast = showLoadingToast(`Uploading "${videoTitle}" to Vimeo...`);
+  const videoDescInput = document.getElementById('video-description') as HTMLTextAreaElement;
 
   try {
-    const result = await invoke<string>('upload_to_vimeo', {
+    // Fail fast on a bad token or a file bigger than the remaining quota
+    await invoke('check_vimeo_account', { accessToken: vimeoToken, videoPath: lastGeneratedVideo });
+
+    const result = await invoke<VimeoUploadResult>('upload_to_vimeo', {
       videoPath: lastGeneratedVideo,
       accessToken: vimeoToken,
-      title: videoTitle
+      title: videoTitle,
+      description: videoDescInput?.value || null,
+      thumbnailPath: lastGeneratedThumbnail
     });
 
-    updateToastSuccess(loadingToast, `Video uploaded successfully!`, result);
+    updateToastSuccess(loadingToast, `Video uploaded successfully!`, result.link);
 
     if (resultMessage) {
-      resultMessage.textContent = `Video uploaded to Vimeo successfully! ${result}`;
+      resultMessage.textContent = `Video uploaded to Vimeo, processing... ${result.link}`;
     }
+
+    // The link only plays once Vimeo has transcoded the video
+    invoke<{ status: string }>('wait_for_vimeo_transcode', { videoUri: result.uri, accessToken: vimeoToken })
+      .then(({ status }) => {
+        if (status !== 'complete') return;
+        if (resultMessage) {
+          resultMessage.textContent = `Video uploaded to Vimeo successfully! ${result.link}`;
+        }
+        showToast('Your video is ready to watch on Vimeo', 'success', 4000);
+      })
+      .catch((error) => {
+        console.error('Error waiting for Vimeo transcode:', error);
+        if (resultMessage) {
+          resultMessage.textContent = `Video uploaded to Vimeo: ${result.link} (${errorMessage(error)})`;
+        }
+      });
   } catch (error) {
     console.error('Error uploading to Vimeo:', error);
-    updateToastError(loadingToast, `Upload failed: ${error}`);
+    updateToastError(loadingToast, `Upload failed: ${errorMessage(error)}`);
 
     if (resultMessage) {
-      resultMessage.textContent = `Vimeo upload failed: ${error}`;
+      resultMessage.textContent = `Vimeo upload failed: ${errorMessage(error)}`;
     }
   } finally {
     if (uploadBtn) {
@@ -3887,13 +4123,13 @@ async function uploadSingleVideoToVimeo(videoPath: string, title: string, button
   const loadingToast = showLoadingToast(`Uploading "${title}" to Vimeo...`);
 
   try {
-    const result = await invoke<string>('upload_to_vimeo', {
+    const result = await invoke<VimeoUploadResult>('upload_to_vimeo', {
       videoPath: videoPath,
       accessToken: vimeoToken,
       title: title
     });
 
-    updateToastSuccess(loadingToast, `"${title}" uploaded successfully!`, result);
+    updateToastSuccess(loadingToast, `"${title}" uploaded successfully!`, result.link);
 
     button.innerHTML = `<svg width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
       <polyline points="20 6 9 17 4 12"/>
@@ -3901,7 +4137,7 @@ async function uploadSingleVideoToVimeo(videoPath: string, title: string, button
     button.style.color = '#51cf66';
   } catch (error) {
     console.error('Error uploading to Vimeo:', error);
-    updateToastError(loadingToast, `Failed to upload "${title}": ${error}`);
+    updateToastError(loadingToast, `Failed to upload "${title}": ${errorMessage(error)}`);
 
     button.innerHTML = originalText;
     button.disabled = false;
@@ -3967,13 +4203,13 @@ async function uploadAllVideosToVimeo() {
     }
 
     try {
-      const result = await invoke<string>('upload_to_vimeo', {
+      const result = await invoke<VimeoUploadResult>('upload_to_vimeo', {
         videoPath: video.videoPath,
         accessToken: vimeoToken,
         title: video.title
       });
       successCount++;
-      console.log(`✅ Uploaded: ${video.title} -> ${result}`);
+      console.log(`✅ Uploaded: ${video.title} -> ${result.link}`);
     } catch (error) {
       failCount++;
       console.error(`❌ Failed to upload ${video.title}:`, error);
@@ -4024,9 +4260,44 @@ function openSettings() {
     const tokenInput = document.querySelector('#vimeo-token') as HTMLInputElement;
 
     if (tokenInput) tokenInput.value = vimeoToken;
+
+    const ffmpegPathInput = document.querySelector('#ffmpeg-path') as HTMLInputElement;
+    if (ffmpegPathInput) ffmpegPathInput.value = ffmpegPath;
+    const allowDownloadCheck = document.querySelector('#ffmpeg-allow-download') as HTMLInputElement;
+    if (allowDownloadCheck) allowDownloadCheck.checked = ffmpegAllowDownload;
+    invoke<FfmpegStatus>('get_ffmpeg_status').then(showFfmpegStatus);
+  }
+}
+
+interface FfmpegStatus {
+  available: boolean;
+  version: string | null;
+  source: 'bundled' | 'system';
+  download_allowed: boolean;
+}
+
+function showFfmpegStatus(status: FfmpegStatus) {
+  const statusText = document.querySelector('#ffmpeg-status') as HTMLElement;
+  if (!statusText) return;
+  if (status.available) {
+    statusText.textContent = `FFmpeg ${status.version} (${status.source})`;
+  } else if (status.download_allowed) {
+    statusText.textContent = 'FFmpeg is not installed yet, it will be downloaded on first export';
+  } else {
+    statusText.textContent = 'FFmpeg was not found. Install it and enter its path above, or allow the download';
   }
 }
 
+// Use the FFmpeg from settings, or the bundled one when no path is set
+async function applyFfmpegPath(): Promise<FfmpegStatus> {
+  const status = await invoke<FfmpegStatus>('configure_ffmpeg', {
+    path: ffmpegPath || null,
+    allowDownload: ffmpegAllowDownload
+  });
+  showFfmpegStatus(status);
+  return status;
+}
+
 function closeSettings() {
   const modal = document.querySelector('#settings-modal') as HTMLElement;
   if (modal) {
@@ -4034,24 +4305,65 @@ function closeSettings() {
   }
 }
 
-function saveSettings() {
+async function saveSettings() {
   const tokenInput = document.querySelector('#vimeo-token') as HTMLInputElement;
 
   if (tokenInput) vimeoToken = tokenInput.value;
 
+  const ffmpegPathInput = document.querySelector('#ffmpeg-path') as HTMLInputElement;
+  const newFfmpegPath = ffmpegPathInput ? ffmpegPathInput.value.trim() : ffmpegPath;
+  const allowDownloadCheck = document.querySelector('#ffmpeg-allow-download') as HTMLInputElement;
+  const newAllowDownload = allowDownloadCheck ? allowDownloadCheck.checked : ffmpegAllowDownload;
+  if (newFfmpegPath !== ffmpegPath || newAllowDownload !== ffmpegAllowDownload) {
+    const previousPath = ffmpegPath;
+    const previousAllowDownload = ffmpegAllowDownload;
+    ffmpegPath = newFfmpegPath;
+    ffmpegAllowDownload = newAllowDownload;
+    try {
+      await applyFfmpegPath();
+    } catch (error) {
+      ffmpegPath = previousPath;
+      ffmpegAllowDownload = previousAllowDownload;
+      alert(`Could not use that FFmpeg: ${errorMessage(error)}`);
+      return;
+    }
+  }
+
   // Save to localStorage
   localStorage.setItem('vimeoToken', vimeoToken);
+  localStorage.setItem('ffmpegPath', ffmpegPath);
+  localStorage.setItem('ffmpegAllowDownload', String(ffmpegAllowDownload));
 
   closeSettings();
-  alert('Settings saved!');
+
+  // Check the token right away so a bad one doesn't surface mid-upload
+  if (vimeoToken) {
+    try {
+      const userName = await invoke<string>('verify_vimeo_token', { accessToken: vimeoToken });
+      alert(`Settings saved! Connected to Vimeo as ${userName}.`);
+    } catch (error) {
+      alert(`Settings saved, but the Vimeo token could not be verified: ${errorMessage(error)}`);
+    }
+  } else {
+    alert('Settings saved!');
+  }
 }
 
 function loadSettings() {
   const savedToken = localStorage.getItem('vimeoToken');
   const savedTitle = localStorage.getItem('videoTitle');
   const savedAutoUpload = localStorage.getItem('autoUpload');
+  const savedFfmpegPath = localStorage.getItem('ffmpegPath');
+  const savedFfmpegAllowDownload = localStorage.getItem('ffmpegAllowDownload');
 
   if (savedToken) vimeoToken = savedToken;
+  if (savedFfmpegAllowDownload) ffmpegAllowDownload = savedFfmpegAllowDownload === 'true';
+  if (savedFfmpegPath || !ffmpegAllowDownload) {
+    ffmpegPath = savedFfmpegPath || '';
+    applyFfmpegPath().catch((error) => {
+      showToast(`Configured FFmpeg is unavailable: ${errorMessage(error)}`, 'warning', 5000);
+    });
+  }
   if (savedTitle) videoTitle = savedTitle;
   if (savedAutoUpload) autoUpload = savedAutoUpload === 'true';
 }
@@ -4290,9 +4602,28 @@ window.addEventListener("DOMContentLoaded", () => {
 
   bgColorPicker?.addEventListener('input', updateVideoPreview);
 
+  // Show Vimeo upload progress on the upload button
+  listen('upload-progress', (event: any) => {
+    const progress = event.payload;
+    const uploadBtn = document.querySelector('#upload-vimeo-btn') as HTMLButtonElement;
+    if (uploadBtn && uploadBtn.disabled) {
+      uploadBtn.textContent = `Uploading... ${Math.round(progress.progress)}%`;
+    }
+  });
+
+  listen('upload-retrying', (event: any) => {
+    const retry = event.payload;
+    console.warn('Upload retrying:', retry.reason);
+    const uploadBtn = document.querySelector('#upload-vimeo-btn') as HTMLButtonElement;
+    if (uploadBtn && uploadBtn.disabled) {
+      uploadBtn.textContent = `Connection hiccup, retrying (${retry.attempt}/${retry.max_attempts})...`;
+    }
+  });
+
   // Listen for export progress events from Rust
   listen('export-progress', (event: any) => {
     const progress = event.payload;
+    if (isOtherExport(progress)) return;
 
     if (progressBar) {
       progressBar.style.width = `${progress.progress}%`;
@@ -4301,8 +4632,84 @@ window.addEventListener("DOMContentLoaded", () => {
       progressText.textContent = `${Math.round(progress.progress)}%`;
     }
     if (progressDetails) {
-      progressDetails.textContent = `Frame: ${progress.frame} | FPS: ${progress.fps.toFixed(1)} | Time: ${progress.time}`;
+      let details = `Frame: ${progress.frame} | FPS: ${progress.fps.toFixed(1)} | Time: ${progress.time}`;
+      if (progress.speed !== null) {
+        details += ` | ${progress.speed.toFixed(1)}x`;
+      }
+      if (progress.eta_seconds !== null) {
+        details += ` | About ${formatDuration(progress.eta_seconds)} remaining`;
+      }
+      progressDetails.textContent = details;
+    }
+  });
+
+  // First-run FFmpeg download, shown in settings and under the export progress bar
+  listen('ffmpeg-download-progress', (event: any) => {
+    const { downloaded_bytes, percent } = event.payload;
+    const text = percent !== null
+      ? `Downloading FFmpeg... ${Math.round(percent)}%`
+      : `Downloading FFmpeg... ${formatFileSize(downloaded_bytes)}`;
+    const statusText = document.querySelector('#ffmpeg-status') as HTMLElement;
+    if (statusText) {
+      statusText.textContent = text;
+    }
+    if (progressDetails) {
+      progressDetails.textContent = text;
+    }
+  });
+
+  listen('ffmpeg-ready', (event: any) => {
+    showFfmpegStatus(event.payload);
+  });
+
+  // Non-fatal export problems, like a requested encoder falling back to software
+  listen('export-warning', (event: any) => {
+    const warning = event.payload;
+    if (isOtherExport(warning)) return;
+    console.warn('Export warning:', warning.message);
+    showToast(warning.message, 'warning', 6000);
+  });
+
+  listen('mix-clipping-detected', (event: any) => {
+    const clipping = event.payload;
+    if (isOtherExport(clipping)) return;
+    console.warn('Mix clipping detected, true peak:', clipping.true_peak);
+    showToast(`The mix peaks at +${clipping.true_peak.toFixed(1)} dBFS and may sound distorted. Turn on the master limiter to prevent clipping.`, 'warning', 8000);
+  });
+
+  // Coarse export status, so the bar doesn't look frozen at 0% during setup
+  listen('export-stage', (event: any) => {
+    const stage = event.payload;
+    if (isOtherExport(stage)) return;
+    const labels: Record<string, string> = {
+      downloading_ffmpeg: 'Downloading FFmpeg (first run only)...',
+      preparing_inputs: 'Preparing inputs...',
+      concatenating_audio: `Joining audio files (${stage.current}/${stage.total})...`,
+      encoding: 'Encoding...',
+      finalizing: 'Finalizing...',
+      done: 'Done'
+    };
+    if (progressDetails && labels[stage.stage]) {
+      progressDetails.textContent = labels[stage.stage];
+    }
+  });
+
+  // Steps that run before the encode itself (joining audio files, measuring loudness)
+  const showStageProgress = (event: any) => {
+    const stage = event.payload;
+    if (isOtherExport(stage)) return;
+    if (progressDetails) {
+      const percent = stage.progress !== null ? ` ${Math.round(stage.progress)}%` : '';
+      progressDetails.textContent = `${stage.phase.charAt(0).toUpperCase()}${stage.phase.slice(1)}...${percent} (${stage.time})`;
     }
+  };
+  listen('concat-progress', showStageProgress);
+  listen('loudness-progress', showStageProgress);
+
+  // Poster frame saved next to the export, used as the Vimeo thumbnail
+  listen('export-thumbnail', (event: any) => {
+    lastGeneratedThumbnail = event.payload;
+    console.log('Thumbnail generated:', lastGeneratedThumbnail);
   });
 
   // Listen for menu events
@@ -4319,6 +4726,10 @@ window.addEventListener("DOMContentLoaded", () => {
     await exportProject();
   });
 
+  listen('export-project-bundle', async () => {
+    await exportProject(true);
+  });
+
   listen('import-project', async () => {
     await importProject();
   });
@@ -4383,7 +4794,7 @@ window.addEventListener("DOMContentLoaded", () => {
 // Project Export/Import Functions
 // ============================================================================
 
-async function exportProject() {
+async function exportProject(embedAssets = false) {
   try {
     // Gather all project data
     const videoTitleInput = document.getElementById('video-title') as HTMLInputElement;
@@ -4411,26 +4822,54 @@ async function exportProject() {
           duration: clip.duration,
           trim_start: clip.trimStart,
           trim_end: clip.trimEnd,
-          source_duration: clip.sourceDuration
+          source_duration: clip.sourceDuration,
+          volume: clip.volume ?? 1.0,
+          speed: clip.speed ?? 1.0
         })),
         volume: track.volume,
+        pan: track.pan ?? 0,
+        voice_enhance: track.voiceEnhance ?? false,
+        denoise_strength: track.denoiseStrength ?? null,
         muted: track.muted
       })),
       video_title: videoTitleInput?.value || 'Converted Video',
-      video_description: videoDescInput?.value || ''
+      video_description: videoDescInput?.value || '',
+      background_music: backgroundMusic()
     };
 
-    const result = await invoke<string>('export_project', { projectData });
+    const result = await invoke<string>('export_project', { projectData, embedAssets });
     showToast(`Project exported successfully to: ${result}`, 'success', 3000);
   } catch (error) {
     console.error('Error exporting project:', error);
-    showToast(`Failed to export project: ${error}`, 'error', 5000);
+    if (!isDialogCancelled(error)) {
+      showToast(`Failed to export project: ${errorMessage(error)}`, 'error', 5000);
+    }
   }
 }
 
 async function importProject() {
   try {
-    const projectData: any = await invoke('import_project');
+    const imported: any = await invoke('import_project');
+    let projectData: any = imported.project;
+
+    // Offer to relink audio files that were moved or deleted since the project was saved
+    if (imported.missing_files.length > 0) {
+      const relinkMissing: Record<string, string> = {};
+      for (const missingFile of imported.missing_files as string[]) {
+        if (!confirm(`Audio file not found:\n${missingFile}\n\nLocate it now?`)) continue;
+        const selected = await open({
+          multiple: false,
+          title: `Locate ${missingFile.split(/[\\/]/).pop()}`,
+          filters: [{ name: 'Audio', extensions: ['mp3', 'wav', 'm4a', 'ogg', 'flac', 'aac'] }]
+        });
+        if (typeof selected === 'string') relinkMissing[missingFile] = selected;
+      }
+      if (Object.keys(relinkMissing).length > 0) {
+        const relinked: any = await invoke('relink_project', { projectData, relinkMissing });
+        projectData = relinked.project;
+        imported.missing_files = relinked.missing_files;
+      }
+    }
 
     // Reset timeline
     timeline.tracks = [];
@@ -4463,6 +4902,17 @@ async function importProject() {
       if (colorPickerArea) colorPickerArea.style.display = 'block';
     }
 
+    // Load background music
+    removeBgMusic();
+    if (projectData.background_music) {
+      const { path, volume, ...settings } = projectData.background_music;
+      showBgMusic(path);
+      bgMusicVolume = volume;
+      bgMusicSettings = { ...defaultBgMusicSettings(), ...settings };
+      if (bgMusicVolumeSlider) bgMusicVolumeSlider.value = volume.toString();
+      if (volumeValue) volumeValue.textContent = volume.toString();
+    }
+
     // Load background style
     backgroundStyle = projectData.background_style || 'cover';
     if (backgroundStyleSelect) {
@@ -4489,6 +4939,9 @@ async function importProject() {
         name: trackData.name,
         clips: [],
         volume: trackData.volume,
+        pan: trackData.pan ?? 0,
+        voiceEnhance: trackData.voice_enhance ?? false,
+        denoiseStrength: trackData.denoise_strength ?? undefined,
         muted: trackData.muted
       };
 
@@ -4509,7 +4962,9 @@ async function importProject() {
           duration: clipData.duration,
           trimStart: clipData.trim_start,
           trimEnd: clipData.trim_end,
-          sourceDuration: clipData.source_duration
+          sourceDuration: clipData.source_duration,
+          volume: clipData.volume ?? 1.0,
+          speed: clipData.speed ?? 1.0
         };
 
         track.clips.push(clip);
@@ -4523,11 +4978,15 @@ async function importProject() {
     updateConvertButton();
     updateVideoPreview();
 
-    showToast('Project imported successfully!', 'success', 3000);
+    if (imported.missing_files.length > 0) {
+      showToast(`Project imported, but ${imported.missing_files.length} audio file(s) are missing`, 'warning', 5000);
+    } else {
+      showToast('Project imported successfully!', 'success', 3000);
+    }
   } catch (error) {
     console.error('Error importing project:', error);
-    if (error !== 'Open cancelled') {
-      showToast(`Failed to import project: ${error}`, 'error', 5000);
+    if (!isDialogCancelled(error)) {
+      showToast(`Failed to import project: ${errorMessage(error)}`, 'error', 5000);
     }
   }
 }
@@ -4807,7 +5266,7 @@ setTimeout(() => {
           let currentTime = 0;
           for (const clip of audioTrack.clips) {
             clip.startTime = currentTime;
-            currentTime += clip.duration;
+            currentTime += clipLength(clip);
           }
 
           renderTimeline();